@@ -0,0 +1,180 @@
+use ferra::{
+    display::{camera::Camera, deferred::DeferredRenderer, controller::{CameraController, OrbitController}, light::{blinn_phong_program, AmbientLight, Attenuation, BlinnPhong, Light, DEFAULT_MAX_LIGHTS}, pbr::{pbr_program, Pbr}, shadow::ShadowSettings, material::Material, mesh::{Indices, Mesh, Topology}, primitives, texture::Texture, vertex::Vertex},
+    ecs::{entity::Entity, system::Schedule, systems::{LightingSystem, MainCamera, RenderPath, RenderSystem, TransformSystem, ViewportSystem}, world::World},
+    scene::{graph::Renderable, transform::Transform},
+    input::{actions::InputMap, gamepad::GamepadEvent, state::CursorMode},
+    config::DEFAULT_CONFIG_PATH,
+    App, AppContext, EngineConfig, Result,
+};
+use glfw::WindowEvent;
+use nalgebra::{Point3, Vector3, Vector4};
+use std::rc::Rc;
+
+const VERTEX_COUNT: usize = 4;
+const VERTICES: [Vertex; VERTEX_COUNT] = [
+    Vertex { position: [ 0.5, 0.5, 0.0 ], color: [ 1.0, 0.0, 0.0 ], texture: [1.0, 1.0] },
+    Vertex { position: [ 0.5, -0.5, 0.0 ], color: [ 0.0, 1.0, 0.0 ], texture: [1.0, 0.0] },
+    Vertex { position: [ -0.5, -0.5, 0.0 ], color: [ 0.0, 0.0, 1.0 ], texture: [0.0, 0.0] },
+    Vertex { position: [ -0.5, 0.5, 0.0 ], color: [ 1.0, 1.0, 0.0 ], texture: [0.0, 1.0] },
+];
+
+const INDICES: [u32; 6] = [
+    0, 1, 3,
+    1, 2, 3,
+];
+
+struct Demo {
+    world: World,
+    schedule: Schedule,
+    main_camera: Entity,
+    controller: OrbitController,
+}
+
+impl App for Demo {
+    fn init(ctx: &mut AppContext) -> Result<Self> {
+        ctx.input.set_map(InputMap::load(ctx.asset_path("input/demo.ron"))?);
+
+        let gl = &ctx.gl;
+
+        let quad = Rc::new(Mesh::new(gl, Vertex::layout(), &VERTICES, Some(Indices::U32(&INDICES)), Topology::Triangles));
+
+        let material = Rc::new(Material::load(gl, ctx.asset_path("materials/container.ron"))?);
+
+        let cube = Rc::new(primitives::cube(1.0).upload(gl));
+        let lit = Rc::new(blinn_phong_program(gl, DEFAULT_MAX_LIGHTS)?);
+        let lit_material = BlinnPhong {
+            diffuse_map: Some(Rc::new(Texture::load_file(gl, &ctx.asset_path("textures/container.jpg").to_string_lossy())?)),
+            ..Default::default()
+        }
+        .material("lit_container", lit);
+        lit_material.validate()?;
+
+        let sphere = Rc::new(primitives::uv_sphere(0.5, 32, 16).upload(gl));
+        let pbr = Rc::new(pbr_program(gl, DEFAULT_MAX_LIGHTS, false)?);
+        let deferred = DeferredRenderer::new(gl)?;
+        let pbr_material = Pbr {
+            base_color: Vector4::new(1.0, 0.77, 0.34, 1.0),
+            metallic: 0.8,
+            roughness: 0.3,
+            ..Default::default()
+        }
+        .material("gold", pbr, None)
+        .with_deferred(deferred.gbuffer_program());
+        pbr_material.validate()?;
+
+        let (width, height) = ctx.framebuffer_size();
+        let mut world = World::new();
+        let main_camera = world.spawn((
+            Camera::perspective(45f32.to_radians(), width as f32 / height as f32, 0.1, 100.0),
+            MainCamera,
+        ));
+
+        world.spawn((
+            Transform::from_translation(Vector3::new(-1.0, 0.0, 0.0)),
+            Renderable {
+                mesh: quad,
+                material,
+            },
+        ));
+
+        world.spawn((
+            Transform::from_translation(Vector3::new(1.0, 0.0, 0.0)),
+            Renderable {
+                mesh: cube,
+                material: Rc::new(lit_material),
+            },
+        ));
+
+        world.spawn((
+            Transform::from_translation(Vector3::new(0.0, 0.0, -1.5)),
+            Renderable {
+                mesh: sphere,
+                material: Rc::new(pbr_material),
+            },
+        ));
+
+        world.spawn((
+            Transform::from_translation(Vector3::new(2.0, 1.5, 1.5)),
+            Light::point(Vector3::new(1.0, 0.9, 0.8), 2.0, Attenuation::range(10.0)),
+        ));
+
+        world.spawn((
+            Transform::from_translation(Vector3::new(-1.0, 2.0, 1.0)).look_at(&Point3::origin(), &Vector3::y()),
+            Light::directional(Vector3::new(0.6, 0.7, 1.0), 0.3).with_shadows(),
+        ));
+
+        world.insert_resource(AmbientLight::default());
+        world.insert_resource(ShadowSettings::default());
+        world.insert_resource(RenderPath::Deferred);
+        world.add_event::<WindowEvent>();
+        world.add_event::<GamepadEvent>();
+
+        let mut schedule = Schedule::new();
+        let lighting = LightingSystem::new(gl.clone(), DEFAULT_MAX_LIGHTS)?;
+        let shadow_debug = lighting.shadow_debug();
+        schedule
+            .add_system(ViewportSystem::new())
+            .add_system(TransformSystem)
+            .add_system(lighting)
+            .add_system(RenderSystem::new(gl.clone()).with_deferred(deferred))
+            .add_system(shadow_debug);
+
+        Ok(Self {
+            world,
+            schedule,
+            main_camera,
+            controller: OrbitController::new(Point3::origin(), 2.0),
+        })
+    }
+
+    fn update(&mut self, ctx: &mut AppContext, dt: f32) -> Result<()> {
+        self.world.insert_resource(ctx.time);
+        self.world.insert_resource(ctx.input.clone());
+        for event in ctx.input.gamepads().events() {
+            self.world.send_event(event.clone());
+        }
+
+        if ctx.input.action_just_pressed("quit") {
+            ctx.exit();
+        }
+        if ctx.input.action_just_pressed("toggle_fullscreen") {
+            ctx.toggle_fullscreen();
+        }
+        if ctx.input.action_just_pressed("toggle_vsync") {
+            let vsync = !ctx.window.vsync();
+            ctx.set_vsync(vsync);
+        }
+        if ctx.input.action_just_pressed("toggle_cursor") {
+            let mode = match ctx.input.cursor_mode() {
+                CursorMode::Locked => CursorMode::Normal,
+                _ => CursorMode::Locked,
+            };
+            ctx.set_cursor_mode(mode);
+        }
+        if ctx.time.frame().is_multiple_of(60) {
+            ctx.window.set_title(&format!("Hello World ({:.0} fps)", ctx.time.fps()));
+        }
+
+        if let Some(mut camera) = self.world.get_mut::<Camera>(self.main_camera) {
+            self.controller.update(&ctx.input, &mut camera, dt);
+        }
+        Ok(())
+    }
+
+    fn render(&mut self, _ctx: &mut AppContext) -> Result<()> {
+        self.schedule.run(&mut self.world)?;
+        self.world.update_events();
+        Ok(())
+    }
+
+    fn event(&mut self, _ctx: &mut AppContext, event: &WindowEvent) -> Result<()> {
+        self.world.send_event(event.clone());
+        Ok(())
+    }
+}
+
+/// `--record <file>` saves the session's input,
+/// `--replay <file> [--headless]` plays it back.
+fn main() -> Result<()> {
+    ferra::run::<Demo>(EngineConfig::resolve(DEFAULT_CONFIG_PATH)?)
+}
@@ -0,0 +1,68 @@
+#version 330 core
+// MAX_LIGHTS is defined, and the light and shadow functions
+// inserted, by the engine when compiling
+
+layout (std140) uniform Lights {
+    vec4 ambient;     // rgb color scaled by intensity
+    vec4 cameraPosition;
+    ivec4 lightCount;
+    Light lights[MAX_LIGHTS];
+};
+
+uniform vec3 diffuse_color;
+uniform vec3 specular_color;
+uniform float shininess;
+
+uniform bool use_diffuse_map;
+uniform bool use_specular_map;
+uniform bool use_normal_map;
+
+uniform sampler2D diffuse_map;
+uniform sampler2D specular_map;
+uniform sampler2D normal_map;
+
+in vec3 WorldPos;
+in vec2 TexCoord;
+in mat3 TBN;
+
+out vec4 FragColor;
+
+void main()
+{
+    vec3 albedo = diffuse_color;
+    float alpha = 1.0;
+    if (use_diffuse_map) {
+        vec4 texel = texture(diffuse_map, TexCoord);
+        albedo *= texel.rgb;
+        alpha = texel.a;
+    }
+
+    vec3 specular = specular_color;
+    if (use_specular_map) {
+        specular *= texture(specular_map, TexCoord).rgb;
+    }
+
+    vec3 N = normalize(TBN[2]);
+    if (use_normal_map) {
+        vec3 tangentNormal = texture(normal_map, TexCoord).xyz * 2.0 - 1.0;
+        N = normalize(TBN * tangentNormal);
+    }
+
+    vec3 V = normalize(cameraPosition.xyz - WorldPos);
+    vec3 color = ambient.rgb * albedo;
+
+    for (int i = 0; i < min(lightCount.x, MAX_LIGHTS); ++i) {
+        vec3 L;
+        float attenuation = lightAttenuation(lights[i], WorldPos, L);
+
+        vec3 H = normalize(L + V);
+        float diffuse = max(dot(N, L), 0.0);
+        float highlight = diffuse > 0.0 ? pow(max(dot(N, H), 0.0), shininess) : 0.0;
+
+        attenuation *= shadowFactor(lights[i].shadow, WorldPos, N, L);
+        vec3 radiance = lightRadiance(lights[i]) * attenuation;
+        color += radiance * (diffuse * albedo + highlight * specular);
+    }
+
+    FragColor = vec4(color, alpha);
+}
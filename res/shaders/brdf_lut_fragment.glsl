@@ -0,0 +1,56 @@
+#version 330 core
+// the ibl sampling functions are inserted here by the engine
+
+uniform uint sample_count;
+
+in vec2 TexCoord;
+
+out vec2 FragColor;
+
+float geometrySchlickGGX(float NdotV, float roughness)
+{
+    // the ibl variant of k
+    float k = (roughness * roughness) / 2.0;
+    return NdotV / (NdotV * (1.0 - k) + k);
+}
+
+float geometrySmith(float NdotV, float NdotL, float roughness)
+{
+    return geometrySchlickGGX(NdotV, roughness) * geometrySchlickGGX(NdotL, roughness);
+}
+
+// the scale and bias applied to F0 by the split sum approximation
+vec2 integrateBRDF(float NdotV, float roughness)
+{
+    vec3 V = vec3(sqrt(1.0 - NdotV * NdotV), 0.0, NdotV);
+    vec3 N = vec3(0.0, 0.0, 1.0);
+
+    float A = 0.0;
+    float B = 0.0;
+
+    for (uint i = 0u; i < sample_count; ++i) {
+        vec2 Xi = hammersley(i, sample_count);
+        vec3 H = importanceSampleGGX(Xi, N, roughness);
+        vec3 L = normalize(2.0 * dot(V, H) * H - V);
+
+        float NdotL = max(L.z, 0.0);
+        float NdotH = max(H.z, 0.0);
+        float VdotH = max(dot(V, H), 0.0);
+
+        if (NdotL > 0.0) {
+            float G = geometrySmith(NdotV, NdotL, roughness);
+            float G_Vis = (G * VdotH) / (NdotH * NdotV);
+            float Fc = pow(1.0 - VdotH, 5.0);
+
+            A += (1.0 - Fc) * G_Vis;
+            B += Fc * G_Vis;
+        }
+    }
+
+    return vec2(A, B) / float(sample_count);
+}
+
+void main()
+{
+    FragColor = integrateBRDF(max(TexCoord.x, 1e-4), TexCoord.y);
+}
@@ -0,0 +1,20 @@
+#version 330 core
+// the g-buffer functions are inserted by the engine when compiling
+
+// rgb color scaled by intensity
+uniform vec3 ambient;
+
+out vec4 FragColor;
+
+void main()
+{
+    vec2 uv = gbufferCoord();
+    if (texture(gbuffer_depth, uv).r >= 1.0) {
+        discard;
+    }
+
+    vec4 albedo = texture(gbuffer_albedo, uv);
+    vec3 emission = texture(gbuffer_emission, uv).rgb;
+
+    FragColor = vec4(ambient * albedo.rgb * albedo.a + emission, 1.0);
+}
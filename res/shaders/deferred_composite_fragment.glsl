@@ -0,0 +1,23 @@
+#version 330 core
+// the g-buffer functions are inserted by the engine when compiling
+
+// the accumulated hdr radiance
+uniform sampler2D lighting;
+
+out vec4 FragColor;
+
+void main()
+{
+    vec2 uv = gbufferCoord();
+    if (texture(gbuffer_depth, uv).r >= 1.0) {
+        discard;
+    }
+
+    vec3 color = texture(lighting, uv).rgb;
+
+    // reinhard tone mapping and gamma correction, as in the forward pbr shader
+    color = color / (color + vec3(1.0));
+    color = pow(color, vec3(1.0 / 2.2));
+
+    FragColor = vec4(color, 1.0);
+}
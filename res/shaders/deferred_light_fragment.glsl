@@ -0,0 +1,36 @@
+#version 330 core
+// the light, shadow, brdf and g-buffer functions are inserted by the engine when compiling
+
+// the light as laid out in the Lights block
+uniform vec4 light_data[5];
+uniform vec3 camera_position;
+
+out vec4 FragColor;
+
+void main()
+{
+    vec2 uv = gbufferCoord();
+    float depth = texture(gbuffer_depth, uv).r;
+    if (depth >= 1.0) {
+        discard;
+    }
+
+    Light light = Light(light_data[0], light_data[1], light_data[2], light_data[3], light_data[4]);
+
+    vec3 worldPos = gbufferPosition(uv, depth);
+    vec3 albedo = texture(gbuffer_albedo, uv).rgb;
+    vec3 N = normalize(texture(gbuffer_normal, uv).xyz);
+    vec2 material = texture(gbuffer_material, uv).rg;
+
+    vec3 V = normalize(camera_position - worldPos);
+    vec3 L;
+    float attenuation = lightAttenuation(light, worldPos, L);
+    if (dot(N, L) <= 0.0) {
+        discard;
+    }
+
+    attenuation *= shadowFactor(light.shadow, worldPos, N, L);
+    vec3 radiance = lightRadiance(light) * attenuation;
+
+    FragColor = vec4(cookTorrance(N, V, L, albedo, material.r, material.g) * radiance, 1.0);
+}
@@ -0,0 +1,17 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+
+// directional lights cover the whole screen with a [-1, 1] quad,
+// the others draw a bounding volume
+uniform bool fullscreen;
+uniform mat4 model;
+uniform mat4 view_projection;
+
+void main()
+{
+    if (fullscreen) {
+        gl_Position = vec4(aPos.xy, 0.0, 1.0);
+    } else {
+        gl_Position = view_projection * model * vec4(aPos, 1.0);
+    }
+}
@@ -0,0 +1,20 @@
+#version 330 core
+uniform sampler2D equirectangular_map;
+
+in vec3 LocalPos;
+
+out vec4 FragColor;
+
+const vec2 invAtan = vec2(0.1591, 0.3183);
+
+vec2 sampleSphericalMap(vec3 v)
+{
+    vec2 uv = vec2(atan(v.z, v.x), asin(v.y));
+    return uv * invAtan + 0.5;
+}
+
+void main()
+{
+    vec2 uv = sampleSphericalMap(normalize(LocalPos));
+    FragColor = vec4(texture(equirectangular_map, uv).rgb, 1.0);
+}
@@ -0,0 +1,12 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 2) in vec2 aTexCoord;
+
+out vec2 TexCoord;
+
+// draws a quad spanning [-1, 1] in x and y over the whole viewport
+void main()
+{
+    TexCoord = aTexCoord;
+    gl_Position = vec4(aPos.xy, 0.0, 1.0);
+}
@@ -0,0 +1,21 @@
+#version 330 core
+// the surface functions are inserted by the engine when compiling
+
+in vec3 WorldPos;
+in vec2 TexCoord;
+in mat3 TBN;
+
+layout (location = 0) out vec4 Albedo;   // rgb linear albedo, a ambient occlusion
+layout (location = 1) out vec4 Normal;   // xyz world space normal
+layout (location = 2) out vec4 Material; // r metalness, g roughness
+layout (location = 3) out vec4 Emission; // rgb emitted radiance
+
+void main()
+{
+    Surface surface = sampleSurface(TexCoord, TBN);
+
+    Albedo = vec4(surface.albedo.rgb, surface.ao);
+    Normal = vec4(surface.N, 1.0);
+    Material = vec4(surface.metalness, surface.roughness, 0.0, 1.0);
+    Emission = vec4(surface.emission, 1.0);
+}
@@ -0,0 +1,24 @@
+// inserted by the engine into the deferred lighting shaders
+
+uniform sampler2D gbuffer_albedo;
+uniform sampler2D gbuffer_normal;
+uniform sampler2D gbuffer_material;
+uniform sampler2D gbuffer_emission;
+uniform sampler2D gbuffer_depth;
+
+uniform mat4 inverse_view_projection;
+uniform vec2 screen_size;
+
+// the g-buffer coordinates of the current fragment
+vec2 gbufferCoord()
+{
+    return gl_FragCoord.xy / screen_size;
+}
+
+// the world position of the surface at uv, reconstructed from its depth
+vec3 gbufferPosition(vec2 uv, float depth)
+{
+    vec4 ndc = vec4(vec3(uv, depth) * 2.0 - 1.0, 1.0);
+    vec4 world = inverse_view_projection * ndc;
+    return world.xyz / world.w;
+}
@@ -0,0 +1,47 @@
+// inserted by the engine into the lit shaders
+
+#define LIGHT_DIRECTIONAL 0
+#define LIGHT_POINT 1
+#define LIGHT_SPOT 2
+
+struct Light {
+    vec4 position;    // xyz position, w light kind
+    vec4 direction;   // xyz direction, w cosine of the inner cone angle
+    vec4 color;       // rgb color, a intensity
+    vec4 attenuation; // x constant, y linear, z quadratic, w cosine of the outer cone angle
+    vec4 shadow;      // x first shadow map layer or -1, y layer count, z 1 for cascades
+};
+
+// the direction from worldPos towards the light in L, and the
+// distance and cone attenuation of the light there
+float lightAttenuation(Light light, vec3 worldPos, out vec3 L)
+{
+    int kind = int(light.position.w);
+
+    if (kind == LIGHT_DIRECTIONAL) {
+        L = normalize(-light.direction.xyz);
+        return 1.0;
+    }
+
+    vec3 toLight = light.position.xyz - worldPos;
+    float d = length(toLight);
+    L = toLight / max(d, 1e-4);
+
+    vec3 k = light.attenuation.xyz;
+    float attenuation = 1.0 / (k.x + k.y * d + k.z * d * d);
+
+    if (kind == LIGHT_SPOT) {
+        float theta = dot(L, normalize(-light.direction.xyz));
+        float inner = light.direction.w;
+        float outer = light.attenuation.w;
+        attenuation *= clamp((theta - outer) / max(inner - outer, 1e-4), 0.0, 1.0);
+    }
+
+    return attenuation;
+}
+
+// the light color scaled by its intensity
+vec3 lightRadiance(Light light)
+{
+    return light.color.rgb * light.color.a;
+}
@@ -0,0 +1,30 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec3 aNormal;
+layout (location = 2) in vec2 aTexCoord;
+layout (location = 3) in vec4 aTangent;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 projection;
+
+out vec3 WorldPos;
+out vec2 TexCoord;
+out mat3 TBN;
+
+void main()
+{
+    vec4 world = model * vec4(aPos, 1.0);
+    mat3 normalMatrix = transpose(inverse(mat3(model)));
+
+    vec3 N = normalize(normalMatrix * aNormal);
+    vec3 T = normalize(mat3(model) * aTangent.xyz);
+    T = normalize(T - dot(T, N) * N);
+    vec3 B = cross(N, T) * aTangent.w;
+
+    WorldPos = world.xyz;
+    TexCoord = aTexCoord;
+    TBN = mat3(T, B, N);
+
+    gl_Position = projection * view * world;
+}
@@ -0,0 +1,70 @@
+#version 330 core
+// MAX_LIGHTS, and USE_IBL for image based lighting, are defined, and the light,
+// shadow, brdf and surface functions inserted, by the engine when compiling
+
+layout (std140) uniform Lights {
+    vec4 ambient;     // rgb color scaled by intensity
+    vec4 cameraPosition;
+    ivec4 lightCount;
+    Light lights[MAX_LIGHTS];
+};
+
+#ifdef USE_IBL
+uniform samplerCube irradiance_map;
+uniform samplerCube prefiltered_map;
+uniform sampler2D brdf_lut;
+uniform float max_reflection_lod;
+#endif
+
+in vec3 WorldPos;
+in vec2 TexCoord;
+in mat3 TBN;
+
+out vec4 FragColor;
+
+void main()
+{
+    Surface surface = sampleSurface(TexCoord, TBN);
+    vec3 albedo = surface.albedo.rgb;
+    vec3 N = surface.N;
+
+    vec3 V = normalize(cameraPosition.xyz - WorldPos);
+    vec3 Lo = vec3(0.0);
+
+    for (int i = 0; i < min(lightCount.x, MAX_LIGHTS); ++i) {
+        vec3 L;
+        float attenuation = lightAttenuation(lights[i], WorldPos, L);
+        if (dot(N, L) <= 0.0) {
+            continue;
+        }
+
+        attenuation *= shadowFactor(lights[i].shadow, WorldPos, N, L);
+        vec3 radiance = lightRadiance(lights[i]) * attenuation;
+        Lo += cookTorrance(N, V, L, albedo, surface.metalness, surface.roughness) * radiance;
+    }
+
+#ifdef USE_IBL
+    float NdotV = max(dot(N, V), 1e-4);
+    vec3 F0 = mix(vec3(0.04), albedo, surface.metalness);
+    vec3 F = fresnelSchlickRoughness(NdotV, F0, surface.roughness);
+    vec3 kD = (1.0 - F) * (1.0 - surface.metalness);
+    vec3 diffuse = texture(irradiance_map, N).rgb * albedo;
+
+    vec3 R = reflect(-V, N);
+    vec3 prefiltered = textureLod(prefiltered_map, R, surface.roughness * max_reflection_lod).rgb;
+    vec2 brdf = texture(brdf_lut, vec2(NdotV, surface.roughness)).rg;
+    vec3 specular = prefiltered * (F * brdf.x + brdf.y);
+
+    vec3 ambientLight = (kD * diffuse + specular) * surface.ao;
+#else
+    vec3 ambientLight = ambient.rgb * albedo * surface.ao;
+#endif
+
+    vec3 color = ambientLight + Lo + surface.emission;
+
+    // reinhard tone mapping and gamma correction
+    color = color / (color + vec3(1.0));
+    color = pow(color, vec3(1.0 / 2.2));
+
+    FragColor = vec4(color, surface.albedo.a);
+}
@@ -0,0 +1,71 @@
+// inserted by the engine into the pbr and g-buffer shaders
+
+uniform vec4 base_color;
+uniform float metallic;
+uniform float roughness;
+uniform vec3 emissive;
+uniform float normal_scale;
+uniform float occlusion_strength;
+
+uniform bool use_base_color_map;
+uniform bool use_metallic_roughness_map;
+uniform bool use_normal_map;
+uniform bool use_occlusion_map;
+uniform bool use_emissive_map;
+
+// base color and emissive maps are in srgb, the rest is linear
+uniform sampler2D base_color_map;
+uniform sampler2D metallic_roughness_map; // roughness in g, metalness in b
+uniform sampler2D normal_map;
+uniform sampler2D occlusion_map;          // occlusion in r
+uniform sampler2D emissive_map;
+
+struct Surface {
+    vec4 albedo;      // linear rgb and alpha
+    float metalness;
+    float roughness;
+    float ao;
+    vec3 emission;
+    vec3 N;           // world space normal
+};
+
+// the material parameters at uv, on a surface with the given tangent frame
+Surface sampleSurface(vec2 uv, mat3 tbn)
+{
+    Surface surface;
+
+    surface.albedo = base_color;
+    if (use_base_color_map) {
+        vec4 texel = texture(base_color_map, uv);
+        surface.albedo *= vec4(pow(texel.rgb, vec3(2.2)), texel.a);
+    }
+
+    surface.metalness = metallic;
+    surface.roughness = roughness;
+    if (use_metallic_roughness_map) {
+        vec4 texel = texture(metallic_roughness_map, uv);
+        surface.roughness *= texel.g;
+        surface.metalness *= texel.b;
+    }
+    surface.roughness = clamp(surface.roughness, 0.04, 1.0);
+    surface.metalness = clamp(surface.metalness, 0.0, 1.0);
+
+    surface.ao = 1.0;
+    if (use_occlusion_map) {
+        surface.ao = 1.0 + occlusion_strength * (texture(occlusion_map, uv).r - 1.0);
+    }
+
+    surface.emission = emissive;
+    if (use_emissive_map) {
+        surface.emission *= pow(texture(emissive_map, uv).rgb, vec3(2.2));
+    }
+
+    surface.N = normalize(tbn[2]);
+    if (use_normal_map) {
+        vec3 tangentNormal = texture(normal_map, uv).xyz * 2.0 - 1.0;
+        tangentNormal.xy *= normal_scale;
+        surface.N = normalize(tbn * tangentNormal);
+    }
+
+    return surface;
+}
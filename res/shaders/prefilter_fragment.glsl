@@ -0,0 +1,47 @@
+#version 330 core
+// the ibl sampling functions are inserted here by the engine
+
+uniform samplerCube environment_map;
+uniform float roughness;
+
+// the face size of the environment map at mip level 0
+uniform float resolution;
+uniform uint sample_count;
+
+in vec3 LocalPos;
+
+out vec4 FragColor;
+
+void main()
+{
+    // assume the view direction equals the normal and reflection
+    vec3 N = normalize(LocalPos);
+    vec3 R = N;
+    vec3 V = R;
+
+    vec3 color = vec3(0.0);
+    float totalWeight = 0.0;
+
+    for (uint i = 0u; i < sample_count; ++i) {
+        vec2 Xi = hammersley(i, sample_count);
+        vec3 H = importanceSampleGGX(Xi, N, roughness);
+        vec3 L = normalize(2.0 * dot(V, H) * H - V);
+
+        float NdotL = max(dot(N, L), 0.0);
+        if (NdotL > 0.0) {
+            // sample a lower mip of the environment where samples are sparse to avoid aliasing
+            float NdotH = max(dot(N, H), 0.0);
+            float HdotV = max(dot(H, V), 0.0);
+            float pdf = distributionGGX(NdotH, roughness) * NdotH / (4.0 * HdotV) + 0.0001;
+
+            float saTexel = 4.0 * PI / (6.0 * resolution * resolution);
+            float saSample = 1.0 / (float(sample_count) * pdf + 0.0001);
+            float mipLevel = roughness == 0.0 ? 0.0 : 0.5 * log2(saSample / saTexel);
+
+            color += textureLod(environment_map, L, mipLevel).rgb * NdotL;
+            totalWeight += NdotL;
+        }
+    }
+
+    FragColor = vec4(color / max(totalWeight, 0.0001), 1.0);
+}
@@ -0,0 +1,13 @@
+#version 330 core
+uniform sampler2DArray shadow_map;
+uniform float layer;
+
+in vec2 TexCoord;
+
+out vec4 FragColor;
+
+void main()
+{
+    float depth = texture(shadow_map, vec3(TexCoord, layer)).r;
+    FragColor = vec4(vec3(depth), 1.0);
+}
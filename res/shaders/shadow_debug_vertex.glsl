@@ -0,0 +1,14 @@
+#version 330 core
+layout (location = 0) in vec3 aPos;
+layout (location = 2) in vec2 aTexCoord;
+
+// x, y of the lower left corner and width, height in normalized device coordinates
+uniform vec4 rect;
+
+out vec2 TexCoord;
+
+void main()
+{
+    TexCoord = aTexCoord;
+    gl_Position = vec4(rect.xy + (aPos.xy * 0.5 + 0.5) * rect.zw, 0.0, 1.0);
+}
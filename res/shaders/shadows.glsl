@@ -0,0 +1,58 @@
+// inserted by the engine after the defines of the lit shaders, which define MAX_SHADOW_MAPS
+
+layout (std140) uniform Shadows {
+    mat4 shadowMatrices[MAX_SHADOW_MAPS]; // world to shadow clip space per layer
+    vec4 cascadeSplits;                   // view space depth at which each cascade ends
+    mat4 cameraView;
+    vec4 shadowParams;                    // x depth bias, y normal bias, z pcf radius in texels
+};
+
+uniform sampler2DArrayShadow shadow_maps;
+
+// the fraction of light reaching worldPos, 1 when fully lit. shadow is the
+// shadow vec4 of the light: x first layer or -1, y layer count, z 1 for cascades
+float shadowFactor(vec4 shadow, vec3 worldPos, vec3 N, vec3 L)
+{
+    int layer = int(shadow.x);
+    if (layer < 0) {
+        return 1.0;
+    }
+
+    if (shadow.z > 0.5) {
+        float depth = -(cameraView * vec4(worldPos, 1.0)).z;
+        int count = int(shadow.y);
+
+        int cascade = 0;
+        while (cascade < count && depth > cascadeSplits[cascade]) {
+            cascade++;
+        }
+        if (cascade == count) {
+            return 1.0;
+        }
+        layer += cascade;
+    }
+
+    // push the lookup off the surface, more so at grazing angles
+    float cosTheta = clamp(dot(N, L), 0.0, 1.0);
+    vec3 offsetPos = worldPos + N * shadowParams.y * (1.0 - cosTheta);
+
+    vec4 projected = shadowMatrices[layer] * vec4(offsetPos, 1.0);
+    vec3 coords = projected.xyz / projected.w * 0.5 + 0.5;
+    if (coords.z > 1.0 || any(lessThan(coords.xy, vec2(0.0))) || any(greaterThan(coords.xy, vec2(1.0)))) {
+        return 1.0;
+    }
+
+    vec2 texel = 1.0 / vec2(textureSize(shadow_maps, 0).xy);
+    int radius = int(shadowParams.z);
+    float reference = coords.z - shadowParams.x;
+
+    float lit = 0.0;
+    for (int x = -radius; x <= radius; ++x) {
+        for (int y = -radius; y <= radius; ++y) {
+            lit += texture(shadow_maps, vec4(coords.xy + vec2(x, y) * texel, float(layer), reference));
+        }
+    }
+
+    float taps = float((2 * radius + 1) * (2 * radius + 1));
+    return lit / taps;
+}
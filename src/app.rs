@@ -0,0 +1,344 @@
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use glfw::{Context, Glfw, WindowEvent};
+
+use crate::{
+    common::log::initialize_logs,
+    config::EngineConfig,
+    display::{
+        context::GlContext,
+        debug::GlDebug,
+        framebuffer::set_viewport,
+        win::{initialize_glfw, initialize_opengl, initialize_window, DisplayMode, Window},
+    },
+    input::{
+        gamepad::{load_mappings_file, Gamepads},
+        record::{InputSource, Player, RecordedFrame, Recorder, RecordingHeader},
+        state::{CursorMode, Input},
+    },
+    time::{Clock, Time},
+    Result,
+};
+
+/// what an `App` gets to reach the window and opengl.
+pub struct AppContext {
+    pub gl: Rc<GlContext>,
+    pub window: Window,
+    pub glfw: Glfw,
+
+    /// the timing of the current frame
+    pub time: Time,
+
+    /// the input polled at the end of the last frame
+    pub input: Input,
+
+    /// the directory asset paths are relative to
+    pub assets: PathBuf,
+}
+
+impl AppContext {
+    /// the size of the window's framebuffer in pixels.
+    pub fn framebuffer_size(&self) -> (u32, u32) {
+        self.window.framebuffer_size()
+    }
+
+    /// move the window between windowed, fullscreen and borderless.
+    pub fn set_display_mode(&mut self, mode: DisplayMode) {
+        self.window.set_display_mode(&mut self.glfw, mode);
+    }
+
+    pub fn toggle_fullscreen(&mut self) {
+        self.window.toggle_fullscreen(&mut self.glfw);
+    }
+
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.window.set_vsync(&mut self.glfw, vsync);
+    }
+
+    pub fn set_cursor_mode(&mut self, mode: CursorMode) {
+        self.input.set_cursor_mode(&mut self.window, mode);
+    }
+
+    /// the path of an asset under the asset directory.
+    pub fn asset_path(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.assets.join(path)
+    }
+
+    /// leave the main loop after the current frame.
+    pub fn exit(&mut self) {
+        self.window.set_should_close(true);
+    }
+}
+
+/// an application driven by `run`.
+///
+/// every frame runs the fixed updates that are due, then updates
+/// the app and renders it into the cleared window. finally the app
+/// receives the window events polled after the buffers were swapped.
+/// an error ends the main loop, after which `shutdown` is still called.
+pub trait App: Sized {
+    /// create the app once the window and opengl are ready.
+    fn init(ctx: &mut AppContext) -> Result<Self>;
+
+    /// advance the simulation by one step of `dt` seconds, which
+    /// is always `Time::fixed_delta`. called zero or more times
+    /// a frame, before `update`.
+    fn fixed_update(&mut self, _ctx: &mut AppContext, _dt: f32) -> Result<()> {
+        Ok(())
+    }
+
+    /// advance the app by `dt` seconds.
+    fn update(&mut self, _ctx: &mut AppContext, _dt: f32) -> Result<()> {
+        Ok(())
+    }
+
+    /// draw the frame. state advanced by `fixed_update` is best
+    /// interpolated by `Time::alpha`.
+    fn render(&mut self, _ctx: &mut AppContext) -> Result<()> {
+        Ok(())
+    }
+
+    /// handle a window event. closing the window and resizing
+    /// the viewport are already taken care of.
+    fn event(&mut self, _ctx: &mut AppContext, _event: &WindowEvent) -> Result<()> {
+        Ok(())
+    }
+
+    fn shutdown(&mut self, _ctx: &mut AppContext) {}
+}
+
+/// where the main loop gets its input from, see `InputSource`.
+enum Driver {
+    Live,
+    Record(Recorder),
+    Replay { player: Player, headless: bool },
+}
+
+impl Driver {
+    fn is_replay(&self) -> bool {
+        matches!(self, Driver::Replay { .. })
+    }
+}
+
+/// open a window as described by `config` and run `A` in it until
+/// the window is closed, a replay ends or the app fails.
+pub fn run<A: App>(mut config: EngineConfig) -> Result<()> {
+    config.validate()?;
+    initialize_logs(&config.log)?;
+    log::debug!("Engine config: {:?}", config);
+
+    // a replay has to tick like the recording to come out the same
+    let player = match &config.input {
+        InputSource::Replay { path, headless } => {
+            let player = Player::open(path)?;
+            config.time.tick_rate = player.header().tick_rate;
+            config.time.max_fixed_steps = player.header().max_fixed_steps;
+            if *headless {
+                config.window.visible = false;
+                config.window.vsync = false;
+                config.time.max_fps = None;
+            }
+            Some(player)
+        },
+        _ => None,
+    };
+
+    let mut glfw = initialize_glfw(config.gl_debug.enabled)?;
+    let (mut window, events) = initialize_window(&mut glfw, &config.window)?;
+
+    let gl = initialize_opengl(&mut window)?;
+    let debug = GlDebug::load(&mut window, &gl, &config.gl_debug);
+    let gl = Rc::new(GlContext::new(gl).with_debug(debug));
+
+    // the framebuffer may be larger than the window on high dpi screens
+    let (width, height) = window.framebuffer_size();
+    set_viewport(&gl, [0, 0, width as i32, height as i32]);
+
+    window.set_all_polling(true);
+
+    if let Some(path) = &config.gamepad_mappings
+        && let Err(err) = load_mappings_file(&glfw, path)
+    {
+        log::warn!("Failed to load gamepad mappings: {}", err);
+    }
+
+    let header = RecordingHeader {
+        tick_rate: config.time.tick_rate,
+        max_fixed_steps: config.time.max_fixed_steps,
+        framebuffer_size: (width, height),
+    };
+
+    let mut driver = match (&config.input, player) {
+        (InputSource::Record(path), _) => Driver::Record(Recorder::create(path, &header)?),
+        (InputSource::Replay { headless, .. }, Some(player)) => {
+            if player.header().framebuffer_size != header.framebuffer_size {
+                log::warn!(
+                    "Replaying a recording made at {:?} at {:?}.",
+                    player.header().framebuffer_size,
+                    header.framebuffer_size
+                );
+            }
+            Driver::Replay { player, headless: *headless }
+        },
+        _ => Driver::Live,
+    };
+
+    let clock = Clock::new(config.time);
+    let mut ctx = AppContext {
+        gl,
+        window,
+        glfw,
+        time: *clock.time(),
+        input: Input::new(),
+        assets: config.assets.clone(),
+    };
+    let (window_width, window_height) = ctx.window.get_size();
+    ctx.input.set_window_size(window_width, window_height);
+
+    log::debug!("Initializing app...");
+    let mut app = A::init(&mut ctx)?;
+    ctx.gl.check_errors();
+    log::info!("Initialized app.");
+
+    let mut result = main_loop(&mut app, &mut ctx, &config, clock, &mut driver, &events);
+    if let Err(err) = &result {
+        log::error!("App failed: {}", err);
+    }
+
+    log::debug!("Shutting down app...");
+    app.shutdown(&mut ctx);
+    log::info!("Shut down app.");
+
+    if let Driver::Record(recorder) = driver {
+        result = result.and(recorder.finish());
+    }
+
+    result
+}
+
+fn main_loop<A: App>(
+    app: &mut A,
+    ctx: &mut AppContext,
+    config: &EngineConfig,
+    mut clock: Clock,
+    driver: &mut Driver,
+    events: &glfw::GlfwReceiver<(f64, WindowEvent)>,
+) -> Result<()> {
+    while !ctx.window.should_close() {
+        let replayed = match driver {
+            Driver::Replay { player, .. } => match player.next_frame() {
+                Some(frame) => Some(frame),
+                None => {
+                    log::info!("Replay finished after {} frames.", ctx.time.frame() + 1);
+                    break;
+                },
+            },
+            _ => None,
+        };
+
+        let now = ctx.glfw.get_time();
+        let frame_start = replayed.as_ref().map_or(now, |frame| frame.time);
+        let steps = clock.tick(frame_start);
+        ctx.time = *clock.time();
+
+        let fixed_delta = ctx.time.fixed_delta();
+        for _ in 0..steps {
+            app.fixed_update(ctx, fixed_delta)?;
+        }
+        ctx.gl.check_errors();
+
+        let delta = ctx.time.delta();
+        app.update(ctx, delta)?;
+        ctx.gl.check_errors();
+
+        if let Some([r, g, b, a]) = config.clear_color {
+            unsafe {
+                ctx.gl.ClearColor(r, g, b, a);
+                ctx.gl.Clear(gl33::GL_COLOR_BUFFER_BIT | gl33::GL_DEPTH_BUFFER_BIT);
+            }
+        }
+
+        app.render(ctx)?;
+        ctx.gl.check_errors();
+
+        ctx.window.swap_buffers();
+
+        let throttle = !ctx.window.vsync() && !matches!(driver, Driver::Replay { headless: true, .. });
+        if let Some(min_frame_time) = clock.settings().min_frame_time().filter(|_| throttle) {
+            let remaining = now + min_frame_time - ctx.glfw.get_time();
+            if remaining > 0.0 {
+                std::thread::sleep(std::time::Duration::from_secs_f64(remaining));
+            }
+        }
+
+        let stats = ctx.gl.stats();
+        log::trace!("Gl calls issued: {}, elided: {}", stats.issued(), stats.elided());
+        ctx.gl.reset_stats();
+
+        ctx.input.begin_poll();
+        ctx.glfw.poll_events();
+
+        let mut recorded = Vec::new();
+        for (time, event) in glfw::flush_messages(events) {
+            // a replay only lets the live window close itself,
+            // everything else comes from the recording
+            if driver.is_replay() {
+                if event == WindowEvent::Close {
+                    handle_window_event(ctx, &event);
+                }
+                continue;
+            }
+
+            handle_window_event(ctx, &event);
+            ctx.input.event(&event);
+            app.event(ctx, &event)?;
+
+            if let Driver::Record(_) = driver {
+                recorded.push((time, event));
+            }
+        }
+
+        match driver {
+            Driver::Replay { .. } => {
+                let frame = replayed.unwrap_or_else(|| unreachable!("replays always have a frame"));
+                for (_, event) in &frame.events {
+                    handle_window_event(ctx, event);
+                    ctx.input.event(event);
+                    app.event(ctx, event)?;
+                }
+                ctx.input.apply_gamepads(&ctx.glfw, &frame.gamepads);
+            },
+            Driver::Record(recorder) => {
+                let gamepads = Gamepads::poll(&ctx.glfw);
+                ctx.input.apply_gamepads(&ctx.glfw, &gamepads);
+                recorder.record(&RecordedFrame { time: frame_start, events: recorded, gamepads })?;
+            },
+            Driver::Live => ctx.input.update_gamepads(&ctx.glfw),
+        }
+    }
+
+    Ok(())
+}
+
+/// what the main loop does with every window event itself.
+fn handle_window_event(ctx: &mut AppContext, event: &WindowEvent) {
+    match *event {
+        WindowEvent::Close => {
+            log::debug!("Window close event received.");
+            ctx.window.set_should_close(true);
+        },
+        WindowEvent::FramebufferSize(width, height) => {
+            log::debug!("Framebuffer resized to {}x{}.", width, height);
+            set_viewport(&ctx.gl, [0, 0, width, height]);
+        },
+        WindowEvent::ContentScale(x, y) => {
+            log::debug!("Window content scale changed to {}x{}.", x, y);
+        },
+        _ => {
+            log::trace!("Glfw event received. {:?}", event);
+        },
+    }
+}
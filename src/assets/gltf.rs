@@ -0,0 +1,637 @@
+use std::{collections::HashMap, path::Path, rc::Rc};
+
+use gltf::{animation::util::ReadOutputs, buffer, image::Format};
+use nalgebra::{Matrix4, Quaternion, UnitQuaternion, Vector3};
+
+use crate::{
+    display::{context::GlContext, mesh::{Indices, Mesh, MeshData, Topology}, texture::{Texture, TextureFilter, TextureFormat, TextureWrap}, vertex::{MeshVertex, SkinnedVertex}},
+    Error,
+    Result,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlphaMode {
+    Opaque,
+
+    /// fragments with an alpha below `alpha_cutoff` are discarded
+    Mask,
+
+    Blend,
+}
+
+/// a reference to one of `GltfScene::textures` and the uv set it uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureRef {
+    pub texture: usize,
+    pub tex_coord: u32,
+}
+
+/// a pbr metallic-roughness material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfMaterial {
+    pub name: Option<String>,
+    pub base_color: [f32; 4],
+    pub base_color_texture: Option<TextureRef>,
+    pub metallic: f32,
+    pub roughness: f32,
+
+    /// metalness in the blue channel, roughness in the green channel
+    pub metallic_roughness_texture: Option<TextureRef>,
+
+    pub normal_texture: Option<TextureRef>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<TextureRef>,
+    pub occlusion_strength: f32,
+    pub emissive: [f32; 3],
+    pub emissive_texture: Option<TextureRef>,
+    pub alpha_mode: AlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool,
+}
+
+pub struct GltfPrimitive {
+    /// uploaded with the `SkinnedVertex` layout if the primitive has
+    /// joints and weights, and the `MeshVertex` layout otherwise
+    pub mesh: Mesh,
+
+    /// the cpu-side copy of the vertex data
+    pub data: MeshData,
+
+    /// index into `GltfScene::materials`
+    pub material: Option<usize>,
+
+    /// joint indices per vertex, for skinned meshes
+    pub joints: Vec<[u16; 4]>,
+
+    /// joint weights per vertex, for skinned meshes
+    pub weights: Vec<[f32; 4]>,
+}
+
+pub struct GltfMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GltfProjection {
+    Perspective {
+        /// vertical field of view in radians
+        yfov: f32,
+        aspect_ratio: Option<f32>,
+        znear: f32,
+
+        /// `None` for an infinite projection
+        zfar: Option<f32>,
+    },
+    Orthographic {
+        xmag: f32,
+        ymag: f32,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfCamera {
+    pub name: Option<String>,
+    pub projection: GltfProjection,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfNode {
+    pub name: Option<String>,
+    pub translation: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub scale: Vector3<f32>,
+
+    /// indices into `GltfScene::nodes`
+    pub children: Vec<usize>,
+
+    pub mesh: Option<usize>,
+    pub camera: Option<usize>,
+    pub skin: Option<usize>,
+}
+
+impl GltfNode {
+    pub fn local_matrix(&self) -> Matrix4<f32> {
+        Matrix4::new_translation(&self.translation)
+            * self.rotation.to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&self.scale)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfSkin {
+    pub name: Option<String>,
+
+    /// the joint nodes, in the order referenced by vertex joint indices
+    pub joints: Vec<usize>,
+
+    /// one matrix per joint
+    pub inverse_bind_matrices: Vec<Matrix4<f32>>,
+
+    pub skeleton: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    Step,
+
+    /// every keyframe stores an in-tangent, the value and an out-tangent
+    CubicSpline,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelValues {
+    Translations(Vec<Vector3<f32>>),
+    Rotations(Vec<UnitQuaternion<f32>>),
+    Scales(Vec<Vector3<f32>>),
+
+    /// morph target weights, flattened across keyframes
+    Weights(Vec<f32>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationChannel {
+    pub node: usize,
+    pub interpolation: Interpolation,
+
+    /// keyframe times in seconds
+    pub times: Vec<f32>,
+
+    pub values: ChannelValues,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfAnimation {
+    pub name: Option<String>,
+    pub channels: Vec<AnimationChannel>,
+}
+
+impl GltfAnimation {
+    /// the time of the last keyframe across all channels.
+    pub fn duration(&self) -> f32 {
+        self.channels
+            .iter()
+            .filter_map(|c| c.times.last())
+            .fold(0.0, |a, &b| a.max(b))
+    }
+}
+
+/// everything imported from a gltf file, with meshes and
+/// textures already uploaded to opengl.
+pub struct GltfScene {
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<GltfMaterial>,
+
+    /// textures pairing the same image and sampler share
+    /// one uploaded texture
+    pub textures: Vec<Rc<Texture>>,
+
+    pub nodes: Vec<GltfNode>,
+
+    /// the root nodes of the default scene
+    pub roots: Vec<usize>,
+
+    pub cameras: Vec<GltfCamera>,
+    pub skins: Vec<GltfSkin>,
+    pub animations: Vec<GltfAnimation>,
+}
+
+impl GltfScene {
+    /// the world matrix of every node, indexed like `nodes`.
+    /// nodes outside the default scene keep the identity.
+    pub fn world_matrices(&self) -> Vec<Matrix4<f32>> {
+        let mut world = vec![Matrix4::identity(); self.nodes.len()];
+        let mut stack: Vec<(usize, Matrix4<f32>)> = self.roots.iter().map(|&r| (r, Matrix4::identity())).collect();
+
+        while let Some((index, parent)) = stack.pop() {
+            let node = &self.nodes[index];
+            world[index] = parent * node.local_matrix();
+            stack.extend(node.children.iter().map(|&c| (c, world[index])));
+        }
+
+        world
+    }
+}
+
+fn gltf_error(path: &Path, message: impl std::fmt::Display) -> Error {
+    log::error!("Failed to import {}: {}", path.display(), message);
+    Error::AssetParse(format!("{}: {}", path.display(), message))
+}
+
+fn topology(mode: gltf::mesh::Mode) -> Topology {
+    use gltf::mesh::Mode;
+
+    match mode {
+        Mode::Points => Topology::Points,
+        Mode::Lines => Topology::Lines,
+        Mode::LineLoop => Topology::LineLoop,
+        Mode::LineStrip => Topology::LineStrip,
+        Mode::Triangles => Topology::Triangles,
+        Mode::TriangleStrip => Topology::TriangleStrip,
+        Mode::TriangleFan => Topology::TriangleFan,
+    }
+}
+
+fn texture_ref(info: Option<gltf::texture::Info>) -> Option<TextureRef> {
+    info.map(|info| TextureRef { texture: info.texture().index(), tex_coord: info.tex_coord() })
+}
+
+fn upload_image(ctx: &GlContext, path: &Path, image: &gltf::image::Data) -> Result<Texture> {
+    // gltf images and uvs both have their origin in the top left
+    // corner, so unlike `Texture::load_file` the rows are not flipped
+    let (data, format) = match image.format {
+        Format::R8G8B8 => (image.pixels.clone(), TextureFormat::Rgb),
+        Format::R8G8B8A8 => (image.pixels.clone(), TextureFormat::Rgba),
+        Format::R8 => (image.pixels.iter().flat_map(|&r| [r, r, r]).collect(), TextureFormat::Rgb),
+        Format::R8G8 => (
+            image.pixels.chunks_exact(2).flat_map(|p| [p[0], p[1], 0]).collect(),
+            TextureFormat::Rgb,
+        ),
+        other => {
+            return Err(gltf_error(path, format!(
+                "unsupported image format {:?}. Only 8 bit images are supported.",
+                other
+            )));
+        }
+    };
+
+    Ok(Texture::load_data(ctx, &data, image.width, image.height, format))
+}
+
+/// sample the bound texture as the gltf sampler says, with the
+/// closest `TextureFilter`. unset filters default to trilinear.
+fn apply_sampler(ctx: &GlContext, texture: &Texture, sampler: &gltf::texture::Sampler) {
+    use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+
+    let mipmapped = !matches!(sampler.min_filter(), Some(MinFilter::Nearest | MinFilter::Linear));
+    let filter = match (sampler.mag_filter(), sampler.min_filter()) {
+        (Some(MagFilter::Nearest), None | Some(MinFilter::Nearest | MinFilter::NearestMipmapNearest | MinFilter::NearestMipmapLinear)) => {
+            TextureFilter::Nearest
+        }
+        _ if mipmapped => TextureFilter::Trilinear,
+        _ => TextureFilter::Linear,
+    };
+
+    let wrap = |mode| match mode {
+        WrappingMode::ClampToEdge => TextureWrap::ClampToEdge,
+        WrappingMode::MirroredRepeat => TextureWrap::MirroredRepeat,
+        WrappingMode::Repeat => TextureWrap::Repeat,
+    };
+
+    texture.set_sampling(ctx, filter, wrap(sampler.wrap_s()));
+    texture.set_wrap(ctx, wrap(sampler.wrap_s()), wrap(sampler.wrap_t()));
+}
+
+/// the cpu-side data of a primitive, checked and completed
+/// but not uploaded yet.
+struct PrimitiveData {
+    data: MeshData,
+    topology: Topology,
+    indexed: bool,
+    joints: Vec<[u16; 4]>,
+    weights: Vec<[f32; 4]>,
+}
+
+fn read_primitive(path: &Path, primitive: &gltf::Primitive, buffers: &[buffer::Data]) -> Result<PrimitiveData> {
+    let reader = primitive.reader(|b| buffers.get(b.index()).map(|data| &data.0[..]));
+
+    let positions: Vec<[f32; 3]> = reader
+        .read_positions()
+        .ok_or_else(|| gltf_error(path, "primitive without positions"))?
+        .collect();
+
+    let mut data = MeshData {
+        normals: reader.read_normals().map(|n| n.collect()).unwrap_or_default(),
+        uvs: reader.read_tex_coords(0).map(|t| t.into_f32().collect()).unwrap_or_default(),
+        tangents: reader.read_tangents().map(|t| t.collect()).unwrap_or_default(),
+        indices: reader.read_indices().map(|i| i.into_u32().collect()).unwrap_or_default(),
+        positions,
+    };
+
+    // normals and tangents are generated by indexing the vertices
+    if let Some(index) = data.indices.iter().find(|&&index| index as usize >= data.vertex_count()) {
+        return Err(gltf_error(path, format!(
+            "primitive index {} is out of range of its {} vertices",
+            index,
+            data.vertex_count()
+        )));
+    }
+
+    let indexed = reader.read_indices().is_some();
+    let topology = topology(primitive.mode());
+
+    if topology == Topology::Triangles {
+        if !indexed {
+            data.indices = (0..data.vertex_count() as u32).collect();
+        }
+        if data.normals.is_empty() {
+            data.compute_normals();
+        }
+        if data.tangents.is_empty() && !data.uvs.is_empty() {
+            data.compute_tangents();
+        }
+    }
+
+    let joints: Vec<[u16; 4]> = reader.read_joints(0).map(|j| j.into_u16().collect()).unwrap_or_default();
+    let weights: Vec<[f32; 4]> = reader.read_weights(0).map(|w| w.into_f32().collect()).unwrap_or_default();
+
+    Ok(PrimitiveData { data, topology, indexed, joints, weights })
+}
+
+fn load_primitive(
+    ctx: &GlContext,
+    path: &Path,
+    primitive: &gltf::Primitive,
+    buffers: &[buffer::Data],
+) -> Result<GltfPrimitive> {
+    let PrimitiveData { data, topology, indexed, joints, weights } = read_primitive(path, primitive, buffers)?;
+
+    let skinned = joints.len() == data.vertex_count() && weights.len() == data.vertex_count();
+    if !skinned && (!joints.is_empty() || !weights.is_empty()) {
+        log::warn!("Ignoring the joints and weights of a primitive in {}, their counts do not match.", path.display());
+    }
+
+    let vertices = data.vertices();
+    let indices = (indexed || topology == Topology::Triangles).then_some(Indices::U32(&data.indices));
+    let mesh = if skinned {
+        let vertices: Vec<SkinnedVertex> = vertices
+            .into_iter()
+            .zip(joints.iter().zip(&weights))
+            .map(|(vertex, (joints, weights))| SkinnedVertex::new(vertex, *joints, *weights))
+            .collect();
+        Mesh::new(ctx, SkinnedVertex::layout(), &vertices, indices, topology)
+    } else {
+        Mesh::new(ctx, MeshVertex::layout(), &vertices, indices, topology)
+    };
+
+    Ok(GltfPrimitive { mesh, material: primitive.material().index(), joints, weights, data })
+}
+
+fn load_material(material: &gltf::Material) -> GltfMaterial {
+    let pbr = material.pbr_metallic_roughness();
+
+    GltfMaterial {
+        name: material.name().map(str::to_string),
+        base_color: pbr.base_color_factor(),
+        base_color_texture: texture_ref(pbr.base_color_texture()),
+        metallic: pbr.metallic_factor(),
+        roughness: pbr.roughness_factor(),
+        metallic_roughness_texture: texture_ref(pbr.metallic_roughness_texture()),
+        normal_texture: material.normal_texture().map(|t| TextureRef {
+            texture: t.texture().index(),
+            tex_coord: t.tex_coord(),
+        }),
+        normal_scale: material.normal_texture().map_or(1.0, |t| t.scale()),
+        occlusion_texture: material.occlusion_texture().map(|t| TextureRef {
+            texture: t.texture().index(),
+            tex_coord: t.tex_coord(),
+        }),
+        occlusion_strength: material.occlusion_texture().map_or(1.0, |t| t.strength()),
+        emissive: material.emissive_factor(),
+        emissive_texture: texture_ref(material.emissive_texture()),
+        alpha_mode: match material.alpha_mode() {
+            gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+            gltf::material::AlphaMode::Mask => AlphaMode::Mask,
+            gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+        },
+        alpha_cutoff: material.alpha_cutoff().unwrap_or(0.5),
+        double_sided: material.double_sided(),
+    }
+}
+
+fn load_node(node: &gltf::Node) -> GltfNode {
+    let (translation, [x, y, z, w], scale) = node.transform().decomposed();
+
+    GltfNode {
+        name: node.name().map(str::to_string),
+        translation: translation.into(),
+        rotation: UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z)),
+        scale: scale.into(),
+        children: node.children().map(|c| c.index()).collect(),
+        mesh: node.mesh().map(|m| m.index()),
+        camera: node.camera().map(|c| c.index()),
+        skin: node.skin().map(|s| s.index()),
+    }
+}
+
+fn load_camera(camera: &gltf::Camera) -> GltfCamera {
+    let projection = match camera.projection() {
+        gltf::camera::Projection::Perspective(p) => GltfProjection::Perspective {
+            yfov: p.yfov(),
+            aspect_ratio: p.aspect_ratio(),
+            znear: p.znear(),
+            zfar: p.zfar(),
+        },
+        gltf::camera::Projection::Orthographic(o) => GltfProjection::Orthographic {
+            xmag: o.xmag(),
+            ymag: o.ymag(),
+            znear: o.znear(),
+            zfar: o.zfar(),
+        },
+    };
+
+    GltfCamera { name: camera.name().map(str::to_string), projection }
+}
+
+fn load_skin(skin: &gltf::Skin, buffers: &[buffer::Data]) -> GltfSkin {
+    let reader = skin.reader(|b| buffers.get(b.index()).map(|data| &data.0[..]));
+    let joints: Vec<usize> = skin.joints().map(|j| j.index()).collect();
+
+    // a missing accessor means every inverse bind matrix is the identity
+    let inverse_bind_matrices = match reader.read_inverse_bind_matrices() {
+        Some(matrices) => matrices.map(Matrix4::from).collect(),
+        None => vec![Matrix4::identity(); joints.len()],
+    };
+
+    GltfSkin {
+        name: skin.name().map(str::to_string),
+        joints,
+        inverse_bind_matrices,
+        skeleton: skin.skeleton().map(|s| s.index()),
+    }
+}
+
+fn load_animation(path: &Path, animation: &gltf::Animation, buffers: &[buffer::Data]) -> Result<GltfAnimation> {
+    let mut channels = Vec::new();
+
+    for channel in animation.channels() {
+        let reader = channel.reader(|b| buffers.get(b.index()).map(|data| &data.0[..]));
+
+        let times = reader
+            .read_inputs()
+            .ok_or_else(|| gltf_error(path, "animation channel without keyframe times"))?
+            .collect();
+
+        let values = match reader.read_outputs() {
+            Some(ReadOutputs::Translations(t)) => ChannelValues::Translations(t.map(Vector3::from).collect()),
+            Some(ReadOutputs::Rotations(r)) => ChannelValues::Rotations(
+                r.into_f32()
+                    .map(|[x, y, z, w]| UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z)))
+                    .collect(),
+            ),
+            Some(ReadOutputs::Scales(s)) => ChannelValues::Scales(s.map(Vector3::from).collect()),
+            Some(ReadOutputs::MorphTargetWeights(w)) => ChannelValues::Weights(w.into_f32().collect()),
+            None => return Err(gltf_error(path, "animation channel without keyframe values")),
+        };
+
+        channels.push(AnimationChannel {
+            node: channel.target().node().index(),
+            interpolation: match channel.sampler().interpolation() {
+                gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                gltf::animation::Interpolation::Step => Interpolation::Step,
+                gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+            },
+            times,
+            values,
+        });
+    }
+
+    Ok(GltfAnimation { name: animation.name().map(str::to_string), channels })
+}
+
+/// import a `.gltf` (json with external or embedded buffers)
+/// or `.glb` (binary container) file.
+///
+/// external buffers and images are resolved relative to the
+/// file. only local files and `data:` uris are supported.
+pub fn load(ctx: &GlContext, path: impl AsRef<Path>) -> Result<GltfScene> {
+    let path = path.as_ref();
+    log::debug!("Loading gltf scene from file... {}", path.display());
+
+    let (document, buffers, images) = gltf::import(path)?;
+
+    // gltf textures pair an image with a sampler. an image is uploaded
+    // once per sampler it is used with, and textures with the same
+    // pair share the upload
+    let mut uploads: HashMap<(usize, Option<usize>), Rc<Texture>> = HashMap::new();
+    let mut textures = Vec::new();
+    for texture in document.textures() {
+        let sampler = texture.sampler();
+        let key = (texture.source().index(), sampler.index());
+
+        let uploaded = match uploads.get(&key) {
+            Some(uploaded) => uploaded.clone(),
+            None => {
+                let uploaded = Rc::new(upload_image(ctx, path, &images[key.0])?);
+                apply_sampler(ctx, &uploaded, &sampler);
+                uploads.insert(key, uploaded.clone());
+                uploaded
+            }
+        };
+        textures.push(uploaded);
+    }
+
+    let mut meshes = Vec::new();
+    for mesh in document.meshes() {
+        let primitives = mesh
+            .primitives()
+            .map(|primitive| load_primitive(ctx, path, &primitive, &buffers))
+            .collect::<Result<Vec<_>>>()?;
+        meshes.push(GltfMesh { name: mesh.name().map(str::to_string), primitives });
+    }
+
+    let roots = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .map(|scene| scene.nodes().map(|n| n.index()).collect())
+        .unwrap_or_default();
+
+    let scene = GltfScene {
+        meshes,
+        materials: document.materials().map(|m| load_material(&m)).collect(),
+        textures,
+        nodes: document.nodes().map(|n| load_node(&n)).collect(),
+        roots,
+        cameras: document.cameras().map(|c| load_camera(&c)).collect(),
+        skins: document.skins().map(|s| load_skin(&s, &buffers)).collect(),
+        animations: document
+            .animations()
+            .map(|a| load_animation(path, &a, &buffers))
+            .collect::<Result<Vec<_>>>()?,
+    };
+
+    log::info!(
+        "Loaded gltf scene {} (meshes = {}, materials = {}, nodes = {}, animations = {}).",
+        path.display(),
+        scene.meshes.len(),
+        scene.materials.len(),
+        scene.nodes.len(),
+        scene.animations.len()
+    );
+
+    Ok(scene)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut out = String::new();
+        for chunk in bytes.chunks(3) {
+            let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+                } else {
+                    out.push('=');
+                }
+            }
+        }
+        out
+    }
+
+    /// a gltf file with one triangle of three vertices and `indices`.
+    fn triangle(indices: [u16; 3]) -> String {
+        let mut bytes = Vec::new();
+        for position in [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]] {
+            bytes.extend(position.iter().flat_map(|v| v.to_le_bytes()));
+        }
+        bytes.extend(indices.iter().flat_map(|i| i.to_le_bytes()));
+        bytes.extend([0, 0]);
+
+        format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "buffers": [{{ "byteLength": {len}, "uri": "data:application/octet-stream;base64,{data}" }}],
+                "bufferViews": [
+                    {{ "buffer": 0, "byteOffset": 0, "byteLength": 36 }},
+                    {{ "buffer": 0, "byteOffset": 36, "byteLength": 6 }}
+                ],
+                "accessors": [
+                    {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                       "min": [0, 0, 0], "max": [1, 1, 0] }},
+                    {{ "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }}
+                ],
+                "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "indices": 1 }}] }}]
+            }}"#,
+            len = bytes.len(),
+            data = base64(&bytes)
+        )
+    }
+
+    fn read(source: &str) -> Result<PrimitiveData> {
+        let (document, buffers, _) = gltf::import_slice(source.as_bytes()).unwrap();
+        let primitive = document.meshes().next().unwrap().primitives().next().unwrap();
+        read_primitive(Path::new("triangle.gltf"), &primitive, &buffers)
+    }
+
+    #[test]
+    fn reads_triangle() {
+        let primitive = read(&triangle([0, 1, 2])).unwrap();
+        assert_eq!(primitive.data.vertex_count(), 3);
+        assert_eq!(primitive.data.indices, vec![0, 1, 2]);
+        assert_eq!(primitive.data.normals, vec![[0.0, 0.0, 1.0]; 3]);
+        assert!(primitive.indexed);
+    }
+
+    #[test]
+    fn rejects_out_of_range_indices() {
+        assert!(matches!(read(&triangle([0, 1, 3])), Err(Error::AssetParse(_))));
+        assert!(matches!(read(&triangle([0, 1, u16::MAX])), Err(Error::AssetParse(_))));
+    }
+}
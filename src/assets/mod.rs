@@ -0,0 +1,7 @@
+/// module for loading wavefront obj models
+/// and their mtl material libraries
+pub mod obj;
+
+/// module for importing gltf 2.0 scenes
+/// from .gltf and .glb files
+pub mod gltf;
@@ -0,0 +1,492 @@
+use std::{collections::HashMap, path::{Path, PathBuf}};
+
+use crate::{display::{context::GlContext, mesh::{Mesh, MeshData}, texture::Texture}, Error, Result};
+
+/// a material from an `.mtl` library. texture paths are
+/// already resolved relative to the library file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjMaterial {
+    pub name: String,
+    pub ambient: [f32; 3],
+    pub diffuse: [f32; 3],
+    pub specular: [f32; 3],
+    pub emissive: [f32; 3],
+    pub shininess: f32,
+
+    /// opacity, 1 is fully opaque
+    pub dissolve: f32,
+
+    pub diffuse_map: Option<PathBuf>,
+    pub specular_map: Option<PathBuf>,
+    pub normal_map: Option<PathBuf>,
+    pub alpha_map: Option<PathBuf>,
+    pub emissive_map: Option<PathBuf>,
+}
+
+impl ObjMaterial {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ambient: [0.0; 3],
+            diffuse: [0.8; 3],
+            specular: [0.0; 3],
+            emissive: [0.0; 3],
+            shininess: 1.0,
+            dissolve: 1.0,
+            diffuse_map: None,
+            specular_map: None,
+            normal_map: None,
+            alpha_map: None,
+            emissive_map: None,
+        }
+    }
+
+    /// load every texture map the material references.
+    pub fn load_textures(&self, ctx: &GlContext) -> Result<ObjTextures> {
+        let load = |path: &Option<PathBuf>| -> Result<Option<Texture>> {
+            path.as_ref()
+                .map(|path| Texture::load_file(ctx, &path.to_string_lossy()))
+                .transpose()
+        };
+
+        Ok(ObjTextures {
+            diffuse: load(&self.diffuse_map)?,
+            specular: load(&self.specular_map)?,
+            normal: load(&self.normal_map)?,
+            alpha: load(&self.alpha_map)?,
+            emissive: load(&self.emissive_map)?,
+        })
+    }
+}
+
+/// the textures of an `ObjMaterial`, loaded into opengl.
+pub struct ObjTextures {
+    pub diffuse: Option<Texture>,
+    pub specular: Option<Texture>,
+    pub normal: Option<Texture>,
+    pub alpha: Option<Texture>,
+    pub emissive: Option<Texture>,
+}
+
+/// the faces of one object/group that share a material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjMesh {
+    /// the name of the enclosing `o` statement
+    pub object: Option<String>,
+
+    /// the name of the enclosing `g` statement
+    pub group: Option<String>,
+
+    /// index into `ObjModel::materials`
+    pub material: Option<usize>,
+
+    pub data: MeshData,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjModel {
+    pub meshes: Vec<ObjMesh>,
+    pub materials: Vec<ObjMaterial>,
+}
+
+impl ObjModel {
+    pub fn upload(&self, ctx: &GlContext) -> Vec<Mesh> {
+        self.meshes.iter().map(|mesh| mesh.data.upload(ctx)).collect()
+    }
+
+    pub fn material(&self, mesh: &ObjMesh) -> Option<&ObjMaterial> {
+        mesh.material.and_then(|i| self.materials.get(i))
+    }
+}
+
+fn parse_error(file: &Path, line: usize, message: impl std::fmt::Display) -> Error {
+    log::error!("Failed to parse {}:{}: {}", file.display(), line, message);
+    Error::AssetParse(format!("{}:{}: {}", file.display(), line, message))
+}
+
+fn parse_floats<const N: usize>(
+    args: &[&str],
+    required: usize,
+    defaults: [f32; N],
+    file: &Path,
+    line: usize,
+) -> Result<[f32; N]> {
+    if args.len() < required {
+        return Err(parse_error(file, line, format!("expected at least {} values", required)));
+    }
+
+    let mut out = defaults;
+    for (value, arg) in out.iter_mut().zip(args) {
+        *value = arg.parse().map_err(|_| parse_error(file, line, format!("invalid number \"{}\"", arg)))?;
+    }
+    Ok(out)
+}
+
+/// resolve a 1-based, possibly negative obj index into a 0-based one.
+fn resolve_index(raw: &str, count: usize, file: &Path, line: usize) -> Result<usize> {
+    let index: i64 = raw.parse().map_err(|_| parse_error(file, line, format!("invalid index \"{}\"", raw)))?;
+
+    let resolved = match index {
+        0 => None,
+        i if i > 0 => Some(i as usize - 1),
+        i => count.checked_sub(i.unsigned_abs() as usize),
+    };
+
+    match resolved {
+        Some(i) if i < count => Ok(i),
+        _ => Err(parse_error(file, line, format!("index {} out of range ({} defined)", index, count))),
+    }
+}
+
+/// a face corner as indices into the position, uv and normal lists.
+type Corner = (usize, Option<usize>, Option<usize>);
+
+/// state of the mesh currently being assembled.
+struct Builder {
+    object: Option<String>,
+    group: Option<String>,
+    material: Option<usize>,
+    data: MeshData,
+    corners: HashMap<Corner, u32>,
+    has_normals: bool,
+    has_uvs: bool,
+}
+
+impl Builder {
+    fn new(object: Option<String>, group: Option<String>, material: Option<usize>) -> Self {
+        Self {
+            object,
+            group,
+            material,
+            data: MeshData::default(),
+            corners: HashMap::new(),
+            has_normals: true,
+            has_uvs: true,
+        }
+    }
+
+    fn vertex(&mut self, corner: Corner, positions: &[[f32; 3]], uvs: &[[f32; 2]], normals: &[[f32; 3]]) -> u32 {
+        if let Some(&index) = self.corners.get(&corner) {
+            return index;
+        }
+
+        let (p, t, n) = corner;
+        self.has_uvs &= t.is_some();
+        self.has_normals &= n.is_some();
+
+        let index = self.data.push_vertex(
+            positions[p],
+            n.map_or([0.0; 3], |n| normals[n]),
+            t.map_or([0.0; 2], |t| uvs[t]),
+        );
+        self.corners.insert(corner, index);
+        index
+    }
+
+    fn finish(mut self) -> Option<ObjMesh> {
+        if self.data.indices.is_empty() {
+            return None;
+        }
+
+        if !self.has_normals {
+            self.data.compute_normals();
+        }
+        if self.has_uvs {
+            self.data.compute_tangents();
+        }
+
+        Some(ObjMesh {
+            object: self.object,
+            group: self.group,
+            material: self.material,
+            data: self.data,
+        })
+    }
+}
+
+/// start a new mesh, keeping the current one if it has faces.
+fn restart(
+    model: &mut ObjModel,
+    builder: &mut Builder,
+    object: Option<String>,
+    group: Option<String>,
+    material: Option<usize>,
+) {
+    let previous = std::mem::replace(builder, Builder::new(object, group, material));
+    if let Some(mesh) = previous.finish() {
+        model.meshes.push(mesh);
+    }
+}
+
+/// load an `.obj` file and the material libraries it references.
+pub fn load(path: impl AsRef<Path>) -> Result<ObjModel> {
+    let path = path.as_ref();
+    log::debug!("Loading obj model from file... {}", path.display());
+
+    let source = std::fs::read_to_string(path)?;
+    let model = parse(&source, path)?;
+
+    log::info!(
+        "Loaded obj model {} (meshes = {}, materials = {}).",
+        path.display(),
+        model.meshes.len(),
+        model.materials.len()
+    );
+    Ok(model)
+}
+
+/// parse obj `source` that was read from `path`. material
+/// libraries are loaded relative to `path`.
+pub fn parse(source: &str, path: &Path) -> Result<ObjModel> {
+    let base = path.parent().unwrap_or(Path::new(""));
+
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+
+    let mut model = ObjModel::default();
+    let mut builder = Builder::new(None, None, None);
+
+    for (number, line) in source.lines().enumerate() {
+        let number = number + 1;
+        let line = line.split('#').next().unwrap_or("").trim();
+
+        let mut parts = line.split_whitespace();
+        let Some(keyword) = parts.next() else { continue };
+        let args: Vec<&str> = parts.collect();
+
+        match keyword {
+            "v" => positions.push(parse_floats(&args, 3, [0.0; 3], path, number)?),
+            "vt" => uvs.push(parse_floats(&args, 1, [0.0; 2], path, number)?),
+            "vn" => normals.push(parse_floats(&args, 3, [0.0; 3], path, number)?),
+            "f" => {
+                if args.len() < 3 {
+                    return Err(parse_error(path, number, "face with fewer than 3 vertices"));
+                }
+
+                let mut face = Vec::with_capacity(args.len());
+                for arg in &args {
+                    let mut refs = arg.split('/');
+                    let p = resolve_index(refs.next().unwrap_or(""), positions.len(), path, number)?;
+                    let t = match refs.next() {
+                        Some("") | None => None,
+                        Some(t) => Some(resolve_index(t, uvs.len(), path, number)?),
+                    };
+                    let n = match refs.next() {
+                        Some("") | None => None,
+                        Some(n) => Some(resolve_index(n, normals.len(), path, number)?),
+                    };
+                    face.push(builder.vertex((p, t, n), &positions, &uvs, &normals));
+                }
+
+                // fan triangulation, which is exact for convex polygons
+                for i in 1..face.len() - 1 {
+                    builder.data.push_triangle(face[0], face[i], face[i + 1]);
+                }
+            },
+            "o" => {
+                let name = Some(args.join(" "));
+                let material = builder.material;
+                restart(&mut model, &mut builder, name, None, material);
+            },
+            "g" => {
+                let name = (!args.is_empty()).then(|| args.join(" "));
+                let (object, material) = (builder.object.clone(), builder.material);
+                restart(&mut model, &mut builder, object, name, material);
+            },
+            "usemtl" => {
+                let name = args.join(" ");
+                let material = model.materials.iter().position(|m| m.name == name);
+                if material.is_none() {
+                    log::warn!("{}:{}: unknown material \"{}\".", path.display(), number, name);
+                }
+
+                if material != builder.material {
+                    let (object, group) = (builder.object.clone(), builder.group.clone());
+                    restart(&mut model, &mut builder, object, group, material);
+                }
+            },
+            "mtllib" => {
+                for library in &args {
+                    let library = base.join(library);
+                    log::debug!("Loading mtl library... {}", library.display());
+                    let source = std::fs::read_to_string(&library)?;
+                    model.materials.extend(parse_mtl(&source, &library)?);
+                }
+            },
+            "s" | "l" | "p" => {},
+            _ => log::debug!("{}:{}: ignoring unsupported statement \"{}\".", path.display(), number, keyword),
+        }
+    }
+
+    restart(&mut model, &mut builder, None, None, None);
+
+    Ok(model)
+}
+
+/// parse an `.mtl` material library read from `path`.
+pub fn parse_mtl(source: &str, path: &Path) -> Result<Vec<ObjMaterial>> {
+    let base = path.parent().unwrap_or(Path::new(""));
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+
+    for (number, line) in source.lines().enumerate() {
+        let number = number + 1;
+        let line = line.split('#').next().unwrap_or("").trim();
+
+        let mut parts = line.split_whitespace();
+        let Some(keyword) = parts.next() else { continue };
+        let args: Vec<&str> = parts.collect();
+
+        if keyword == "newmtl" {
+            materials.push(ObjMaterial::new(&args.join(" ")));
+            continue;
+        }
+
+        let Some(material) = materials.last_mut() else {
+            return Err(parse_error(path, number, format!("\"{}\" before any newmtl", keyword)));
+        };
+
+        // texture statements may carry options before the file name,
+        // the file name is always the last argument
+        let map = || -> Result<PathBuf> {
+            args.last()
+                .map(|file| base.join(file))
+                .ok_or_else(|| parse_error(path, number, "missing texture file name"))
+        };
+
+        match keyword {
+            "Ka" => material.ambient = parse_floats(&args, 3, [0.0; 3], path, number)?,
+            "Kd" => material.diffuse = parse_floats(&args, 3, [0.0; 3], path, number)?,
+            "Ks" => material.specular = parse_floats(&args, 3, [0.0; 3], path, number)?,
+            "Ke" => material.emissive = parse_floats(&args, 3, [0.0; 3], path, number)?,
+            "Ns" => material.shininess = parse_floats(&args, 1, [0.0], path, number)?[0],
+            "d" => material.dissolve = parse_floats(&args, 1, [0.0], path, number)?[0],
+            "Tr" => material.dissolve = 1.0 - parse_floats(&args, 1, [0.0], path, number)?[0],
+            "map_Kd" => material.diffuse_map = Some(map()?),
+            "map_Ks" => material.specular_map = Some(map()?),
+            "map_Bump" | "map_bump" | "bump" | "norm" => material.normal_map = Some(map()?),
+            "map_d" => material.alpha_map = Some(map()?),
+            "map_Ke" => material.emissive_map = Some(map()?),
+            _ => log::debug!("{}:{}: ignoring unsupported statement \"{}\".", path.display(), number, keyword),
+        }
+    }
+
+    Ok(materials)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAD: &str = "
+        v 0 0 0
+        v 1 0 0
+        v 1 1 0
+        v 0 1 0
+    ";
+
+    fn parse_str(source: &str) -> Result<ObjModel> {
+        parse(source, Path::new("test.obj"))
+    }
+
+    #[test]
+    fn triangulates_polygons_as_fans() {
+        let model = parse_str(&format!("{}\nf 1 2 3 4", QUAD)).unwrap();
+        assert_eq!(model.meshes.len(), 1);
+
+        let data = &model.meshes[0].data;
+        assert_eq!(data.vertex_count(), 4);
+        assert_eq!(data.indices, [0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn resolves_negative_indices() {
+        let model = parse_str(&format!("{}\nf -4 -3 -2\nv 5 5 5\nf -1 1 2", QUAD)).unwrap();
+        let data = &model.meshes[0].data;
+        assert_eq!(data.positions[..3], [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]]);
+        assert_eq!(data.positions[3], [5.0, 5.0, 5.0]);
+        assert_eq!(data.indices[3..], [3, 0, 1]);
+    }
+
+    #[test]
+    fn rejects_out_of_range_indices() {
+        for face in ["f 1 2 5", "f 0 1 2", "f -5 1 2", "f 1/1 2/1 3/1", "f 1//1 2//1 3//1"] {
+            let result = parse_str(&format!("{}\n{}", QUAD, face));
+            assert!(matches!(result, Err(Error::AssetParse(_))), "{}", face);
+        }
+        assert!(matches!(parse_str(&format!("{}\nf 1 2", QUAD)), Err(Error::AssetParse(_))));
+    }
+
+    #[test]
+    fn shares_identical_corners() {
+        let source = format!("{}\nvt 0 0\nvt 1 1\nf 1/1 2/1 3/1\nf 1/1 3/1 4/1\nf 1/2 2/1 3/1", QUAD);
+        let data = &parse_str(&source).unwrap().meshes[0].data;
+
+        // 1/1 and 3/1 are shared between the first faces, 1/2 is a new vertex
+        assert_eq!(data.vertex_count(), 5);
+        assert_eq!(data.indices, [0, 1, 2, 0, 2, 3, 4, 1, 2]);
+    }
+
+    #[test]
+    fn splits_objects_and_groups() {
+        let source = format!("{}\no first\nf 1 2 3\ng a\nf 1 3 4\ng b\no second\nf 2 3 4\ng\nf 1 2 3", QUAD);
+        let model = parse_str(&source).unwrap();
+
+        let names: Vec<_> = model.meshes.iter().map(|mesh| (mesh.object.as_deref(), mesh.group.as_deref())).collect();
+        assert_eq!(names, [
+            (Some("first"), None),
+            (Some("first"), Some("a")),
+            (Some("second"), None),
+            (Some("second"), None),
+        ]);
+        assert!(model.meshes.iter().all(|mesh| mesh.data.triangle_count() == 1));
+    }
+
+    #[test]
+    fn splits_materials() {
+        let dir = std::env::temp_dir().join(format!("ferra-obj-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("test.mtl"), "newmtl red\nKd 1 0 0\nnewmtl blue\nKd 0 0 1\n").unwrap();
+
+        let source = format!("mtllib test.mtl\n{}\nusemtl red\nf 1 2 3\nusemtl blue\nf 1 3 4\nusemtl blue\nf 2 3 4", QUAD);
+        let model = parse(&source, &dir.join("test.obj"));
+        std::fs::remove_dir_all(&dir).unwrap();
+        let model = model.unwrap();
+
+        assert_eq!(model.materials.len(), 2);
+        assert_eq!(model.meshes.len(), 2);
+        assert_eq!(model.material(&model.meshes[0]).unwrap().name, "red");
+        assert_eq!(model.material(&model.meshes[1]).unwrap().name, "blue");
+        assert_eq!(model.meshes[1].data.triangle_count(), 2);
+    }
+
+    #[test]
+    fn parses_materials() {
+        let source = "
+            newmtl shiny metal
+            Kd 0.5 0.25 1
+            Ns 64
+            Tr 0.25
+            map_Kd textures/albedo.png
+            bump -bm 0.5 textures/bump.png
+            map_d alpha.png
+        ";
+        let materials = parse_mtl(source, Path::new("models/test.mtl")).unwrap();
+        assert_eq!(materials.len(), 1);
+
+        let material = &materials[0];
+        assert_eq!(material.name, "shiny metal");
+        assert_eq!(material.diffuse, [0.5, 0.25, 1.0]);
+        assert_eq!(material.shininess, 64.0);
+        assert_eq!(material.dissolve, 0.75);
+        assert_eq!(material.diffuse_map.as_deref(), Some(Path::new("models/textures/albedo.png")));
+        assert_eq!(material.normal_map.as_deref(), Some(Path::new("models/textures/bump.png")));
+        assert_eq!(material.alpha_map.as_deref(), Some(Path::new("models/alpha.png")));
+    }
+
+    #[test]
+    fn rejects_statements_before_newmtl() {
+        assert!(matches!(parse_mtl("Kd 1 1 1", Path::new("test.mtl")), Err(Error::AssetParse(_))));
+        assert!(matches!(parse_mtl("newmtl a\nKd 1 x 1", Path::new("test.mtl")), Err(Error::AssetParse(_))));
+    }
+}
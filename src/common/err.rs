@@ -24,14 +24,44 @@ pub enum Error {
     #[error("Gl uniform location not found. {0}")]
     GlUniformLocation(String),
 
+    #[error("Gl uniform block not found. {0}")]
+    GlUniformBlock(String),
+
     #[error("Gl texture activation failed. {0}")]
     GlTextureActivation(String),
 
+    #[error("Gl framebuffer incomplete. {0}")]
+    GlFramebuffer(String),
+
+    #[error("Gl draw range out of bounds. {0}")]
+    GlDrawRange(String),
+
+    #[error("Material parameter invalid. {0}")]
+    MaterialParam(String),
+
+    #[error("Io error. {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Asset parsing failed. {0}")]
+    AssetParse(String),
+
+    #[error("Gltf import failed. {0}")]
+    Gltf(#[from] gltf::Error),
+
     #[error("Image error. {0}")]
     Image(#[from] image::ImageError),
 
     #[error("Image format error. {0}")]
-    ImageFormat(String)
+    ImageFormat(String),
+
+    #[error("Gamepad mappings invalid. {0}")]
+    GamepadMappings(String),
+
+    #[error("Input recording invalid. {0}")]
+    Recording(String),
+
+    #[error("Engine config invalid. {0}")]
+    Config(String)
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -1,4 +1,357 @@
-pub fn initialize_logs() {
-    env_logger::init(); 
-    log::debug!("Logging initialized.");   
+use std::{
+    backtrace::Backtrace,
+    collections::VecDeque,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    panic::PanicHookInfo,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+
+use jiff::Timestamp;
+use log::{Level, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+/// how the engine logs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    /// the filter in env_logger syntax, setting levels per module,
+    /// e.g. `warn,ferra=debug,ferra::display::context=trace`
+    pub filter: String,
+
+    /// write the log to timestamped files as well
+    pub file: bool,
+
+    /// where log files and crash reports go, a `logs` directory
+    /// next to the executable if `None`
+    pub directory: Option<PathBuf>,
+
+    /// the size in bytes a log file is rotated at
+    pub max_file_size: u64,
+
+    /// how many log files are kept, the oldest are removed
+    pub max_files: usize,
+
+    /// how many recent lines `history` keeps
+    pub history: usize,
+
+    /// write a crash report when the engine panics
+    pub crash_reports: bool,
 }
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            filter: if cfg!(debug_assertions) { "debug" } else { "info" }.to_string(),
+            file: false,
+            directory: None,
+            max_file_size: 8 * 1024 * 1024,
+            max_files: 5,
+            history: 1000,
+            crash_reports: true,
+        }
+    }
+}
+
+impl LogSettings {
+    /// the directory log files and crash reports go to.
+    pub fn directory(&self) -> PathBuf {
+        self.directory.clone().unwrap_or_else(|| {
+            let exe = std::env::current_exe().ok();
+            let dir = exe.as_deref().and_then(Path::parent).unwrap_or(Path::new("."));
+            dir.join("logs")
+        })
+    }
+}
+
+/// a logged line, as kept by the history.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLine {
+    pub time: Timestamp,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for LogLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{} {:<5} {}] {}", self.time, self.level, self.target, self.message)
+    }
+}
+
+/// the most recent log lines, as for an on-screen console.
+#[derive(Debug, Default)]
+pub struct LogHistory {
+    lines: Mutex<VecDeque<LogLine>>,
+    capacity: usize,
+}
+
+impl LogHistory {
+    fn new(capacity: usize) -> Self {
+        Self { lines: Mutex::new(VecDeque::with_capacity(capacity)), capacity }
+    }
+
+    fn push(&self, line: LogLine) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut lines = self.lines.lock().unwrap_or_else(|err| err.into_inner());
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// the last `count` lines, oldest first.
+    pub fn recent(&self, count: usize) -> Vec<LogLine> {
+        let lines = self.lines.lock().unwrap_or_else(|err| err.into_inner());
+        lines.iter().skip(lines.len().saturating_sub(count)).cloned().collect()
+    }
+
+    /// the last `count` lines at `level` or more severe, oldest first.
+    pub fn recent_at(&self, level: Level, count: usize) -> Vec<LogLine> {
+        let lines = self.lines.lock().unwrap_or_else(|err| err.into_inner());
+        let mut recent: Vec<_> = lines.iter().rev().filter(|line| line.level <= level).take(count).cloned().collect();
+        recent.reverse();
+        recent
+    }
+
+    pub fn clear(&self) {
+        self.lines.lock().unwrap_or_else(|err| err.into_inner()).clear();
+    }
+}
+
+/// a log file that moves on to a new timestamped file when full.
+struct RotatingFile {
+    directory: PathBuf,
+    writer: BufWriter<File>,
+    written: u64,
+    max_size: u64,
+    max_files: usize,
+}
+
+impl RotatingFile {
+    fn open(directory: PathBuf, max_size: u64, max_files: usize) -> std::io::Result<Self> {
+        fs::create_dir_all(&directory)?;
+        let writer = BufWriter::new(File::create(new_file_path(&directory, "ferra", "log"))?);
+        let file = Self { directory, writer, written: 0, max_size, max_files };
+        file.prune();
+        Ok(file)
+    }
+
+    fn write_line(&mut self, line: &LogLine) -> std::io::Result<()> {
+        if self.written >= self.max_size {
+            self.rotate()?;
+        }
+        let line = format!("{}\n", line);
+        self.writer.write_all(line.as_bytes())?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.writer.flush()?;
+        self.writer = BufWriter::new(File::create(new_file_path(&self.directory, "ferra", "log"))?);
+        self.written = 0;
+        self.prune();
+        Ok(())
+    }
+
+    /// remove the oldest log files over `max_files`.
+    fn prune(&self) {
+        let Ok(entries) = fs::read_dir(&self.directory) else {
+            return;
+        };
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+                name.starts_with("ferra-") && name.ends_with(".log")
+            })
+            .collect();
+
+        files.sort();
+        for path in files.iter().take(files.len().saturating_sub(self.max_files.max(1))) {
+            if let Err(err) = fs::remove_file(path) {
+                eprintln!("Failed to remove old log file {}: {}", path.display(), err);
+            }
+        }
+    }
+}
+
+/// a path in `directory` named after the current time, that is not
+/// taken yet. names sort by the time they were made.
+fn new_file_path(directory: &Path, prefix: &str, extension: &str) -> PathBuf {
+    let stamp = Timestamp::now().strftime("%Y%m%d-%H%M%S-%3f");
+    (0..)
+        .map(|n| directory.join(format!("{}-{}-{:03}.{}", prefix, stamp, n, extension)))
+        .find(|path| !path.exists())
+        .unwrap_or_else(|| unreachable!("some file name is free"))
+}
+
+/// logs to stderr through env_logger, and to the history and
+/// the log file if there is one.
+struct Logger {
+    stderr: env_logger::Logger,
+    file: Mutex<Option<RotatingFile>>,
+    history: LogHistory,
+    settings: LogSettings,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.stderr.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.stderr.matches(record) {
+            return;
+        }
+        self.stderr.log(record);
+
+        let line = LogLine {
+            time: Timestamp::now(),
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+
+        let mut file = self.file.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(sink) = file.as_mut()
+            && let Err(err) = sink.write_line(&line)
+        {
+            // keep logging to stderr rather than fail every line
+            eprintln!("Failed to write the log file, disabling it: {}", err);
+            *file = None;
+        }
+        drop(file);
+
+        self.history.push(line);
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+        if let Some(sink) = self.file.lock().unwrap_or_else(|err| err.into_inner()).as_mut() {
+            let _ = sink.writer.flush();
+        }
+    }
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// what crash reports tell about the environment, e.g. the gl renderer.
+static CRASH_INFO: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// log as described by `settings`, then install the panic hook
+/// writing crash reports. only the first call has an effect.
+pub fn initialize_logs(settings: &LogSettings) -> Result<()> {
+    if LOGGER.get().is_some() {
+        log::warn!("Logging is already initialized.");
+        return Ok(());
+    }
+
+    let file = if settings.file {
+        let directory = settings.directory();
+        let file = RotatingFile::open(directory.clone(), settings.max_file_size, settings.max_files)
+            .map_err(|err| Error::Config(format!("log directory {}: {}", directory.display(), err)))?;
+        Some(file)
+    } else {
+        None
+    };
+
+    let stderr = env_logger::Builder::new().parse_filters(&settings.filter).build();
+    let max_level = stderr.filter();
+    let logger = LOGGER.get_or_init(|| Logger {
+        stderr,
+        file: Mutex::new(file),
+        history: LogHistory::new(settings.history),
+        settings: settings.clone(),
+    });
+
+    if log::set_logger(logger).is_err() {
+        return Err(Error::Config("another logger is already installed.".to_string()));
+    }
+    log::set_max_level(max_level);
+
+    if settings.crash_reports {
+        install_panic_hook();
+    }
+
+    log::debug!("Logging initialized. ({})", settings.filter);
+    if settings.file {
+        log::info!("Logging to files in {}.", settings.directory().display());
+    }
+    Ok(())
+}
+
+/// the recent log lines, if logging was initialized.
+pub fn history() -> Option<&'static LogHistory> {
+    LOGGER.get().map(|logger| &logger.history)
+}
+
+/// add a line to every crash report, replacing one with the same key.
+pub fn set_crash_info(key: &str, value: impl Into<String>) {
+    let mut info = CRASH_INFO.lock().unwrap_or_else(|err| err.into_inner());
+    let value = value.into();
+    match info.iter_mut().find(|(k, _)| k == key) {
+        Some((_, v)) => *v = value,
+        None => info.push((key.to_string(), value)),
+    }
+}
+
+/// on a panic, log it, write a crash report and flush the log
+/// before the previous hook runs.
+fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        log::error!("Panicked: {}", info);
+
+        if let Some(logger) = LOGGER.get() {
+            match write_crash_report(logger, info) {
+                Ok(path) => log::error!("Wrote crash report to {}.", path.display()),
+                Err(err) => log::error!("Failed to write crash report: {}", err),
+            }
+        }
+        log::logger().flush();
+
+        previous(info);
+    }));
+}
+
+fn write_crash_report(logger: &Logger, info: &PanicHookInfo) -> std::io::Result<PathBuf> {
+    let directory = logger.settings.directory();
+    fs::create_dir_all(&directory)?;
+    let path = new_file_path(&directory, "crash", "txt");
+
+    let mut report = BufWriter::new(File::create(&path)?);
+    writeln!(report, "ferra {} crashed at {}.", env!("CARGO_PKG_VERSION"), Timestamp::now())?;
+    writeln!(report)?;
+    writeln!(report, "thread: {}", std::thread::current().name().unwrap_or("<unnamed>"))?;
+    writeln!(report, "panic: {}", info)?;
+    writeln!(report)?;
+
+    for (key, value) in CRASH_INFO.lock().unwrap_or_else(|err| err.into_inner()).iter() {
+        writeln!(report, "{}: {}", key, value)?;
+    }
+    writeln!(report, "os: {} {}", std::env::consts::OS, std::env::consts::ARCH)?;
+    writeln!(report)?;
+
+    writeln!(report, "backtrace:")?;
+    writeln!(report, "{}", Backtrace::force_capture())?;
+
+    writeln!(report, "recent log:")?;
+    for line in logger.history.recent(100) {
+        writeln!(report, "{}", line)?;
+    }
+
+    report.flush()?;
+    Ok(path)
+}
+
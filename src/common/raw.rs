@@ -1,5 +1,6 @@
 pub trait AsRaw<T>
 {
+    #[allow(clippy::wrong_self_convention)]
     fn as_raw(self) -> Vec<T>;
 }
 
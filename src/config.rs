@@ -0,0 +1,525 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::log::LogSettings,
+    display::{
+        debug::GlDebugSettings,
+        win::{DisplayMode, WindowSettings},
+    },
+    input::record::InputSource,
+    time::TimeSettings,
+    Error, Result,
+};
+
+/// the config file `EngineConfig::resolve` looks for by default.
+pub const DEFAULT_CONFIG_PATH: &str = "ferra.toml";
+
+/// the prefix of the environment variables overriding the config,
+/// e.g. `FERRA_WIDTH` for `--width`.
+const ENV_PREFIX: &str = "FERRA_";
+
+/// the overrides taken from the environment and the command line.
+/// the environment variable of each is its name in upper snake case.
+const OVERRIDES: [&str; 15] = [
+    "title",
+    "width",
+    "height",
+    "fullscreen",
+    "borderless",
+    "windowed",
+    "vsync",
+    "log",
+    "log-file",
+    "gl-debug",
+    "assets",
+    "tick-rate",
+    "max-fps",
+    "record",
+    "replay",
+];
+
+/// how `run` sets up the engine, the window and the frame.
+///
+/// usually resolved in layers: the engine defaults, then a ferra.toml
+/// or .ron file, then `FERRA_*` environment variables and finally
+/// command-line arguments, e.g.
+///
+/// ```toml
+/// assets = "res"
+///
+/// [log]
+/// filter = "info,ferra=debug"
+/// file = true
+///
+/// [window]
+/// title = "Hello World"
+/// width = 1280
+/// height = 720
+/// mode = { Borderless = { monitor = 0 } }
+///
+/// [time]
+/// tick_rate = 60.0
+/// max_fps = 240.0
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineConfig {
+    pub window: WindowSettings,
+
+    /// the color the window is cleared to before `App::render`,
+    /// or `None` to leave clearing to the app
+    pub clear_color: Option<[f32; 4]>,
+
+    pub time: TimeSettings,
+
+    pub log: LogSettings,
+
+    pub gl_debug: GlDebugSettings,
+
+    /// the directory asset paths are relative to
+    pub assets: PathBuf,
+
+    /// a gamecontrollerdb.txt with gamepad mappings to add
+    /// to those built into glfw
+    pub gamepad_mappings: Option<PathBuf>,
+
+    /// whether to take input live, record it or replay a recording.
+    /// only set from code or the command line.
+    #[serde(skip)]
+    pub input: InputSource,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            window: WindowSettings::default(),
+            clear_color: Some([0.0, 0.0, 0.0, 1.0]),
+            time: TimeSettings::default(),
+            log: LogSettings::default(),
+            gl_debug: GlDebugSettings::default(),
+            assets: PathBuf::from("res"),
+            gamepad_mappings: None,
+            input: InputSource::Live,
+        }
+    }
+}
+
+impl EngineConfig {
+    pub fn new(title: &str) -> Self {
+        let mut config = Self::default();
+        config.window.title = title.to_string();
+        config
+    }
+
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.window.width = width;
+        self.window.height = height;
+        self
+    }
+
+    pub fn with_display_mode(mut self, mode: DisplayMode) -> Self {
+        self.window.mode = mode;
+        self
+    }
+
+    pub fn with_clear_color(mut self, clear_color: Option<[f32; 4]>) -> Self {
+        self.clear_color = clear_color;
+        self
+    }
+
+    /// run `tick_rate` fixed updates per second, at most
+    /// `max_fixed_steps` of them in one frame.
+    pub fn with_tick_rate(mut self, tick_rate: f64, max_fixed_steps: u32) -> Self {
+        self.time.tick_rate = tick_rate;
+        self.time.max_fixed_steps = max_fixed_steps;
+        self
+    }
+
+    pub fn with_vsync(mut self, vsync: bool) -> Self {
+        self.window.vsync = vsync;
+        self
+    }
+
+    pub fn with_input(mut self, input: InputSource) -> Self {
+        self.input = input;
+        self
+    }
+
+    /// cap the frame rate while vsync is off.
+    pub fn with_max_fps(mut self, max_fps: Option<f64>) -> Self {
+        self.time.max_fps = max_fps;
+        self
+    }
+
+    /// log with `filter`, in env_logger syntax.
+    pub fn with_log(mut self, filter: &str) -> Self {
+        self.log.filter = filter.to_string();
+        self
+    }
+
+    pub fn with_assets(mut self, assets: impl Into<PathBuf>) -> Self {
+        self.assets = assets.into();
+        self
+    }
+
+    /// the path of an asset under the asset directory.
+    pub fn asset_path(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.assets.join(path)
+    }
+
+    pub fn from_toml(source: &str) -> Result<Self> {
+        toml::from_str(source).map_err(|err| Error::Config(format!("Invalid toml config. {}", err)))
+    }
+
+    pub fn from_ron(source: &str) -> Result<Self> {
+        ron::from_str(source).map_err(|err| Error::Config(format!("Invalid ron config. {}", err)))
+    }
+
+    /// load a config from a .toml or .ron file. fields it
+    /// leaves out keep the engine defaults.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        log::debug!("Loading engine config from file... {}", path.display());
+
+        let source = std::fs::read_to_string(path)
+            .map_err(|err| Error::Config(format!("{}: could not be read. {}", path.display(), err)))?;
+        let config = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&source),
+            Some("ron") => Self::from_ron(&source),
+            _ => Err(Error::Config("unknown config format, expected .toml or .ron.".to_string())),
+        };
+        config.map_err(|err| match err {
+            Error::Config(msg) => Error::Config(format!("{}: {}", path.display(), msg)),
+            err => err,
+        })
+    }
+
+    /// the config of this process: `path`, or the file given with
+    /// `--config` or `FERRA_CONFIG`, overridden by the environment
+    /// and the command-line arguments, then validated.
+    ///
+    /// a missing file at the default path is skipped, one that was
+    /// asked for explicitly is an error.
+    pub fn resolve(path: impl AsRef<Path>) -> Result<Self> {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let explicit = args
+            .iter()
+            .enumerate()
+            .find_map(|(i, arg)| match arg.strip_prefix("--config") {
+                Some("") => args.get(i + 1).map(PathBuf::from),
+                Some(value) => value.strip_prefix('=').map(PathBuf::from),
+                None => None,
+            })
+            .or_else(|| std::env::var_os(format!("{}CONFIG", ENV_PREFIX)).map(PathBuf::from));
+
+        let mut config = match explicit {
+            Some(path) => Self::load(path)?,
+            None if path.as_ref().exists() => Self::load(path)?,
+            None => Self::default(),
+        };
+
+        config.apply_env()?;
+        config.apply_args(args)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// apply the `FERRA_*` environment variables that are set.
+    /// `RUST_LOG` is taken as the log filter unless `FERRA_LOG` is set.
+    pub fn apply_env(&mut self) -> Result<()> {
+        if let Ok(filter) = std::env::var("RUST_LOG") {
+            self.log.filter = filter;
+        }
+
+        for key in OVERRIDES {
+            let name = format!("{}{}", ENV_PREFIX, key.to_uppercase().replace('-', "_"));
+            if let Ok(value) = std::env::var(&name) {
+                self.set(key, Some(&value)).map_err(|err| with_source(err, &name))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// apply command-line arguments, given as `--key value` or
+    /// `--key=value`. flags may leave out the value, so `--vsync`,
+    /// `--vsync true` and `--vsync=true` are the same. `--no-vsync`
+    /// turns vsync off and `--headless` makes a replay headless.
+    pub fn apply_args(&mut self, args: impl IntoIterator<Item = String>) -> Result<()> {
+        let mut args = args.into_iter().peekable();
+        let mut headless = false;
+
+        while let Some(arg) = args.next() {
+            let Some(option) = arg.strip_prefix("--") else {
+                return Err(Error::Config(format!("Unexpected argument `{}`, expected `--key value`.", arg)));
+            };
+            let (key, inline) = match option.split_once('=') {
+                Some((key, value)) => (key, Some(value.to_string())),
+                None => (option, None),
+            };
+
+            match key {
+                "headless" => {
+                    headless = true;
+                    continue;
+                },
+                "no-vsync" => {
+                    self.window.vsync = false;
+                    continue;
+                },
+                "config" => {
+                    // taken by `resolve` already
+                    if inline.is_none() {
+                        args.next();
+                    }
+                    continue;
+                },
+                _ => {},
+            }
+
+            let value = match inline {
+                Some(value) => Some(value),
+                None if takes_value(key) => args.next_if(|next| !next.starts_with("--")),
+                None => args.next_if(|next| flag(Some(next)).is_ok()),
+            };
+            self.set(key, value.as_deref()).map_err(|err| with_source(err, &format!("--{}", key)))?;
+        }
+
+        if headless {
+            match &mut self.input {
+                InputSource::Replay { headless, .. } => *headless = true,
+                _ => return Err(Error::Config("--headless: only applies to a --replay.".to_string())),
+            }
+        }
+        Ok(())
+    }
+
+    /// set one of the overridable values by name, as used by the
+    /// environment and the command line. flags without a value are set.
+    pub fn set(&mut self, key: &str, value: Option<&str>) -> Result<()> {
+        match key {
+            "title" => self.window.title = required(value)?.to_string(),
+            "width" => self.window.width = parse(value)?,
+            "height" => self.window.height = parse(value)?,
+            "fullscreen" => {
+                if flag(value)? {
+                    self.window.mode = DisplayMode::Fullscreen { monitor: 0, video_mode: None };
+                }
+            },
+            "borderless" => {
+                if flag(value)? {
+                    self.window.mode = DisplayMode::Borderless { monitor: 0 };
+                }
+            },
+            "windowed" => {
+                if flag(value)? {
+                    self.window.mode = DisplayMode::Windowed;
+                }
+            },
+            "vsync" => self.window.vsync = flag(value)?,
+            "log" => self.log.filter = required(value)?.to_string(),
+            "log-file" => self.log.file = flag(value)?,
+            "gl-debug" => self.gl_debug.enabled = flag(value)?,
+            "assets" => self.assets = PathBuf::from(required(value)?),
+            "tick-rate" => self.time.tick_rate = parse(value)?,
+            "max-fps" => {
+                self.time.max_fps = match required(value)? {
+                    "none" | "off" => None,
+                    value => Some(parse(Some(value))?),
+                }
+            },
+            "record" => self.input = InputSource::Record(PathBuf::from(required(value)?)),
+            "replay" => self.input = InputSource::Replay { path: PathBuf::from(required(value)?), headless: false },
+            _ => {
+                return Err(Error::Config(format!(
+                    "unknown option `{}`, expected one of: {}.",
+                    key,
+                    OVERRIDES.join(", ")
+                )));
+            },
+        }
+        Ok(())
+    }
+
+    /// check the values make sense together, before anything is created.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |msg: String| Err(Error::Config(msg));
+
+        if self.window.width == 0 || self.window.height == 0 {
+            return invalid(format!(
+                "window size {}x{} is empty, width and height have to be at least 1.",
+                self.window.width, self.window.height
+            ));
+        }
+        if !self.time.tick_rate.is_finite() || self.time.tick_rate <= 0.0 {
+            return invalid(format!("tick rate {} has to be a positive number.", self.time.tick_rate));
+        }
+        if self.time.max_fixed_steps == 0 {
+            return invalid("max fixed steps has to be at least 1.".to_string());
+        }
+        if let Some(max_fps) = self.time.max_fps
+            && (!max_fps.is_finite() || max_fps <= 0.0)
+        {
+            return invalid(format!("max fps {} has to be a positive number.", max_fps));
+        }
+        if let Some(color) = self.clear_color
+            && color.iter().any(|c| !(0.0..=1.0).contains(c))
+        {
+            return invalid(format!("clear color {:?} has to be in [0, 1].", color));
+        }
+
+        validate_log_filter(&self.log.filter)?;
+        if self.log.file && (self.log.max_files == 0 || self.log.max_file_size == 0) {
+            return invalid("log files need max files and max file size of at least 1.".to_string());
+        }
+
+        if !self.assets.is_dir() {
+            return invalid(format!("asset directory {} does not exist.", self.assets.display()));
+        }
+        Ok(())
+    }
+}
+
+/// whether an override reads the next argument as its value.
+fn takes_value(key: &str) -> bool {
+    !matches!(key, "fullscreen" | "borderless" | "windowed" | "vsync" | "log-file" | "gl-debug")
+}
+
+fn with_source(err: Error, source: &str) -> Error {
+    match err {
+        Error::Config(msg) => Error::Config(format!("{}: {}", source, msg)),
+        err => err,
+    }
+}
+
+fn required(value: Option<&str>) -> Result<&str> {
+    value.ok_or_else(|| Error::Config("a value is required.".to_string()))
+}
+
+fn parse<T: FromStr>(value: Option<&str>) -> Result<T> {
+    let value = required(value)?;
+    value
+        .parse()
+        .map_err(|_| Error::Config(format!("`{}` is not a valid {}.", value, std::any::type_name::<T>())))
+}
+
+fn flag(value: Option<&str>) -> Result<bool> {
+    match value {
+        None | Some("1" | "true" | "yes" | "on") => Ok(true),
+        Some("0" | "false" | "no" | "off") => Ok(false),
+        Some(value) => Err(Error::Config(format!("`{}` is not a valid flag, expected true or false.", value))),
+    }
+}
+
+/// check every directive of an env_logger filter names a known level,
+/// as env_logger itself only warns about them on stderr.
+fn validate_log_filter(filter: &str) -> Result<()> {
+    // a trailing `/regex` filters messages, not levels
+    let directives = filter.split('/').next().unwrap_or_default();
+
+    for directive in directives.split(',').map(str::trim) {
+        // a bare directive is either a level or a module enabled at every level
+        let Some((_, level)) = directive.split_once('=') else {
+            continue;
+        };
+        if log::LevelFilter::from_str(level.trim()).is_err() {
+            return Err(Error::Config(format!(
+                "log filter `{}`: unknown level `{}`, expected off, error, warn, info, debug or trace.",
+                filter, level
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> impl Iterator<Item = String> + '_ {
+        args.split_whitespace().map(str::to_string)
+    }
+
+    fn config() -> EngineConfig {
+        EngineConfig::default().with_assets(".")
+    }
+
+    #[test]
+    fn parses_values_and_flags() {
+        let mut config = config();
+        config.apply_args(args("--width 640 --height=480 --fullscreen --vsync false --gl-debug --title demo")).unwrap();
+        assert_eq!((config.window.width, config.window.height), (640, 480));
+        assert_eq!(config.window.mode, DisplayMode::Fullscreen { monitor: 0, video_mode: None });
+        assert!(!config.window.vsync);
+        assert!(config.gl_debug.enabled);
+        assert_eq!(config.window.title, "demo");
+
+        config.apply_args(args("--vsync --log-file=off --gl-debug no --max-fps none")).unwrap();
+        assert!(config.window.vsync);
+        assert!(!config.log.file);
+        assert!(!config.gl_debug.enabled);
+        assert_eq!(config.time.max_fps, None);
+
+        config.apply_args(args("--no-vsync")).unwrap();
+        assert!(!config.window.vsync);
+    }
+
+    #[test]
+    fn rejects_bad_args() {
+        for bad in ["width 640", "--width", "--width wide", "--vsync=maybe", "--unknown 1", "--vsync maybe"] {
+            assert!(matches!(config().apply_args(args(bad)), Err(Error::Config(_))), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn later_layers_override_earlier_ones() {
+        let mut config = EngineConfig::from_toml("assets = \".\"\n[window]\nwidth = 320\nheight = 200").unwrap();
+        config.set("width", Some("640")).unwrap();
+        config.set("height", Some("400")).unwrap();
+        config.apply_args(args("--width 1280")).unwrap();
+        assert_eq!((config.window.width, config.window.height), (1280, 400));
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn headless_needs_a_replay() {
+        assert!(matches!(config().apply_args(args("--headless")), Err(Error::Config(_))));
+        assert!(matches!(config().apply_args(args("--record input.rec --headless")), Err(Error::Config(_))));
+
+        let mut config = config();
+        config.apply_args(args("--headless --replay input.rec")).unwrap();
+        assert_eq!(config.input, InputSource::Replay { path: PathBuf::from("input.rec"), headless: true });
+    }
+
+    #[test]
+    fn validates() {
+        config().validate().unwrap();
+
+        let invalid = [
+            config().with_size(0, 600),
+            config().with_tick_rate(0.0, 5),
+            config().with_tick_rate(60.0, 0),
+            config().with_max_fps(Some(f64::NAN)),
+            config().with_clear_color(Some([0.0, 0.0, 2.0, 1.0])),
+            config().with_log("ferra=loud"),
+            config().with_assets("does/not/exist"),
+        ];
+        for config in invalid {
+            assert!(matches!(config.validate(), Err(Error::Config(_))));
+        }
+    }
+
+    #[test]
+    fn validates_log_filters() {
+        for filter in ["", "info", "warn,ferra=debug", "ferra", "ferra::display=TRACE, glfw=off", "info/some=regex"] {
+            assert!(validate_log_filter(filter).is_ok(), "{}", filter);
+        }
+        for filter in ["ferra=loud", "info,ferra=", "ferra = verbose/x"] {
+            assert!(matches!(validate_log_filter(filter), Err(Error::Config(_))), "{}", filter);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use nalgebra::{Isometry3, Matrix4, Orthographic3, Perspective3, Point2, Point3, Translation3, Vector3};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective {
+        /// vertical field of view in radians
+        fovy: f32,
+        near: f32,
+        far: f32,
+    },
+    Orthographic {
+        /// the visible height in world units, the width
+        /// follows from the aspect ratio
+        height: f32,
+        near: f32,
+        far: f32,
+    },
+}
+
+/// a ray in world space, e.g. from the camera through the cursor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+
+    /// normalized direction
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    pub fn at(&self, t: f32) -> Point3<f32> {
+        self.origin + self.direction * t
+    }
+
+    /// the distance along the ray to the plane through `point`
+    /// with `normal`, if the ray hits it in front of its origin.
+    pub fn intersect_plane(&self, point: &Point3<f32>, normal: &Vector3<f32>) -> Option<f32> {
+        let denom = normal.dot(&self.direction);
+        if denom.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let t = normal.dot(&(point - self.origin)) / denom;
+        (t >= 0.0).then_some(t)
+    }
+}
+
+/// a camera looking down its local -z axis with +y up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Camera {
+    /// the camera pose, i.e. the transform from camera to world space
+    pub pose: Isometry3<f32>,
+
+    pub projection: Projection,
+
+    /// viewport width divided by height
+    pub aspect: f32,
+}
+
+impl Camera {
+    pub fn perspective(fovy: f32, aspect: f32, near: f32, far: f32) -> Self {
+        Self { pose: Isometry3::identity(), projection: Projection::Perspective { fovy, near, far }, aspect }
+    }
+
+    pub fn orthographic(height: f32, aspect: f32, near: f32, far: f32) -> Self {
+        Self { pose: Isometry3::identity(), projection: Projection::Orthographic { height, near, far }, aspect }
+    }
+
+    /// update the aspect ratio after the framebuffer was resized.
+    pub fn set_viewport(&mut self, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            self.aspect = width as f32 / height as f32;
+        }
+    }
+
+    pub fn look_at(&mut self, eye: &Point3<f32>, target: &Point3<f32>, up: &Vector3<f32>) {
+        self.pose = Isometry3::look_at_rh(eye, target, up).inverse();
+    }
+
+    pub fn position(&self) -> Point3<f32> {
+        self.pose.translation.vector.into()
+    }
+
+    pub fn set_position(&mut self, position: &Point3<f32>) {
+        self.pose.translation = Translation3::from(position.coords);
+    }
+
+    pub fn forward(&self) -> Vector3<f32> {
+        self.pose.rotation * -Vector3::z()
+    }
+
+    pub fn right(&self) -> Vector3<f32> {
+        self.pose.rotation * Vector3::x()
+    }
+
+    pub fn up(&self) -> Vector3<f32> {
+        self.pose.rotation * Vector3::y()
+    }
+
+    pub fn view_matrix(&self) -> Matrix4<f32> {
+        self.pose.inverse().to_homogeneous()
+    }
+
+    pub fn projection_matrix(&self) -> Matrix4<f32> {
+        match self.projection {
+            Projection::Perspective { fovy, near, far } => {
+                Perspective3::new(self.aspect, fovy, near, far).to_homogeneous()
+            },
+            Projection::Orthographic { height, near, far } => {
+                let (h, w) = (height * 0.5, height * 0.5 * self.aspect);
+                Orthographic3::new(-w, w, -h, h, near, far).to_homogeneous()
+            },
+        }
+    }
+
+    /// the distances of the near and far clip planes.
+    pub fn clip_planes(&self) -> (f32, f32) {
+        match self.projection {
+            Projection::Perspective { near, far, .. } | Projection::Orthographic { near, far, .. } => (near, far),
+        }
+    }
+
+    pub fn view_projection(&self) -> Matrix4<f32> {
+        self.projection_matrix() * self.view_matrix()
+    }
+
+    /// the ray through a point in window coordinates, with the
+    /// origin in the top left corner like glfw cursor positions.
+    pub fn screen_to_world_ray(&self, screen: &Point2<f32>, width: f32, height: f32) -> Ray {
+        let ndc_x = 2.0 * screen.x / width - 1.0;
+        let ndc_y = 1.0 - 2.0 * screen.y / height;
+
+        let inverse = self.view_projection().try_inverse().unwrap_or_else(Matrix4::identity);
+        let near = inverse.transform_point(&Point3::new(ndc_x, ndc_y, -1.0));
+        let far = inverse.transform_point(&Point3::new(ndc_x, ndc_y, 1.0));
+
+        Ray { origin: near, direction: (far - near).normalize() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    fn assert_close(a: Vector3<f32>, b: Vector3<f32>) {
+        assert!((a - b).norm() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    fn looking_at_origin(mut camera: Camera) -> Camera {
+        camera.look_at(&Point3::new(0.0, 0.0, 10.0), &Point3::origin(), &Vector3::y());
+        camera
+    }
+
+    #[test]
+    fn intersects_planes_in_front() {
+        let ray = Ray { origin: Point3::new(0.0, 2.0, 0.0), direction: -Vector3::y() };
+        assert_eq!(ray.intersect_plane(&Point3::origin(), &Vector3::y()), Some(2.0));
+        assert_eq!(ray.intersect_plane(&Point3::origin(), &-Vector3::y()), Some(2.0));
+        assert_eq!(ray.intersect_plane(&Point3::new(0.0, 3.0, 0.0), &Vector3::y()), None);
+        assert_eq!(ray.intersect_plane(&Point3::origin(), &Vector3::x()), None);
+        assert_eq!(ray.at(2.0), Point3::origin());
+    }
+
+    #[test]
+    fn screen_centre_looks_forward() {
+        let camera = looking_at_origin(Camera::perspective(FRAC_PI_2, 2.0, 0.1, 100.0));
+        let ray = camera.screen_to_world_ray(&Point2::new(400.0, 200.0), 800.0, 400.0);
+
+        assert_close(ray.direction, camera.forward());
+        assert_close(ray.origin.coords, Vector3::new(0.0, 0.0, 9.9));
+
+        let t = ray.intersect_plane(&Point3::origin(), &Vector3::z()).unwrap();
+        assert!((t - 9.9).abs() < 1e-4);
+        assert_close(ray.at(t).coords, Vector3::zeros());
+    }
+
+    #[test]
+    fn screen_edges_follow_the_field_of_view() {
+        // with a 90° field of view the top edge is as far up as the plane is away
+        let camera = looking_at_origin(Camera::perspective(FRAC_PI_2, 2.0, 0.1, 100.0));
+        let ray = camera.screen_to_world_ray(&Point2::new(800.0, 0.0), 800.0, 400.0);
+        let t = ray.intersect_plane(&Point3::origin(), &Vector3::z()).unwrap();
+        assert_close(ray.at(t).coords, Vector3::new(20.0, 10.0, 0.0));
+
+        let camera = looking_at_origin(Camera::orthographic(4.0, 2.0, 0.1, 100.0));
+        let ray = camera.screen_to_world_ray(&Point2::new(0.0, 400.0), 800.0, 400.0);
+        assert_close(ray.direction, -Vector3::z());
+        let t = ray.intersect_plane(&Point3::origin(), &Vector3::z()).unwrap();
+        assert_close(ray.at(t).coords, Vector3::new(-4.0, -2.0, 0.0));
+    }
+}
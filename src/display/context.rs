@@ -0,0 +1,312 @@
+use std::{cell::{Cell, RefCell}, collections::HashMap, ops::Deref, panic::Location};
+
+use gl33::{GLenum, GlFns};
+
+use super::{debug::GlDebug, state::{RenderState, StateCache}};
+
+/// the most texture units tracked by the context. fewer are
+/// used when the driver has fewer, see `GlContext::texture_units`.
+pub const MAX_TEXTURE_UNITS: u32 = 32;
+
+/// how many calls of one kind were forwarded to opengl
+/// and how many were skipped because they were redundant.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CallCounter {
+    pub issued: u64,
+    pub elided: u64,
+}
+
+impl CallCounter {
+    fn record(&mut self, issued: bool) {
+        if issued {
+            self.issued += 1;
+        } else {
+            self.elided += 1;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContextStats {
+    pub programs: CallCounter,
+    pub vertex_arrays: CallCounter,
+    pub buffers: CallCounter,
+    pub texture_units: CallCounter,
+    pub textures: CallCounter,
+    pub render_states: CallCounter,
+}
+
+impl ContextStats {
+    pub fn issued(&self) -> u64 {
+        self.counters().iter().map(|c| c.issued).sum()
+    }
+
+    pub fn elided(&self) -> u64 {
+        self.counters().iter().map(|c| c.elided).sum()
+    }
+
+    fn counters(&self) -> [CallCounter; 6] {
+        [self.programs, self.vertex_arrays, self.buffers, self.texture_units, self.textures, self.render_states]
+    }
+}
+
+/// wraps the loaded opengl functions and tracks the
+/// object bindings, so that redundant binds are skipped.
+///
+/// the context dereferences to `GlFns`, so it can be passed
+/// wherever raw gl access is needed. calls made that way are
+/// not tracked; call `invalidate` after binding objects directly.
+pub struct GlContext {
+    gl: GlFns,
+
+    program: Cell<Option<u32>>,
+    vertex_array: Cell<Option<u32>>,
+
+    /// buffer bound per target
+    buffers: RefCell<HashMap<u32, u32>>,
+
+    active_unit: Cell<Option<u32>>,
+
+    /// texture bound per (unit, target)
+    textures: RefCell<HashMap<(u32, u32), u32>>,
+
+    /// 1x1 white textures per target, bound to unset samplers
+    default_textures: RefCell<HashMap<u32, u32>>,
+
+    /// the texture units usable by fragment shaders
+    texture_units: u32,
+
+    state: RefCell<StateCache>,
+    stats: Cell<ContextStats>,
+
+    debug: GlDebug,
+}
+
+impl Deref for GlContext {
+    type Target = GlFns;
+
+    fn deref(&self) -> &GlFns {
+        &self.gl
+    }
+}
+
+impl GlContext {
+    pub fn new(gl: GlFns) -> Self {
+        let mut units = 0;
+        unsafe { gl.GetIntegerv(gl33::GL_MAX_TEXTURE_IMAGE_UNITS, &mut units) };
+        // opengl 3.3 guarantees 16 units
+        let texture_units = (units.max(16) as u32).min(MAX_TEXTURE_UNITS);
+        log::debug!("Using {} of {} texture units.", texture_units, units);
+
+        // filter across cube map faces, which the prefiltered
+        // environment lookups of rough surfaces depend on
+        unsafe { gl.Enable(gl33::GL_TEXTURE_CUBE_MAP_SEAMLESS) };
+
+        Self {
+            gl,
+            program: Cell::new(None),
+            vertex_array: Cell::new(None),
+            buffers: RefCell::new(HashMap::new()),
+            active_unit: Cell::new(None),
+            textures: RefCell::new(HashMap::new()),
+            default_textures: RefCell::new(HashMap::new()),
+            texture_units,
+            state: RefCell::new(StateCache::new()),
+            stats: Cell::new(ContextStats::default()),
+            debug: GlDebug::disabled(),
+        }
+    }
+
+    /// the number of texture units samplers can be assigned to.
+    pub fn texture_units(&self) -> u32 {
+        self.texture_units
+    }
+
+    pub fn with_debug(mut self, debug: GlDebug) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    pub fn debug(&self) -> &GlDebug {
+        &self.debug
+    }
+
+    /// name an object in debug messages and graphics debuggers.
+    pub fn label(&self, identifier: GLenum, id: u32, label: &str) {
+        self.debug.label(identifier, id, label);
+    }
+
+    /// log the opengl errors raised since the last check at the
+    /// caller, when there is no debug output to report them.
+    #[track_caller]
+    pub fn check_errors(&self) -> bool {
+        self.debug.check_errors(&self.gl, Location::caller())
+    }
+
+    pub fn gl(&self) -> &GlFns {
+        &self.gl
+    }
+
+    pub fn stats(&self) -> ContextStats {
+        self.stats.get()
+    }
+
+    pub fn reset_stats(&self) {
+        self.stats.set(ContextStats::default());
+    }
+
+    /// forget all tracked bindings and render state. the next
+    /// bind of every kind is forwarded to opengl.
+    pub fn invalidate(&self) {
+        self.program.set(None);
+        self.vertex_array.set(None);
+        self.buffers.borrow_mut().clear();
+        self.active_unit.set(None);
+        self.textures.borrow_mut().clear();
+        self.state.borrow_mut().invalidate();
+    }
+
+    fn record(&self, counter: fn(&mut ContextStats) -> &mut CallCounter, issued: bool) {
+        let mut stats = self.stats.get();
+        counter(&mut stats).record(issued);
+        self.stats.set(stats);
+    }
+
+    pub fn use_program(&self, id: u32) {
+        let issued = self.program.get() != Some(id);
+        if issued {
+            self.gl.UseProgram(id);
+            self.program.set(Some(id));
+        }
+        self.record(|s| &mut s.programs, issued);
+    }
+
+    pub fn bind_vertex_array(&self, id: u32) {
+        let issued = self.vertex_array.get() != Some(id);
+        if issued {
+            self.gl.BindVertexArray(id);
+            self.vertex_array.set(Some(id));
+
+            // the element array binding is part of the vertex array state
+            self.buffers.borrow_mut().remove(&gl33::GL_ELEMENT_ARRAY_BUFFER.0);
+        }
+        self.record(|s| &mut s.vertex_arrays, issued);
+    }
+
+    pub fn bind_buffer(&self, target: GLenum, id: u32) {
+        let issued = self.buffers.borrow().get(&target.0) != Some(&id);
+        if issued {
+            unsafe {
+                self.gl.BindBuffer(GLenum(target.0), id);
+            }
+            self.buffers.borrow_mut().insert(target.0, id);
+        }
+        self.record(|s| &mut s.buffers, issued);
+    }
+
+    /// bind a buffer to an indexed binding point. this also
+    /// binds it to the generic `target`.
+    pub fn bind_buffer_base(&self, target: GLenum, index: u32, id: u32) {
+        unsafe {
+            self.gl.BindBufferBase(GLenum(target.0), index, id);
+        }
+        self.buffers.borrow_mut().insert(target.0, id);
+        self.record(|s| &mut s.buffers, true);
+    }
+
+    pub fn active_texture(&self, unit: u32) {
+        let issued = self.active_unit.get() != Some(unit);
+        if issued {
+            unsafe {
+                self.gl.ActiveTexture(GLenum(gl33::GL_TEXTURE0.0 + unit));
+            }
+            self.active_unit.set(Some(unit));
+        }
+        self.record(|s| &mut s.texture_units, issued);
+    }
+
+    /// bind a texture to the currently active unit.
+    pub fn bind_texture(&self, target: GLenum, id: u32) {
+        let unit = self.active_unit.get().unwrap_or(0);
+        let key = (unit, target.0);
+
+        let issued = self.textures.borrow().get(&key) != Some(&id);
+        if issued {
+            unsafe {
+                self.gl.BindTexture(GLenum(target.0), id);
+            }
+            self.textures.borrow_mut().insert(key, id);
+        }
+        self.record(|s| &mut s.textures, issued);
+    }
+
+    /// activate `unit` and bind a texture to it.
+    pub fn bind_texture_unit(&self, unit: u32, target: GLenum, id: u32) {
+        self.active_texture(unit);
+        self.bind_texture(target, id);
+    }
+
+    pub fn apply_state(&self, state: &RenderState) {
+        let mut cache = self.state.borrow_mut();
+        let issued = cache.current() != Some(state);
+        if issued {
+            cache.apply(&self.gl, state);
+        }
+        self.record(|s| &mut s.render_states, issued);
+    }
+
+    /// a 1x1 opaque white texture of `target`, either `GL_TEXTURE_2D`
+    /// or `GL_TEXTURE_CUBE_MAP`, made on first use. samplers no texture
+    /// is assigned to read it, so they are complete and of the right type.
+    pub fn default_texture(&self, target: GLenum) -> u32 {
+        if let Some(id) = self.default_textures.borrow().get(&target.0) {
+            return *id;
+        }
+
+        let mut id = 0;
+        unsafe {
+            self.gl.GenTextures(1, &mut id);
+        }
+        self.bind_texture(target, id);
+
+        let faces = if target == gl33::GL_TEXTURE_CUBE_MAP {
+            (0..6).map(|face| GLenum(gl33::GL_TEXTURE_CUBE_MAP_POSITIVE_X.0 + face)).collect()
+        } else {
+            vec![target]
+        };
+        let white = [255u8; 4];
+        for face in faces {
+            unsafe {
+                self.gl.TexImage2D(
+                    face,
+                    0,
+                    gl33::GL_RGBA8.0 as i32,
+                    1,
+                    1,
+                    0,
+                    gl33::GL_RGBA,
+                    gl33::GL_UNSIGNED_BYTE,
+                    white.as_ptr().cast(),
+                );
+            }
+        }
+        unsafe {
+            self.gl.TexParameteri(target, gl33::GL_TEXTURE_MIN_FILTER, gl33::GL_NEAREST.0 as i32);
+            self.gl.TexParameteri(target, gl33::GL_TEXTURE_MAG_FILTER, gl33::GL_NEAREST.0 as i32);
+        }
+
+        self.label(gl33::GL_TEXTURE, id, "default texture");
+        self.default_textures.borrow_mut().insert(target.0, id);
+        id
+    }
+
+    /// drop any tracked binding of a deleted buffer.
+    pub fn forget_buffer(&self, id: u32) {
+        self.buffers.borrow_mut().retain(|_, bound| *bound != id);
+    }
+
+    /// drop any tracked binding of a deleted texture.
+    pub fn forget_texture(&self, id: u32) {
+        self.textures.borrow_mut().retain(|_, bound| *bound != id);
+    }
+}
@@ -0,0 +1,206 @@
+use glfw::{Key, MouseButton};
+use nalgebra::{Isometry3, Point3, Translation3, UnitQuaternion, Vector3};
+
+use crate::input::state::Input;
+
+use super::camera::{Camera, Projection, Ray};
+
+/// moves a camera in response to the polled input.
+///
+/// `update` is called once per frame and only reads `Input`, so
+/// replayed sessions move the camera like the recorded ones.
+pub trait CameraController {
+    fn update(&mut self, input: &Input, camera: &mut Camera, dt: f32);
+}
+
+/// rotation with yaw around the world y axis applied after pitch.
+fn yaw_pitch(yaw: f32, pitch: f32) -> UnitQuaternion<f32> {
+    UnitQuaternion::from_axis_angle(&Vector3::y_axis(), yaw)
+        * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), pitch)
+}
+
+const MAX_PITCH: f32 = 89.0 * std::f32::consts::PI / 180.0;
+
+/// first person free flight: wasd to move, space / left shift to
+/// rise and sink, left control to move faster and the right mouse
+/// button held to look around.
+pub struct FlyController {
+    pub yaw: f32,
+    pub pitch: f32,
+
+    /// units per second
+    pub speed: f32,
+
+    /// speed multiplier while left control is held
+    pub boost: f32,
+
+    /// radians per pixel of cursor movement
+    pub sensitivity: f32,
+
+    pub look_button: MouseButton,
+}
+
+impl FlyController {
+    /// a controller that continues from the camera's current orientation.
+    pub fn from_camera(camera: &Camera) -> Self {
+        let forward = camera.forward();
+
+        Self {
+            yaw: (-forward.x).atan2(-forward.z),
+            pitch: forward.y.clamp(-1.0, 1.0).asin(),
+            speed: 4.0,
+            boost: 4.0,
+            sensitivity: 0.003,
+            look_button: MouseButton::Button2,
+        }
+    }
+}
+
+impl CameraController for FlyController {
+    fn update(&mut self, input: &Input, camera: &mut Camera, dt: f32) {
+        let (dx, dy) = (input.cursor_delta().x, input.cursor_delta().y);
+
+        if input.mouse_pressed(self.look_button) {
+            self.yaw -= dx * self.sensitivity;
+            self.pitch = (self.pitch - dy * self.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+
+        camera.pose.rotation = yaw_pitch(self.yaw, self.pitch);
+
+        let mut direction = Vector3::zeros();
+        for (key, axis) in [
+            (Key::W, camera.forward()),
+            (Key::S, -camera.forward()),
+            (Key::D, camera.right()),
+            (Key::A, -camera.right()),
+            (Key::Space, Vector3::y()),
+            (Key::LeftShift, -Vector3::y()),
+        ] {
+            if input.pressed(key) {
+                direction += axis;
+            }
+        }
+
+        if let Some(direction) = direction.try_normalize(f32::EPSILON) {
+            let boost = if input.pressed(Key::LeftControl) { self.boost } else { 1.0 };
+            camera.pose.translation.vector += direction * self.speed * boost * dt;
+        }
+    }
+}
+
+/// orbits around a target point: left mouse button to rotate,
+/// middle mouse button to pan and the scroll wheel to zoom.
+pub struct OrbitController {
+    pub target: Point3<f32>,
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+
+    /// radians per pixel of cursor movement
+    pub sensitivity: f32,
+
+    /// fraction of the distance zoomed per scroll step
+    pub zoom_speed: f32,
+}
+
+impl OrbitController {
+    pub fn new(target: Point3<f32>, distance: f32) -> Self {
+        Self {
+            target,
+            distance,
+            min_distance: 0.1,
+            max_distance: 1000.0,
+            yaw: 0.0,
+            pitch: 0.0,
+            sensitivity: 0.005,
+            zoom_speed: 0.1,
+        }
+    }
+}
+
+impl CameraController for OrbitController {
+    fn update(&mut self, input: &Input, camera: &mut Camera, _dt: f32) {
+        let (dx, dy, scroll) = (input.cursor_delta().x, input.cursor_delta().y, input.scroll().y);
+
+        if input.mouse_pressed(MouseButton::Button1) {
+            self.yaw -= dx * self.sensitivity;
+            self.pitch = (self.pitch - dy * self.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+
+        if input.mouse_pressed(MouseButton::Button3) {
+            // pan by roughly one pixel per pixel at the target's depth
+            let scale = self.distance / input.window_size().y.max(1.0);
+            self.target += (-camera.right() * dx + camera.up() * dy) * scale;
+        }
+
+        self.distance = (self.distance * (1.0 - scroll * self.zoom_speed))
+            .clamp(self.min_distance, self.max_distance);
+
+        let rotation = yaw_pitch(self.yaw, self.pitch);
+        let position = self.target + rotation * Vector3::z() * self.distance;
+        camera.pose = Isometry3::from_parts(Translation3::from(position.coords), rotation);
+    }
+}
+
+/// 2d navigation: drag with the left or middle mouse button to pan
+/// and scroll to zoom. zooming changes the visible height of an
+/// orthographic camera, or moves a perspective camera along its view.
+/// a perspective camera navigates the plane at `plane_z`, so the
+/// content there follows the cursor.
+pub struct PanZoomController {
+    /// fraction of the visible height zoomed per scroll step
+    pub zoom_speed: f32,
+    pub min_height: f32,
+    pub max_height: f32,
+
+    /// the z of the plane a perspective camera looks at
+    pub plane_z: f32,
+}
+
+impl Default for PanZoomController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PanZoomController {
+    pub fn new() -> Self {
+        Self { zoom_speed: 0.1, min_height: 0.01, max_height: 10000.0, plane_z: 0.0 }
+    }
+}
+
+impl CameraController for PanZoomController {
+    fn update(&mut self, input: &Input, camera: &mut Camera, _dt: f32) {
+        let (dx, dy, scroll) = (input.cursor_delta().x, input.cursor_delta().y, input.scroll().y);
+        let window_height = input.window_size().y;
+
+        let visible = match &mut camera.projection {
+            Projection::Orthographic { height, .. } => {
+                *height = (*height * (1.0 - scroll * self.zoom_speed)).clamp(self.min_height, self.max_height);
+                *height
+            },
+            Projection::Perspective { fovy, .. } => {
+                // the visible height at the plane is 2 * distance * tan(fovy / 2)
+                let height_per_distance = 2.0 * (*fovy * 0.5).tan();
+                let ray = Ray { origin: camera.position(), direction: camera.forward() };
+                let plane = Point3::new(0.0, 0.0, self.plane_z);
+                let distance = ray
+                    .intersect_plane(&plane, &Vector3::z())
+                    .unwrap_or_else(|| (camera.position().z - self.plane_z).abs());
+
+                let zoomed = (distance * (1.0 - scroll * self.zoom_speed))
+                    .clamp(self.min_height / height_per_distance, self.max_height / height_per_distance);
+                camera.pose.translation.vector += ray.direction * (distance - zoomed);
+                zoomed * height_per_distance
+            },
+        };
+
+        if input.mouse_pressed(MouseButton::Button1) || input.mouse_pressed(MouseButton::Button3) {
+            let units_per_pixel = visible / window_height.max(1.0);
+            let offset = (-camera.right() * dx + camera.up() * dy) * units_per_pixel;
+            camera.pose.translation.vector += offset;
+        }
+    }
+}
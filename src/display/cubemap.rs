@@ -0,0 +1,95 @@
+use gl33::{GLenum, GlFns};
+
+use super::{
+    context::GlContext,
+    texture::{set_sampling, TextureFilter, TextureFormat, TextureWrap},
+};
+
+/// a cube map texture with square faces, sampled by direction.
+pub struct Cubemap {
+    pub id: u32,
+
+    /// the edge length of the faces at mip level 0
+    pub size: u32,
+    pub format: TextureFormat,
+
+    /// the number of allocated mip levels
+    pub levels: u32,
+}
+
+impl Cubemap {
+    /// allocate the six faces with `levels` mip levels, at most
+    /// `mip_levels(size)`. the cube map is clamped to its edges
+    /// and filtered linearly.
+    pub fn new(ctx: &GlContext, size: u32, format: TextureFormat, levels: u32) -> Self {
+        let size = size.max(1);
+        let levels = levels.clamp(1, Self::mip_levels(size));
+
+        let mut id = 0;
+        unsafe {
+            ctx.GenTextures(1, &mut id);
+        }
+
+        let cubemap = Self { id, size, format, levels };
+        cubemap.bind(ctx);
+
+        for level in 0..levels {
+            let level_size = cubemap.level_size(level) as i32;
+            for face in 0..6 {
+                unsafe {
+                    ctx.TexImage2D(
+                        Self::face_target(face),
+                        level as i32,
+                        format.internal_format(),
+                        level_size,
+                        level_size,
+                        0,
+                        format.pixel_format(),
+                        format.pixel_type(),
+                        std::ptr::null(),
+                    );
+                }
+            }
+        }
+
+        unsafe {
+            ctx.TexParameteri(gl33::GL_TEXTURE_CUBE_MAP, gl33::GL_TEXTURE_MAX_LEVEL, levels as i32 - 1);
+        }
+
+        let filter = if levels > 1 { TextureFilter::Trilinear } else { TextureFilter::Linear };
+        set_sampling(ctx, gl33::GL_TEXTURE_CUBE_MAP, filter, TextureWrap::ClampToEdge);
+
+        cubemap
+    }
+
+    /// the number of levels in a full mip chain of `size` faces.
+    pub fn mip_levels(size: u32) -> u32 {
+        size.max(1).ilog2() + 1
+    }
+
+    /// the gl target of face `face` in the order +x, -x, +y, -y, +z, -z.
+    pub fn face_target(face: u32) -> GLenum {
+        GLenum(gl33::GL_TEXTURE_CUBE_MAP_POSITIVE_X.0 + face)
+    }
+
+    /// the edge length of the faces at mip `level`.
+    pub fn level_size(&self, level: u32) -> u32 {
+        (self.size >> level).max(1)
+    }
+
+    pub fn bind(&self, ctx: &GlContext) {
+        ctx.bind_texture(gl33::GL_TEXTURE_CUBE_MAP, self.id);
+    }
+
+    /// name the cube map in debug messages and graphics debuggers.
+    pub fn set_label(&self, ctx: &GlContext, label: &str) {
+        ctx.label(gl33::GL_TEXTURE, self.id, label);
+    }
+
+    /// fill the mip chain of the bound cube map from level 0.
+    pub fn generate_mipmap(&self, gl: &GlFns) {
+        unsafe {
+            gl.GenerateMipmap(gl33::GL_TEXTURE_CUBE_MAP);
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use std::{
+    collections::HashMap,
+    ffi::{c_char, c_void, CStr},
+    hash::{DefaultHasher, Hash, Hasher},
+    panic::Location,
+    sync::Mutex,
+};
+
+use gl33::{GLenum, GlFns};
+use glfw::PWindow;
+use serde::{Deserialize, Serialize};
+
+/// how severe an opengl debug message is.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum DebugSeverity {
+    /// anything but an error or a performance warning
+    Notification,
+
+    /// redundant state changes and unimportant undefined behavior
+    #[default]
+    Low,
+
+    /// major performance warnings, deprecated functionality
+    Medium,
+
+    /// errors and undefined behavior
+    High,
+}
+
+impl DebugSeverity {
+    fn from_gl(severity: GLenum) -> Self {
+        match severity {
+            gl33::GL_DEBUG_SEVERITY_HIGH => DebugSeverity::High,
+            gl33::GL_DEBUG_SEVERITY_MEDIUM => DebugSeverity::Medium,
+            gl33::GL_DEBUG_SEVERITY_LOW => DebugSeverity::Low,
+            _ => DebugSeverity::Notification,
+        }
+    }
+
+    fn gl(self) -> GLenum {
+        match self {
+            DebugSeverity::Notification => gl33::GL_DEBUG_SEVERITY_NOTIFICATION,
+            DebugSeverity::Low => gl33::GL_DEBUG_SEVERITY_LOW,
+            DebugSeverity::Medium => gl33::GL_DEBUG_SEVERITY_MEDIUM,
+            DebugSeverity::High => gl33::GL_DEBUG_SEVERITY_HIGH,
+        }
+    }
+
+    fn level(self) -> log::Level {
+        match self {
+            DebugSeverity::High => log::Level::Error,
+            DebugSeverity::Medium => log::Level::Warn,
+            DebugSeverity::Low => log::Level::Info,
+            DebugSeverity::Notification => log::Level::Debug,
+        }
+    }
+}
+
+/// how opengl mistakes are reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GlDebugSettings {
+    /// ask for a debug context and log its messages, where
+    /// `KHR_debug` is supported
+    pub enabled: bool,
+
+    /// the least severe debug messages logged
+    pub min_severity: DebugSeverity,
+
+    /// report messages from within the failing call, so backtraces
+    /// lead to it. slower than letting the driver batch them.
+    pub synchronous: bool,
+
+    /// check `glGetError` after draws when there is no debug output
+    pub check_errors: bool,
+}
+
+impl Default for GlDebugSettings {
+    fn default() -> Self {
+        Self {
+            enabled: cfg!(debug_assertions),
+            min_severity: DebugSeverity::Low,
+            synchronous: true,
+            check_errors: cfg!(debug_assertions),
+        }
+    }
+}
+
+type DebugProc = extern "system" fn(u32, u32, u32, u32, i32, *const c_char, *mut c_void);
+type DebugMessageCallbackFn = unsafe extern "system" fn(Option<DebugProc>, *const c_void);
+type DebugMessageControlFn = unsafe extern "system" fn(u32, u32, u32, i32, *const u32, u8);
+type ObjectLabelFn = unsafe extern "system" fn(u32, u32, i32, *const c_char);
+
+/// how often each message was reported, by hash. a message is logged
+/// the first time and again each time its count doubles.
+static REPORTED: Mutex<Option<HashMap<u64, u64>>> = Mutex::new(None);
+
+/// whether a message is logged this time, and how often it was seen.
+fn report(message: impl Hash) -> (bool, u64) {
+    let mut hasher = DefaultHasher::new();
+    message.hash(&mut hasher);
+
+    let mut reported = REPORTED.lock().unwrap_or_else(|err| err.into_inner());
+    let count = reported.get_or_insert_with(HashMap::new).entry(hasher.finish()).or_insert(0);
+    *count += 1;
+    (count.is_power_of_two(), *count)
+}
+
+/// the opengl debug functions, loaded by hand as they are
+/// not part of gl 3.3.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GlDebug {
+    object_label: Option<ObjectLabelFn>,
+
+    /// whether debug messages are routed into the log
+    output: bool,
+
+    /// whether `check_errors` calls `glGetError`
+    check_errors: bool,
+}
+
+impl GlDebug {
+    /// no debug output, labels or error checks.
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// route debug messages into the log if `KHR_debug` is supported,
+    /// falling back to `glGetError` checks otherwise.
+    /// the window's context has to be current.
+    pub fn load(window: &mut PWindow, gl: &GlFns, settings: &GlDebugSettings) -> Self {
+        let fallback = Self { check_errors: settings.check_errors, ..Self::default() };
+        if !settings.enabled {
+            return fallback;
+        }
+
+        log::debug!("Enabling OpenGL debug output...");
+
+        if !has_extension(gl, "GL_KHR_debug") {
+            log::warn!("OpenGL debug output is not supported, KHR_debug is missing.");
+            return fallback;
+        }
+
+        let mut load = |name: &str| {
+            let address = window.get_proc_address(name);
+            (!address.is_null()).then_some(address)
+        };
+        let (Some(callback), Some(control), Some(label)) =
+            (load("glDebugMessageCallback"), load("glDebugMessageControl"), load("glObjectLabel"))
+        else {
+            log::warn!("Failed to load the KHR_debug functions.");
+            return fallback;
+        };
+
+        // SAFETY: the addresses are the KHR_debug entry points, whose
+        // signatures the function types match
+        let (callback, control, label) = unsafe {
+            (
+                std::mem::transmute::<*const c_void, DebugMessageCallbackFn>(callback),
+                std::mem::transmute::<*const c_void, DebugMessageControlFn>(control),
+                std::mem::transmute::<*const c_void, ObjectLabelFn>(label),
+            )
+        };
+
+        let mut flags = 0;
+        unsafe {
+            gl.GetIntegerv(gl33::GL_CONTEXT_FLAGS, &mut flags);
+        }
+        if flags as u32 & gl33::GL_CONTEXT_FLAG_DEBUG_BIT.0 == 0 {
+            log::warn!("The OpenGL context is not a debug context, drivers may report less.");
+        }
+
+        unsafe {
+            gl.Enable(gl33::GL_DEBUG_OUTPUT);
+            if settings.synchronous {
+                gl.Enable(gl33::GL_DEBUG_OUTPUT_SYNCHRONOUS);
+            }
+
+            // only what is severe enough reaches the callback
+            let dont_care = gl33::GL_DONT_CARE.0;
+            control(dont_care, dont_care, dont_care, 0, std::ptr::null(), 0);
+            for severity in [DebugSeverity::Notification, DebugSeverity::Low, DebugSeverity::Medium, DebugSeverity::High] {
+                if severity >= settings.min_severity {
+                    control(dont_care, dont_care, severity.gl().0, 0, std::ptr::null(), 1);
+                }
+            }
+
+            callback(Some(debug_callback), std::ptr::null());
+        }
+
+        log::info!("Enabled OpenGL debug output. (min severity = {:?})", settings.min_severity);
+        Self { object_label: Some(label), output: true, check_errors: false }
+    }
+
+    /// whether debug messages are routed into the log.
+    pub fn output(&self) -> bool {
+        self.output
+    }
+
+    /// name an object in debug messages and graphics debuggers, e.g.
+    /// `GL_TEXTURE` and a texture id. does nothing without `KHR_debug`.
+    pub fn label(&self, identifier: GLenum, name: u32, label: &str) {
+        let Some(object_label) = self.object_label else {
+            return;
+        };
+        unsafe {
+            object_label(identifier.0, name, label.len() as i32, label.as_ptr() as *const c_char);
+        }
+    }
+
+    /// log the errors opengl raised since the last check, if enabled.
+    /// returns whether there were any.
+    pub fn check_errors(&self, gl: &GlFns, location: &Location) -> bool {
+        if !self.check_errors {
+            return false;
+        }
+
+        let mut found = false;
+
+        // a lost context keeps reporting errors, so the loop is bounded
+        for _ in 0..16 {
+            let error = unsafe { gl.GetError() };
+            if error == gl33::GL_NO_ERROR {
+                break;
+            }
+            found = true;
+
+            let (log, count) = report((location.file(), location.line(), location.column(), error.0));
+            if log {
+                log::error!("Gl error {} at {}.{}", error_name(error), location, repeated(count));
+            }
+        }
+        found
+    }
+}
+
+/// whether the context supports an extension, e.g. `GL_KHR_debug`.
+pub fn has_extension(gl: &GlFns, name: &str) -> bool {
+    let mut count = 0;
+    unsafe {
+        gl.GetIntegerv(gl33::GL_NUM_EXTENSIONS, &mut count);
+    }
+    (0..count.max(0) as u32).any(|i| {
+        let extension = unsafe { gl.GetStringi(gl33::GL_EXTENSIONS, i) };
+        !extension.is_null() && unsafe { CStr::from_ptr(extension as *const c_char) }.to_bytes() == name.as_bytes()
+    })
+}
+
+extern "system" fn debug_callback(
+    source: u32,
+    type_: u32,
+    id: u32,
+    severity: u32,
+    length: i32,
+    message: *const c_char,
+    _user_param: *mut c_void,
+) {
+    let message = if message.is_null() {
+        String::new()
+    } else if length >= 0 {
+        let bytes = unsafe { std::slice::from_raw_parts(message as *const u8, length as usize) };
+        String::from_utf8_lossy(bytes).into_owned()
+    } else {
+        unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned()
+    };
+    let message = message.trim_end();
+
+    let (log, count) = report((source, type_, id, message));
+    if !log {
+        return;
+    }
+
+    let severity = DebugSeverity::from_gl(GLenum(severity));
+    log::log!(
+        target: "ferra::gl",
+        severity.level(),
+        "Gl {} {} ({}): {}{}",
+        source_name(GLenum(source)),
+        type_name(GLenum(type_)),
+        id,
+        message,
+        repeated(count)
+    );
+
+    // a synchronous error's backtrace leads to the failing call
+    if GLenum(type_) == gl33::GL_DEBUG_TYPE_ERROR {
+        let backtrace = std::backtrace::Backtrace::capture();
+        if backtrace.status() == std::backtrace::BacktraceStatus::Captured {
+            log::error!(target: "ferra::gl", "Gl error backtrace:\n{}", backtrace);
+        }
+    }
+}
+
+fn repeated(count: u64) -> String {
+    if count > 1 { format!(" (repeated {} times)", count) } else { String::new() }
+}
+
+fn source_name(source: GLenum) -> &'static str {
+    match source {
+        gl33::GL_DEBUG_SOURCE_API => "api",
+        gl33::GL_DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        gl33::GL_DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        gl33::GL_DEBUG_SOURCE_THIRD_PARTY => "third party",
+        gl33::GL_DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    }
+}
+
+fn type_name(type_: GLenum) -> &'static str {
+    match type_ {
+        gl33::GL_DEBUG_TYPE_ERROR => "error",
+        gl33::GL_DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behavior",
+        gl33::GL_DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behavior",
+        gl33::GL_DEBUG_TYPE_PORTABILITY => "portability",
+        gl33::GL_DEBUG_TYPE_PERFORMANCE => "performance",
+        gl33::GL_DEBUG_TYPE_MARKER => "marker",
+        gl33::GL_DEBUG_TYPE_PUSH_GROUP => "push group",
+        gl33::GL_DEBUG_TYPE_POP_GROUP => "pop group",
+        _ => "other",
+    }
+}
+
+fn error_name(error: GLenum) -> &'static str {
+    match error {
+        gl33::GL_INVALID_ENUM => "GL_INVALID_ENUM",
+        gl33::GL_INVALID_VALUE => "GL_INVALID_VALUE",
+        gl33::GL_INVALID_OPERATION => "GL_INVALID_OPERATION",
+        gl33::GL_INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
+        gl33::GL_OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
+        gl33::GL_STACK_OVERFLOW => "GL_STACK_OVERFLOW",
+        gl33::GL_STACK_UNDERFLOW => "GL_STACK_UNDERFLOW",
+        _ => "unknown",
+    }
+}
@@ -0,0 +1,465 @@
+use std::rc::Rc;
+
+use nalgebra::{Matrix4, Vector3};
+
+use crate::{scene::graph::Renderable, Result};
+
+use super::{
+    camera::Camera,
+    context::GlContext,
+    framebuffer::{set_viewport, viewport, Framebuffer},
+    light::{light_vectors, with_lighting, AmbientLight, LightKind, PlacedLight},
+    mesh::Mesh,
+    pbr::{BRDF_SRC, SURFACE_SRC},
+    primitives,
+    shader::{with_defines, with_prelude, Program},
+    shadow::{MAX_SHADOW_MAPS, SHADOWS_BINDING, SHADOW_MAP_UNIT},
+    state::{BlendState, CompareFunc, CullMode, DepthState, RenderState},
+    texture::{Texture, TextureFilter, TextureFormat, TextureWrap},
+};
+
+const LIT_VERT_SRC: &str = include_str!("../../res/shaders/lit_vertex.glsl");
+const GBUFFER_FRAG_SRC: &str = include_str!("../../res/shaders/gbuffer_fragment.glsl");
+
+const FULLSCREEN_VERT_SRC: &str = include_str!("../../res/shaders/fullscreen_vertex.glsl");
+const LIGHT_VERT_SRC: &str = include_str!("../../res/shaders/deferred_light_vertex.glsl");
+const LIGHT_FRAG_SRC: &str = include_str!("../../res/shaders/deferred_light_fragment.glsl");
+const AMBIENT_FRAG_SRC: &str = include_str!("../../res/shaders/deferred_ambient_fragment.glsl");
+const COMPOSITE_FRAG_SRC: &str = include_str!("../../res/shaders/deferred_composite_fragment.glsl");
+
+/// the g-buffer samplers and position reconstruction shared by the lighting passes.
+const GBUFFER_SAMPLING_SRC: &str = include_str!("../../res/shaders/gbuffer_sampling.glsl");
+
+/// the g-buffer textures with the units the lighting passes read them from.
+const GBUFFER_SAMPLERS: [&str; 5] = ["gbuffer_albedo", "gbuffer_normal", "gbuffer_material", "gbuffer_emission", "gbuffer_depth"];
+const LIGHTING_UNIT: u32 = 5;
+
+/// light volumes are icospheres, whose faces lie inside the sphere
+/// through their vertices. they are scaled up to cover it.
+const VOLUME_SCALE: f32 = 1.1;
+
+/// the render targets of the deferred renderer, all the size of the viewport.
+pub struct GBuffer {
+    framebuffer: Framebuffer,
+    lighting_framebuffer: Framebuffer,
+
+    /// rgb linear albedo, a ambient occlusion
+    pub albedo: Texture,
+
+    /// xyz world space normal
+    pub normal: Texture,
+
+    /// r metalness, g roughness
+    pub material: Texture,
+
+    /// rgb emitted radiance
+    pub emission: Texture,
+
+    pub depth: Texture,
+
+    /// the hdr radiance accumulated by the lighting passes
+    pub lighting: Texture,
+
+    pub width: u32,
+    pub height: u32,
+}
+
+impl GBuffer {
+    pub fn new(ctx: &GlContext, width: u32, height: u32) -> Result<Self> {
+        let (width, height) = (width.max(1), height.max(1));
+        log::debug!("Creating g-buffer... ({}x{})", width, height);
+
+        let target = |format| Texture::empty(ctx, width, height, format, TextureFilter::Nearest, TextureWrap::ClampToEdge);
+        let albedo = target(TextureFormat::Rgba);
+        let normal = target(TextureFormat::Rgba16F);
+        let material = target(TextureFormat::Rgba);
+        let emission = target(TextureFormat::Rgba16F);
+        let depth = target(TextureFormat::Depth24Stencil8);
+        let lighting = target(TextureFormat::Rgba16F);
+
+        let framebuffer = Framebuffer::new(ctx);
+        framebuffer.bind(ctx);
+        for (i, texture) in [&albedo, &normal, &material, &emission].into_iter().enumerate() {
+            framebuffer.attach_texture(ctx, gl33::GLenum(gl33::GL_COLOR_ATTACHMENT0.0 + i as u32), texture, 0);
+        }
+        framebuffer.attach_texture(ctx, gl33::GL_DEPTH_STENCIL_ATTACHMENT, &depth, 0);
+
+        let attachments = [
+            gl33::GL_COLOR_ATTACHMENT0,
+            gl33::GL_COLOR_ATTACHMENT1,
+            gl33::GL_COLOR_ATTACHMENT2,
+            gl33::GL_COLOR_ATTACHMENT3,
+        ];
+        unsafe {
+            ctx.DrawBuffers(attachments.len() as i32, attachments.as_ptr());
+        }
+        framebuffer.check(ctx)?;
+
+        // light volumes are depth tested against a copy of the scene depth,
+        // as `depth` is sampled while lighting and must not be attached
+        let mut lighting_framebuffer = Framebuffer::new(ctx);
+        lighting_framebuffer.bind(ctx);
+        lighting_framebuffer.attach_texture(ctx, gl33::GL_COLOR_ATTACHMENT0, &lighting, 0);
+        lighting_framebuffer.attach_depth_stencil_buffer(ctx, width, height);
+        lighting_framebuffer.check(ctx)?;
+
+        Framebuffer::unbind(ctx);
+
+        framebuffer.set_label(ctx, "g-buffer");
+        lighting_framebuffer.set_label(ctx, "g-buffer lighting");
+        for (texture, label) in [
+            (&albedo, "g-buffer albedo"),
+            (&normal, "g-buffer normal"),
+            (&material, "g-buffer material"),
+            (&emission, "g-buffer emission"),
+            (&depth, "g-buffer depth"),
+            (&lighting, "g-buffer lighting"),
+        ] {
+            texture.set_label(ctx, label);
+        }
+
+        Ok(Self { framebuffer, lighting_framebuffer, albedo, normal, material, emission, depth, lighting, width, height })
+    }
+
+    /// delete the framebuffers and textures.
+    pub fn delete(self, ctx: &GlContext) {
+        self.framebuffer.delete(ctx);
+        self.lighting_framebuffer.delete(ctx);
+        for texture in [self.albedo, self.normal, self.material, self.emission, self.depth, self.lighting] {
+            texture.delete(ctx);
+        }
+    }
+
+    /// copy the scene depth into the depth buffer of the lighting
+    /// framebuffer, which light volumes are tested against.
+    fn copy_depth(&self, ctx: &GlContext) {
+        let (width, height) = (self.width as i32, self.height as i32);
+        unsafe {
+            ctx.BindFramebuffer(gl33::GL_READ_FRAMEBUFFER, self.framebuffer.id);
+            ctx.BindFramebuffer(gl33::GL_DRAW_FRAMEBUFFER, self.lighting_framebuffer.id);
+            ctx.BlitFramebuffer(0, 0, width, height, 0, 0, width, height, gl33::GL_DEPTH_BUFFER_BIT, gl33::GL_NEAREST);
+        }
+    }
+
+    /// bind the g-buffer textures, and the lighting after them,
+    /// to the units the lighting passes expect.
+    fn bind_textures(&self, ctx: &GlContext) {
+        for (unit, texture) in [&self.albedo, &self.normal, &self.material, &self.emission, &self.depth].into_iter().enumerate() {
+            ctx.bind_texture_unit(unit as u32, gl33::GL_TEXTURE_2D, texture.id);
+        }
+        ctx.bind_texture_unit(LIGHTING_UNIT, gl33::GL_TEXTURE_2D, self.lighting.id);
+    }
+}
+
+/// a deferred shading path for scenes with many lights.
+///
+/// renderables whose materials have a deferred program, usually
+/// `gbuffer_program`, are drawn into a g-buffer. every light then
+/// adds its contribution to an hdr target, directional lights over
+/// the whole screen and the others over a bounding sphere. the
+/// result is tone mapped onto the bound framebuffer, whose depth
+/// is taken over from the g-buffer, before everything else is
+/// drawn forward on top, transparent objects back to front.
+pub struct DeferredRenderer {
+    gbuffer_program: Rc<Program>,
+    light_program: Program,
+    ambient_program: Program,
+    composite_program: Program,
+
+    gbuffer: Option<GBuffer>,
+    quad: Mesh,
+    sphere: Mesh,
+}
+
+impl DeferredRenderer {
+    pub fn new(ctx: &GlContext) -> Result<Self> {
+        let mut gbuffer_program = Program::new();
+        gbuffer_program.link(ctx, LIT_VERT_SRC, &with_prelude(GBUFFER_FRAG_SRC, SURFACE_SRC))?;
+
+        let defines = [("MAX_SHADOW_MAPS", MAX_SHADOW_MAPS.to_string())];
+        let fragment = with_lighting(&with_prelude(&with_prelude(LIGHT_FRAG_SRC, GBUFFER_SAMPLING_SRC), BRDF_SRC));
+
+        let mut light_program = Program::new();
+        light_program.link(ctx, LIGHT_VERT_SRC, &with_defines(&fragment, &defines))?;
+        light_program.uniform_block_binding(ctx, "Shadows", SHADOWS_BINDING)?;
+        light_program.bind(ctx);
+        light_program.uniform_1i(ctx, "shadow_maps", SHADOW_MAP_UNIT as i32)?;
+
+        let mut ambient_program = Program::new();
+        ambient_program.link(ctx, FULLSCREEN_VERT_SRC, &with_prelude(AMBIENT_FRAG_SRC, GBUFFER_SAMPLING_SRC))?;
+
+        let mut composite_program = Program::new();
+        composite_program.link(ctx, FULLSCREEN_VERT_SRC, &with_prelude(COMPOSITE_FRAG_SRC, GBUFFER_SAMPLING_SRC))?;
+
+        for program in [&light_program, &ambient_program, &composite_program] {
+            program.bind(ctx);
+            for (unit, name) in GBUFFER_SAMPLERS.into_iter().enumerate() {
+                set_sampler(ctx, program, name, unit as u32)?;
+            }
+            set_sampler(ctx, program, "lighting", LIGHTING_UNIT)?;
+        }
+
+        gbuffer_program.set_label(ctx, "deferred g-buffer");
+        light_program.set_label(ctx, "deferred light");
+        ambient_program.set_label(ctx, "deferred ambient");
+        composite_program.set_label(ctx, "deferred composite");
+
+        let quad = primitives::quad(2.0, 2.0).upload(ctx);
+        quad.set_label(ctx, "deferred quad");
+        let sphere = primitives::icosphere(1.0, 1).upload(ctx);
+        sphere.set_label(ctx, "deferred light volume");
+
+        Ok(Self {
+            gbuffer_program: Rc::new(gbuffer_program),
+            light_program,
+            ambient_program,
+            composite_program,
+            gbuffer: None,
+            quad,
+            sphere,
+        })
+    }
+
+    /// the program writing `Pbr` materials to the g-buffer,
+    /// to be given to `Material::with_deferred`.
+    pub fn gbuffer_program(&self) -> Rc<Program> {
+        self.gbuffer_program.clone()
+    }
+
+    pub fn gbuffer(&self) -> Option<&GBuffer> {
+        self.gbuffer.as_ref()
+    }
+
+    /// draw `renderables` as seen by `camera` into the bound framebuffer,
+    /// whose origin has to be at the viewport's. the shadow maps of
+    /// the `lights` have to be rendered and bound already.
+    pub fn render(
+        &mut self,
+        ctx: &GlContext,
+        camera: &Camera,
+        ambient: &AmbientLight,
+        lights: &[PlacedLight],
+        renderables: &[(Renderable, Matrix4<f32>)],
+    ) -> Result<()> {
+        let saved_viewport = viewport(ctx);
+        let (width, height) = (saved_viewport[2].max(1) as u32, saved_viewport[3].max(1) as u32);
+
+        if self.gbuffer.as_ref().is_none_or(|gbuffer| gbuffer.width != width || gbuffer.height != height) {
+            if let Some(gbuffer) = self.gbuffer.take() {
+                gbuffer.delete(ctx);
+            }
+            self.gbuffer = Some(GBuffer::new(ctx, width, height)?);
+        }
+
+        let mut target = 0;
+        unsafe {
+            ctx.GetIntegerv(gl33::GL_DRAW_FRAMEBUFFER_BINDING, &mut target);
+        }
+
+        let result = self.render_deferred(ctx, camera, ambient, lights, renderables);
+
+        unsafe {
+            ctx.BindFramebuffer(gl33::GL_FRAMEBUFFER, target as u32);
+        }
+        set_viewport(ctx, saved_viewport);
+        result?;
+
+        self.composite(ctx, target as u32)?;
+        self.render_forward(ctx, camera, renderables)
+    }
+
+    /// fill the g-buffer and accumulate the lighting.
+    fn render_deferred(
+        &self,
+        ctx: &GlContext,
+        camera: &Camera,
+        ambient: &AmbientLight,
+        lights: &[PlacedLight],
+        renderables: &[(Renderable, Matrix4<f32>)],
+    ) -> Result<()> {
+        let Some(gbuffer) = &self.gbuffer else {
+            return Ok(());
+        };
+
+        let view = camera.view_matrix();
+        let projection = camera.projection_matrix();
+        let view_projection = projection * view;
+        let inverse_view_projection = view_projection.try_inverse().unwrap_or_else(Matrix4::identity);
+
+        gbuffer.framebuffer.bind(ctx);
+        set_viewport(ctx, [0, 0, gbuffer.width as i32, gbuffer.height as i32]);
+
+        // clearing respects the depth and color masks
+        ctx.apply_state(&RenderState::opaque());
+        clear(ctx, [0.0; 4], gl33::GL_COLOR_BUFFER_BIT | gl33::GL_DEPTH_BUFFER_BIT | gl33::GL_STENCIL_BUFFER_BIT);
+
+        for (renderable, model) in renderables.iter().filter(|(renderable, _)| renderable.is_deferred()) {
+            renderable.draw_deferred(ctx, model, &view, &projection)?;
+        }
+
+        gbuffer.copy_depth(ctx);
+        gbuffer.lighting_framebuffer.bind(ctx);
+        clear(ctx, [0.0; 4], gl33::GL_COLOR_BUFFER_BIT);
+        gbuffer.bind_textures(ctx);
+
+        let screen_size = [gbuffer.width as f32, gbuffer.height as f32];
+        let fullscreen_state = RenderState {
+            depth: DepthState { test: false, write: false, func: CompareFunc::Always },
+            blend: BlendState::additive(),
+            cull: CullMode::None,
+            ..RenderState::default()
+        };
+
+        // only the back faces of a volume are drawn, where they lie
+        // behind the scene, so lights also work with the camera inside
+        let volume_state = RenderState {
+            depth: DepthState { test: true, write: false, func: CompareFunc::GreaterEqual },
+            blend: BlendState::additive(),
+            cull: CullMode::Front,
+            ..RenderState::default()
+        };
+
+        let ambient_color = ambient.color * ambient.intensity;
+        self.ambient_program.bind(ctx);
+        set_uniform_2f(ctx, &self.ambient_program, "screen_size", screen_size);
+        set_uniform_3f(ctx, &self.ambient_program, "ambient", &ambient_color);
+        ctx.apply_state(&fullscreen_state);
+        self.quad.draw(ctx);
+
+        let program = &self.light_program;
+        program.bind(ctx);
+        set_uniform_2f(ctx, program, "screen_size", screen_size);
+        program.uniform_mat4(ctx, "view_projection", &view_projection)?;
+        program.uniform_mat4(ctx, "inverse_view_projection", &inverse_view_projection)?;
+        set_uniform_3f(ctx, program, "camera_position", &camera.position().coords);
+
+        let light_location = program.uniform_location(ctx, "light_data")?;
+        let fullscreen_location = program.uniform_location(ctx, "fullscreen")?;
+
+        for placed in lights {
+            let reach = match placed.light.kind {
+                LightKind::Directional => None,
+                LightKind::Point { attenuation } | LightKind::Spot { attenuation, .. } => {
+                    Some(attenuation.reach()).filter(|reach| reach.is_finite())
+                },
+            };
+
+            let data = light_vectors(placed);
+            unsafe {
+                ctx.Uniform4fv(light_location, 5, data.as_ptr());
+                ctx.Uniform1i(fullscreen_location, reach.is_none() as i32);
+            }
+
+            match reach {
+                Some(reach) => {
+                    let model = Matrix4::new_translation(&placed.position().coords)
+                        * Matrix4::new_scaling(reach * VOLUME_SCALE);
+                    program.uniform_mat4(ctx, "model", &model)?;
+                    ctx.apply_state(&volume_state);
+                    self.sphere.draw(ctx);
+                },
+                None => {
+                    ctx.apply_state(&fullscreen_state);
+                    self.quad.draw(ctx);
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    /// tone map the lighting onto `target` and copy the g-buffer depth to it.
+    fn composite(&self, ctx: &GlContext, target: u32) -> Result<()> {
+        let Some(gbuffer) = &self.gbuffer else {
+            return Ok(());
+        };
+
+        let (width, height) = (gbuffer.width as i32, gbuffer.height as i32);
+        unsafe {
+            ctx.BindFramebuffer(gl33::GL_READ_FRAMEBUFFER, gbuffer.framebuffer.id);
+            ctx.BindFramebuffer(gl33::GL_DRAW_FRAMEBUFFER, target);
+            ctx.BlitFramebuffer(
+                0,
+                0,
+                width,
+                height,
+                0,
+                0,
+                width,
+                height,
+                gl33::GL_DEPTH_BUFFER_BIT | gl33::GL_STENCIL_BUFFER_BIT,
+                gl33::GL_NEAREST,
+            );
+            ctx.BindFramebuffer(gl33::GL_FRAMEBUFFER, target);
+        }
+
+        gbuffer.bind_textures(ctx);
+        self.composite_program.bind(ctx);
+        set_uniform_2f(ctx, &self.composite_program, "screen_size", [width as f32, height as f32]);
+
+        ctx.apply_state(&RenderState {
+            depth: DepthState { test: false, write: false, func: CompareFunc::Always },
+            cull: CullMode::None,
+            ..RenderState::default()
+        });
+        self.quad.draw(ctx);
+
+        Ok(())
+    }
+
+    /// draw the renderables without a deferred program, opaque ones
+    /// first and blended ones back to front.
+    fn render_forward(&self, ctx: &GlContext, camera: &Camera, renderables: &[(Renderable, Matrix4<f32>)]) -> Result<()> {
+        let view = camera.view_matrix();
+        let projection = camera.projection_matrix();
+
+        let (mut blended, opaque): (Vec<_>, Vec<_>) = renderables
+            .iter()
+            .filter(|(renderable, _)| !renderable.is_deferred())
+            .partition(|(renderable, _)| renderable.material.state.blend.enabled);
+
+        let depth = |model: &Matrix4<f32>| (view * model.column(3)).z;
+        blended.sort_by(|(_, a), (_, b)| depth(a).total_cmp(&depth(b)));
+
+        for (renderable, model) in opaque.into_iter().chain(blended) {
+            renderable.draw(ctx, model, &view, &projection)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// clear the bound framebuffer, keeping the clear color of the caller.
+fn clear(ctx: &GlContext, color: [f32; 4], mask: gl33::GLbitfield) {
+    let mut saved = [0.0; 4];
+    unsafe {
+        ctx.GetFloatv(gl33::GL_COLOR_CLEAR_VALUE, saved.as_mut_ptr());
+        ctx.ClearColor(color[0], color[1], color[2], color[3]);
+        ctx.Clear(mask);
+        ctx.ClearColor(saved[0], saved[1], saved[2], saved[3]);
+    }
+}
+
+/// point a sampler of the bound program at `unit`, if it is active.
+fn set_sampler(ctx: &GlContext, program: &Program, name: &str, unit: u32) -> Result<()> {
+    if program.uniform_info(name).is_some() {
+        program.uniform_1i(ctx, name, unit as i32)?;
+    }
+    Ok(())
+}
+
+fn set_uniform_2f(ctx: &GlContext, program: &Program, name: &str, value: [f32; 2]) {
+    if let Some(uniform) = program.uniform_info(name) {
+        unsafe {
+            ctx.Uniform2f(uniform.location, value[0], value[1]);
+        }
+    }
+}
+
+fn set_uniform_3f(ctx: &GlContext, program: &Program, name: &str, value: &Vector3<f32>) {
+    if let Some(uniform) = program.uniform_info(name) {
+        unsafe {
+            ctx.Uniform3f(uniform.location, value.x, value.y, value.z);
+        }
+    }
+}
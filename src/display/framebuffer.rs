@@ -0,0 +1,151 @@
+use gl33::{GLenum, GlFns};
+
+use crate::{Error, Result};
+
+use super::{context::GlContext, cubemap::Cubemap, texture::Texture};
+
+/// an offscreen render target with textures attached
+/// as its color and depth buffers.
+///
+/// framebuffer bindings are not tracked by the `GlContext`.
+pub struct Framebuffer {
+    pub id: u32,
+
+    /// the depth renderbuffer, if one was attached
+    depth: Option<u32>,
+}
+
+impl Framebuffer {
+    pub fn new(gl: &GlFns) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl.GenFramebuffers(1, &mut id);
+        }
+        Self { id, depth: None }
+    }
+
+    pub fn bind(&self, gl: &GlFns) {
+        unsafe {
+            gl.BindFramebuffer(gl33::GL_FRAMEBUFFER, self.id);
+        }
+    }
+
+    /// bind the default framebuffer of the window.
+    pub fn unbind(gl: &GlFns) {
+        unsafe {
+            gl.BindFramebuffer(gl33::GL_FRAMEBUFFER, 0);
+        }
+    }
+
+    /// attach mip `level` of a texture to the bound framebuffer,
+    /// e.g. at `GL_COLOR_ATTACHMENT0` or `GL_DEPTH_ATTACHMENT`.
+    pub fn attach_texture(&self, gl: &GlFns, attachment: GLenum, texture: &Texture, level: u32) {
+        unsafe {
+            gl.FramebufferTexture2D(gl33::GL_FRAMEBUFFER, attachment, gl33::GL_TEXTURE_2D, texture.id, level as i32);
+        }
+    }
+
+    /// attach mip `level` of one face of a cube map to the bound framebuffer.
+    pub fn attach_cubemap_face(&self, gl: &GlFns, attachment: GLenum, cubemap: &Cubemap, face: u32, level: u32) {
+        unsafe {
+            gl.FramebufferTexture2D(
+                gl33::GL_FRAMEBUFFER,
+                attachment,
+                Cubemap::face_target(face),
+                cubemap.id,
+                level as i32,
+            );
+        }
+    }
+
+    /// attach `layer` of mip `level` of an array texture to the bound framebuffer.
+    pub fn attach_texture_layer(&self, gl: &GlFns, attachment: GLenum, texture: u32, level: u32, layer: u32) {
+        unsafe {
+            gl.FramebufferTextureLayer(gl33::GL_FRAMEBUFFER, attachment, texture, level as i32, layer as i32);
+        }
+    }
+
+    /// render depth only; the bound framebuffer neither reads
+    /// nor writes color.
+    pub fn disable_color(&self, gl: &GlFns) {
+        unsafe {
+            gl.DrawBuffer(gl33::GL_NONE);
+            gl.ReadBuffer(gl33::GL_NONE);
+        }
+    }
+
+    /// attach a `width` by `height` depth renderbuffer to the bound
+    /// framebuffer, reallocating the one attached before.
+    pub fn attach_depth_buffer(&mut self, gl: &GlFns, width: u32, height: u32) {
+        self.attach_renderbuffer(gl, gl33::GL_DEPTH_COMPONENT24, gl33::GL_DEPTH_ATTACHMENT, width, height);
+    }
+
+    /// like `attach_depth_buffer`, but with a stencil buffer as well,
+    /// so depth can be blitted from `Depth24Stencil8` textures.
+    pub fn attach_depth_stencil_buffer(&mut self, gl: &GlFns, width: u32, height: u32) {
+        self.attach_renderbuffer(gl, gl33::GL_DEPTH24_STENCIL8, gl33::GL_DEPTH_STENCIL_ATTACHMENT, width, height);
+    }
+
+    fn attach_renderbuffer(&mut self, gl: &GlFns, format: GLenum, attachment: GLenum, width: u32, height: u32) {
+        let id = *self.depth.get_or_insert_with(|| {
+            let mut id = 0;
+            unsafe {
+                gl.GenRenderbuffers(1, &mut id);
+            }
+            id
+        });
+
+        unsafe {
+            gl.BindRenderbuffer(gl33::GL_RENDERBUFFER, id);
+            gl.RenderbufferStorage(gl33::GL_RENDERBUFFER, format, width as i32, height as i32);
+            gl.FramebufferRenderbuffer(gl33::GL_FRAMEBUFFER, attachment, gl33::GL_RENDERBUFFER, id);
+        }
+    }
+
+    /// name the framebuffer, and its depth renderbuffer if attached,
+    /// in debug messages and graphics debuggers.
+    pub fn set_label(&self, ctx: &GlContext, label: &str) {
+        ctx.label(gl33::GL_FRAMEBUFFER, self.id, label);
+        if let Some(depth) = self.depth {
+            ctx.label(gl33::GL_RENDERBUFFER, depth, &format!("{} depth", label));
+        }
+    }
+
+    /// delete the framebuffer and its depth renderbuffer, leaving
+    /// the attached textures alive.
+    pub fn delete(self, gl: &GlFns) {
+        unsafe {
+            if let Some(depth) = self.depth {
+                gl.DeleteRenderbuffers(1, &depth);
+            }
+            gl.DeleteFramebuffers(1, &self.id);
+        }
+    }
+
+    /// check that the bound framebuffer can be rendered to.
+    pub fn check(&self, gl: &GlFns) -> Result<()> {
+        let status = unsafe { gl.CheckFramebufferStatus(gl33::GL_FRAMEBUFFER) };
+
+        if status != gl33::GL_FRAMEBUFFER_COMPLETE {
+            log::error!("Framebuffer {} is incomplete. (status = {:#x})", self.id, status.0);
+            return Err(Error::GlFramebuffer(format!("Framebuffer {} status {:#x}.", self.id, status.0)));
+        }
+
+        Ok(())
+    }
+}
+
+/// the current viewport as (x, y, width, height).
+pub fn viewport(gl: &GlFns) -> [i32; 4] {
+    let mut viewport = [0; 4];
+    unsafe {
+        gl.GetIntegerv(gl33::GL_VIEWPORT, viewport.as_mut_ptr());
+    }
+    viewport
+}
+
+pub fn set_viewport(gl: &GlFns, [x, y, width, height]: [i32; 4]) {
+    unsafe {
+        gl.Viewport(x, y, width, height);
+    }
+}
@@ -0,0 +1,81 @@
+use nalgebra::Matrix4;
+
+use super::{context::GlContext, mesh::Mesh, vertex::{Buffer, BufferUsage, VertexLayout}};
+
+/// a vertex buffer of per-instance model matrices
+/// that is refilled every frame.
+///
+/// each matrix is exposed to the vertex shader as a `mat4`
+/// attribute spanning four locations, starting at `location`.
+pub struct TransformInstances {
+    pub buffer: Buffer<f32>,
+    pub location: u32,
+
+    /// the number of matrices the buffer storage can hold
+    capacity: usize,
+
+    /// the number of matrices uploaded by the last `update`
+    count: usize,
+
+    /// scratch space reused between updates
+    staging: Vec<f32>,
+}
+
+const MATRIX_FLOATS: usize = 16;
+
+impl TransformInstances {
+    pub fn new(ctx: &GlContext, location: u32) -> Self {
+        Self {
+            buffer: Buffer::new_vertex(ctx),
+            location,
+            capacity: 0,
+            count: 0,
+            staging: Vec::new(),
+        }
+    }
+
+    pub fn layout(&self) -> VertexLayout {
+        VertexLayout::new(MATRIX_FLOATS * std::mem::size_of::<f32>())
+            .matrix4(self.location, 0)
+            .per_instance()
+    }
+
+    /// the number of instances uploaded by the last `update`.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// make `mesh` read its instance transforms from this buffer.
+    pub fn attach(&self, ctx: &GlContext, mesh: &Mesh) {
+        mesh.attach_instances(ctx, &self.buffer, &self.layout());
+    }
+
+    /// upload this frame's transforms, orphaning the previous storage
+    /// so the driver does not have to wait for pending draws.
+    pub fn update(&mut self, ctx: &GlContext, transforms: &[Matrix4<f32>]) {
+        self.staging.clear();
+        for transform in transforms {
+            // nalgebra stores matrices column-major, just like glsl expects
+            self.staging.extend_from_slice(transform.as_slice());
+        }
+
+        self.buffer.bind(ctx);
+
+        if transforms.len() > self.capacity {
+            self.capacity = transforms.len().next_power_of_two();
+            log::debug!("Growing transform instance buffer to {} matrices.", self.capacity);
+        }
+
+        self.buffer.allocate(ctx, self.capacity * MATRIX_FLOATS, BufferUsage::Stream);
+        self.buffer.sub_data(ctx, 0, &self.staging);
+        self.count = transforms.len();
+    }
+
+    /// draw `mesh` once per uploaded transform.
+    #[track_caller]
+    pub fn draw(&self, ctx: &GlContext, mesh: &Mesh) {
+        if self.count > 0 {
+            mesh.draw_instanced(ctx, self.count);
+        }
+    }
+}
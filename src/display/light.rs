@@ -0,0 +1,374 @@
+use std::{ops::Range, rc::Rc};
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+
+use crate::Result;
+
+use super::{
+    context::GlContext,
+    material::Material,
+    shader::{with_defines, with_prelude, Program},
+    shadow::{MAX_SHADOW_MAPS, SHADOWS_BINDING, SHADOW_MAP_UNIT},
+    texture::Texture,
+    vertex::{Buffer, BufferUsage},
+};
+
+const VERT_SRC: &str = include_str!("../../res/shaders/lit_vertex.glsl");
+const FRAG_SRC: &str = include_str!("../../res/shaders/blinn_phong_fragment.glsl");
+
+/// the light struct and attenuation shared by the lit shaders.
+const LIGHTS_SRC: &str = include_str!("../../res/shaders/lights.glsl");
+
+/// the `Shadows` block and shadow map sampling shared by the lit shaders.
+const SHADOWS_SRC: &str = include_str!("../../res/shaders/shadows.glsl");
+
+/// the uniform buffer binding point the `Lights` block reads from.
+pub const LIGHTS_BINDING: u32 = 0;
+
+pub const DEFAULT_MAX_LIGHTS: usize = 16;
+
+/// how the intensity of point and spot lights falls off with
+/// distance d, as 1 / (constant + linear * d + quadratic * d²).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attenuation {
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32,
+}
+
+impl Attenuation {
+    /// an attenuation that has mostly faded out at `range`.
+    pub fn range(range: f32) -> Self {
+        let range = range.max(f32::EPSILON);
+        Self { constant: 1.0, linear: 4.5 / range, quadratic: 75.0 / (range * range) }
+    }
+
+    /// the distance at which the light has faded to 1/256 of its
+    /// intensity, infinite if it never does.
+    pub fn reach(&self) -> f32 {
+        let c = self.constant - 256.0;
+        if self.quadratic > 0.0 {
+            let discriminant = self.linear * self.linear - 4.0 * self.quadratic * c;
+            (-self.linear + discriminant.max(0.0).sqrt()) / (2.0 * self.quadratic)
+        } else if self.linear > 0.0 {
+            -c / self.linear
+        } else {
+            f32::INFINITY
+        }
+    }
+}
+
+impl Default for Attenuation {
+    fn default() -> Self {
+        Self::range(50.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    /// parallel rays along the light's -z axis
+    Directional,
+
+    /// shines in every direction from the light's position
+    Point { attenuation: Attenuation },
+
+    /// a cone along the light's -z axis. the angles are measured
+    /// from the axis in radians; the light fades out between them.
+    Spot { attenuation: Attenuation, inner: f32, outer: f32 },
+}
+
+/// a light source, placed in the world by its entity's transform.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+
+    /// whether the light renders shadow maps. only directional
+    /// and spot lights cast shadows.
+    pub cast_shadows: bool,
+}
+
+impl Light {
+    pub fn directional(color: Vector3<f32>, intensity: f32) -> Self {
+        Self { kind: LightKind::Directional, color, intensity, cast_shadows: false }
+    }
+
+    pub fn point(color: Vector3<f32>, intensity: f32, attenuation: Attenuation) -> Self {
+        Self { kind: LightKind::Point { attenuation }, color, intensity, cast_shadows: false }
+    }
+
+    pub fn spot(color: Vector3<f32>, intensity: f32, attenuation: Attenuation, inner: f32, outer: f32) -> Self {
+        Self { kind: LightKind::Spot { attenuation, inner, outer }, color, intensity, cast_shadows: false }
+    }
+
+    pub fn with_shadows(mut self) -> Self {
+        self.cast_shadows = true;
+        self
+    }
+}
+
+/// a light with its world matrix, as uploaded by `LightBuffer::update`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedLight {
+    pub light: Light,
+    pub world: Matrix4<f32>,
+
+    /// the shadow map layers the light samples, assigned by the
+    /// `ShadowRenderer`. `None` for unshadowed lights.
+    pub shadow_layers: Option<Range<u32>>,
+}
+
+impl PlacedLight {
+    pub fn new(light: Light, world: Matrix4<f32>) -> Self {
+        Self { light, world, shadow_layers: None }
+    }
+
+    pub fn position(&self) -> Point3<f32> {
+        Point3::from(self.world.column(3).xyz())
+    }
+
+    /// the normalized -z axis of the world matrix.
+    pub fn direction(&self) -> Vector3<f32> {
+        let direction = (self.world * Vector4::new(0.0, 0.0, -1.0, 0.0)).xyz();
+        direction.try_normalize(f32::EPSILON).unwrap_or(-Vector3::z())
+    }
+}
+
+/// the light added to every lit surface regardless of the
+/// light sources, used as a resource by the lighting system.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AmbientLight {
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+}
+
+impl Default for AmbientLight {
+    fn default() -> Self {
+        Self { color: Vector3::repeat(1.0), intensity: 0.1 }
+    }
+}
+
+/// the std140 layout of a light in the `Lights` block.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct GpuLight {
+    /// xyz position, w light kind
+    position: [f32; 4],
+
+    /// xyz direction, w cosine of the inner cone angle
+    direction: [f32; 4],
+
+    /// rgb color, a intensity
+    color: [f32; 4],
+
+    /// constant, linear and quadratic factors, w cosine of the outer cone angle
+    attenuation: [f32; 4],
+
+    /// x first shadow map layer or -1, y layer count, z 1 for cascades
+    shadow: [f32; 4],
+}
+
+/// the std140 layout of the `Lights` block up to the light array.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct GpuLightsHeader {
+    ambient: [f32; 4],
+    camera_position: [f32; 4],
+    count: [i32; 4],
+}
+
+// SAFETY: both are `repr(C)` and made up of 4 byte scalars
+// only, so they have no padding and every bit pattern is valid.
+unsafe impl Zeroable for GpuLight {}
+unsafe impl Pod for GpuLight {}
+
+unsafe impl Zeroable for GpuLightsHeader {}
+unsafe impl Pod for GpuLightsHeader {}
+
+impl GpuLight {
+    fn new(placed: &PlacedLight) -> Self {
+        let light = &placed.light;
+        let position = placed.position();
+        let direction = placed.direction();
+
+        let (kind, attenuation, inner, outer) = match light.kind {
+            LightKind::Directional => (0.0, Attenuation { constant: 1.0, linear: 0.0, quadratic: 0.0 }, 1.0, 1.0),
+            LightKind::Point { attenuation } => (1.0, attenuation, 1.0, 1.0),
+            LightKind::Spot { attenuation, inner, outer } => (2.0, attenuation, inner.cos(), outer.cos()),
+        };
+
+        Self {
+            position: [position.x, position.y, position.z, kind],
+            direction: [direction.x, direction.y, direction.z, inner],
+            color: [light.color.x, light.color.y, light.color.z, light.intensity],
+            attenuation: [attenuation.constant, attenuation.linear, attenuation.quadratic, outer],
+            shadow: match &placed.shadow_layers {
+                Some(layers) => [
+                    layers.start as f32,
+                    layers.len() as f32,
+                    (light.kind == LightKind::Directional) as u32 as f32,
+                    0.0,
+                ],
+                None => [-1.0, 0.0, 0.0, 0.0],
+            },
+        }
+    }
+}
+
+/// the light as the 5 vec4s of the `Light` struct of the lit shaders.
+pub(crate) fn light_vectors(placed: &PlacedLight) -> [f32; 20] {
+    bytemuck::cast(GpuLight::new(placed))
+}
+
+/// the uniform buffer backing the `Lights` block of lit shaders.
+pub struct LightBuffer {
+    buffer: Buffer<u8>,
+    max_lights: usize,
+}
+
+impl LightBuffer {
+    pub fn new(ctx: &GlContext, max_lights: usize) -> Self {
+        let mut buffer = Buffer::new_uniform(ctx);
+        buffer.bind(ctx);
+        buffer.allocate(ctx, Self::size(max_lights), BufferUsage::Dynamic);
+        buffer.set_label(ctx, "lights");
+
+        Self { buffer, max_lights }
+    }
+
+    /// the size in bytes of a `Lights` block holding `max_lights`.
+    pub fn size(max_lights: usize) -> usize {
+        size_of::<GpuLightsHeader>() + max_lights * size_of::<GpuLight>()
+    }
+
+    pub fn max_lights(&self) -> usize {
+        self.max_lights
+    }
+
+    /// upload the lights. lights beyond `max_lights` are left out.
+    pub fn update(
+        &mut self,
+        ctx: &GlContext,
+        ambient: &AmbientLight,
+        camera_position: &Point3<f32>,
+        lights: &[PlacedLight],
+    ) {
+        if lights.len() > self.max_lights {
+            log::debug!("Dropping {} of {} lights over the limit.", lights.len() - self.max_lights, lights.len());
+        }
+
+        let lights: Vec<GpuLight> = lights
+            .iter()
+            .take(self.max_lights)
+            .map(GpuLight::new)
+            .collect();
+
+        let ambient = ambient.color * ambient.intensity;
+        let header = GpuLightsHeader {
+            ambient: [ambient.x, ambient.y, ambient.z, 1.0],
+            camera_position: [camera_position.x, camera_position.y, camera_position.z, 1.0],
+            count: [lights.len() as i32, 0, 0, 0],
+        };
+
+        let mut data = Vec::with_capacity(Self::size(lights.len()));
+        data.extend_from_slice(bytemuck::bytes_of(&header));
+        data.extend_from_slice(bytemuck::cast_slice(&lights));
+
+        self.buffer.bind(ctx);
+        self.buffer.sub_data(ctx, 0, &data);
+    }
+
+    pub fn bind(&self, ctx: &GlContext) {
+        self.buffer.bind_base(ctx, LIGHTS_BINDING);
+    }
+}
+
+/// compile the built-in blinn-phong program for up to `max_lights`
+/// lights. it expects `MeshVertex` vertices.
+pub fn blinn_phong_program(ctx: &GlContext, max_lights: usize) -> Result<Program> {
+    let program = link_lit_program(ctx, FRAG_SRC, max_lights, &[])?;
+    program.set_label(ctx, "blinn-phong");
+    Ok(program)
+}
+
+/// insert the light and shadow functions into a fragment shader.
+/// it has to be compiled with `MAX_SHADOW_MAPS` defined.
+pub(crate) fn with_lighting(fragment: &str) -> String {
+    with_prelude(&with_prelude(fragment, LIGHTS_SRC), SHADOWS_SRC)
+}
+
+/// link a fragment shader reading the `Lights` and `Shadows` blocks
+/// with the lit vertex shader, inserting the shadow sampling code.
+pub(crate) fn link_lit_program(
+    ctx: &GlContext,
+    fragment: &str,
+    max_lights: usize,
+    defines: &[(&str, String)],
+) -> Result<Program> {
+    let mut defines = defines.to_vec();
+    defines.push(("MAX_LIGHTS", max_lights.max(1).to_string()));
+    defines.push(("MAX_SHADOW_MAPS", MAX_SHADOW_MAPS.to_string()));
+
+    let fragment = with_defines(&with_lighting(fragment), &defines);
+
+    let mut program = Program::new();
+    program.link(ctx, VERT_SRC, &fragment)?;
+    program.uniform_block_binding(ctx, "Lights", LIGHTS_BINDING)?;
+    program.uniform_block_binding(ctx, "Shadows", SHADOWS_BINDING)?;
+
+    program.bind(ctx);
+    program.uniform_1i(ctx, "shadow_maps", SHADOW_MAP_UNIT as i32)?;
+
+    Ok(program)
+}
+
+/// the parameters of the built-in blinn-phong shader. the maps
+/// are multiplied with the colors; the normal map is in tangent space.
+#[derive(Clone)]
+pub struct BlinnPhong {
+    pub diffuse: Vector3<f32>,
+    pub specular: Vector3<f32>,
+    pub shininess: f32,
+
+    pub diffuse_map: Option<Rc<Texture>>,
+    pub specular_map: Option<Rc<Texture>>,
+    pub normal_map: Option<Rc<Texture>>,
+}
+
+impl Default for BlinnPhong {
+    fn default() -> Self {
+        Self {
+            diffuse: Vector3::repeat(1.0),
+            specular: Vector3::repeat(0.5),
+            shininess: 32.0,
+            diffuse_map: None,
+            specular_map: None,
+            normal_map: None,
+        }
+    }
+}
+
+impl BlinnPhong {
+    /// a material using `program`, which should come from `blinn_phong_program`.
+    pub fn material(&self, name: &str, program: Rc<Program>) -> Material {
+        let mut material = Material::new(name, program)
+            .with("diffuse_color", self.diffuse)
+            .with("specular_color", self.specular)
+            .with("shininess", self.shininess);
+
+        for (map, flag, texture) in [
+            ("diffuse_map", "use_diffuse_map", &self.diffuse_map),
+            ("specular_map", "use_specular_map", &self.specular_map),
+            ("normal_map", "use_normal_map", &self.normal_map),
+        ] {
+            material.set(flag, texture.is_some());
+            if let Some(texture) = texture {
+                material.set(map, texture.clone());
+            }
+        }
+
+        material
+    }
+}
@@ -0,0 +1,486 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use gl33::GlFns;
+use nalgebra::{Matrix3, Matrix4, Vector2, Vector3, Vector4};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+use super::{
+    context::GlContext,
+    cubemap::Cubemap,
+    shader::{Program, UniformInfo, UniformType},
+    state::RenderState,
+    texture::Texture,
+};
+
+/// a value a material assigns to a uniform.
+#[derive(Clone)]
+pub enum MaterialParam {
+    Float(f32),
+    Int(i32),
+    UInt(u32),
+    Bool(bool),
+    Vec2(Vector2<f32>),
+    Vec3(Vector3<f32>),
+    Vec4(Vector4<f32>),
+    Mat3(Matrix3<f32>),
+    Mat4(Matrix4<f32>),
+    Texture(Rc<Texture>),
+    Cubemap(Rc<Cubemap>),
+}
+
+impl MaterialParam {
+    /// whether the value can be assigned to a uniform of type `kind`.
+    pub fn matches(&self, kind: UniformType) -> bool {
+        match self {
+            MaterialParam::Float(_) => kind == UniformType::Float,
+            MaterialParam::Int(_) => kind == UniformType::Int,
+            MaterialParam::UInt(_) => kind == UniformType::UInt,
+            MaterialParam::Bool(_) => matches!(kind, UniformType::Bool | UniformType::Int),
+            MaterialParam::Vec2(_) => kind == UniformType::Vec2,
+            MaterialParam::Vec3(_) => kind == UniformType::Vec3,
+            MaterialParam::Vec4(_) => kind == UniformType::Vec4,
+            MaterialParam::Mat3(_) => kind == UniformType::Mat3,
+            MaterialParam::Mat4(_) => kind == UniformType::Mat4,
+            MaterialParam::Texture(_) => kind == UniformType::Sampler2D,
+            MaterialParam::Cubemap(_) => kind == UniformType::SamplerCube,
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            MaterialParam::Float(_) => "float",
+            MaterialParam::Int(_) => "int",
+            MaterialParam::UInt(_) => "uint",
+            MaterialParam::Bool(_) => "bool",
+            MaterialParam::Vec2(_) => "vec2",
+            MaterialParam::Vec3(_) => "vec3",
+            MaterialParam::Vec4(_) => "vec4",
+            MaterialParam::Mat3(_) => "mat3",
+            MaterialParam::Mat4(_) => "mat4",
+            MaterialParam::Texture(_) => "texture",
+            MaterialParam::Cubemap(_) => "cubemap",
+        }
+    }
+
+    /// set the uniform at `location`; textures are sampled from `unit`,
+    /// which the caller has bound them to.
+    fn upload(&self, gl: &GlFns, location: i32, unit: u32) {
+        unsafe {
+            match self {
+                MaterialParam::Float(v) => gl.Uniform1f(location, *v),
+                MaterialParam::Int(v) => gl.Uniform1i(location, *v),
+                MaterialParam::UInt(v) => gl.Uniform1ui(location, *v),
+                MaterialParam::Bool(v) => gl.Uniform1i(location, *v as i32),
+                MaterialParam::Vec2(v) => gl.Uniform2f(location, v.x, v.y),
+                MaterialParam::Vec3(v) => gl.Uniform3f(location, v.x, v.y, v.z),
+                MaterialParam::Vec4(v) => gl.Uniform4f(location, v.x, v.y, v.z, v.w),
+                MaterialParam::Mat3(m) => gl.UniformMatrix3fv(location, 1, 0, m.as_ptr()),
+                MaterialParam::Mat4(m) => gl.UniformMatrix4fv(location, 1, 0, m.as_ptr()),
+                MaterialParam::Texture(_) | MaterialParam::Cubemap(_) => gl.Uniform1i(location, unit as i32),
+            }
+        }
+    }
+}
+
+macro_rules! param_from {
+    ($($ty:ty => $variant:ident),+ $(,)?) => {
+        $(impl From<$ty> for MaterialParam {
+            fn from(value: $ty) -> Self {
+                MaterialParam::$variant(value)
+            }
+        })+
+    };
+}
+
+param_from! {
+    f32 => Float,
+    i32 => Int,
+    u32 => UInt,
+    bool => Bool,
+    Vector2<f32> => Vec2,
+    Vector3<f32> => Vec3,
+    Vector4<f32> => Vec4,
+    Matrix3<f32> => Mat3,
+    Matrix4<f32> => Mat4,
+    Rc<Texture> => Texture,
+    Rc<Cubemap> => Cubemap,
+}
+
+/// a program together with the values of its uniforms and the
+/// render state to draw with.
+#[derive(Clone)]
+pub struct Material {
+    pub name: String,
+    pub program: Rc<Program>,
+    pub state: RenderState,
+
+    /// the program writing the parameters to the g-buffer of the
+    /// `DeferredRenderer`. materials without one are drawn forward.
+    pub deferred: Option<Rc<Program>>,
+
+    params: BTreeMap<String, MaterialParam>,
+}
+
+impl Material {
+    pub fn new(name: &str, program: Rc<Program>) -> Self {
+        Self {
+            name: name.to_string(),
+            program,
+            state: RenderState::default(),
+            deferred: None,
+            params: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_state(mut self, state: RenderState) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// also draw the material into the g-buffer with `program`, e.g.
+    /// from `DeferredRenderer::gbuffer_program`. parameters it does not
+    /// declare, like lighting inputs of the forward program, are skipped.
+    pub fn with_deferred(mut self, program: Rc<Program>) -> Self {
+        self.deferred = Some(program);
+        self
+    }
+
+    pub fn with(mut self, name: &str, value: impl Into<MaterialParam>) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    pub fn set(&mut self, name: &str, value: impl Into<MaterialParam>) {
+        self.params.insert(name.to_string(), value.into());
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<MaterialParam> {
+        self.params.remove(name)
+    }
+
+    pub fn param(&self, name: &str) -> Option<&MaterialParam> {
+        self.params.get(name)
+    }
+
+    pub fn params(&self) -> impl Iterator<Item = (&str, &MaterialParam)> {
+        self.params.iter().map(|(name, param)| (name.as_str(), param))
+    }
+
+    /// the reflected uniform of `program` a parameter is assigned
+    /// to, checking that it exists and has a matching type.
+    fn uniform<'p>(&self, program: &'p Program, name: &str, param: &MaterialParam) -> Result<&'p UniformInfo> {
+        let uniform = program.uniform_info(name).ok_or_else(|| {
+            Error::MaterialParam(format!(
+                "\"{}\" of material \"{}\" is not an active uniform of its program.",
+                name, self.name
+            ))
+        })?;
+
+        if !param.matches(uniform.kind) {
+            return Err(Error::MaterialParam(format!(
+                "\"{}\" of material \"{}\" is a {}, but the program declares {:?}.",
+                name,
+                self.name,
+                param.type_name(),
+                uniform.kind
+            )));
+        }
+
+        Ok(uniform)
+    }
+
+    /// check every parameter against the program's active uniforms.
+    pub fn validate(&self) -> Result<()> {
+        for (name, param) in &self.params {
+            self.uniform(&self.program, name, param)?;
+        }
+
+        for uniform in self.program.uniforms().filter(|uniform| uniform.kind.is_sampler()) {
+            if !self.params.contains_key(&uniform.name) {
+                log::debug!("Sampler \"{}\" is not set by material \"{}\".", uniform.name, self.name);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// use the program, upload the parameters, bind the textures to
+    /// the fixed units of their samplers and apply the render state.
+    /// samplers the material does not set read a default texture.
+    ///
+    /// returns the number of texture units used, so callers can bind
+    /// further textures after them.
+    pub fn bind(&self, ctx: &GlContext) -> Result<u32> {
+        self.bind_program(ctx, &self.program, false)
+    }
+
+    /// like `bind`, but with the deferred program.
+    pub fn bind_deferred(&self, ctx: &GlContext) -> Result<u32> {
+        let program = self.deferred.as_ref().ok_or_else(|| {
+            Error::MaterialParam(format!("Material \"{}\" has no deferred program.", self.name))
+        })?;
+        self.bind_program(ctx, program, true)
+    }
+
+    fn bind_program(&self, ctx: &GlContext, program: &Program, skip_missing: bool) -> Result<u32> {
+        program.bind(ctx);
+
+        for (name, param) in &self.params {
+            if skip_missing && program.uniform_info(name).is_none() {
+                continue;
+            }
+            let uniform = self.uniform(program, name, param)?;
+
+            if !uniform.kind.is_sampler() {
+                param.upload(ctx, uniform.location, 0);
+            } else if program.sampler_unit(name).is_none() {
+                return Err(Error::MaterialParam(format!(
+                    "Material \"{}\" uses more than {} textures.",
+                    self.name, ctx.texture_units() - 1
+                )));
+            }
+        }
+
+        // every sampler keeps its unit, so one left unset never
+        // reads a texture of another type bound by an earlier draw
+        let mut units = 0;
+        for (sampler, unit) in program.samplers() {
+            let texture = match self.params.get(&sampler.name) {
+                Some(param @ MaterialParam::Texture(texture)) => Some((param, gl33::GL_TEXTURE_2D, texture.id)),
+                Some(param @ MaterialParam::Cubemap(cubemap)) => Some((param, gl33::GL_TEXTURE_CUBE_MAP, cubemap.id)),
+                _ => None,
+            };
+
+            match texture {
+                Some((param, target, id)) => {
+                    ctx.bind_texture_unit(unit, target, id);
+                    param.upload(ctx, sampler.location, unit);
+                }
+                None => {
+                    let Some(target) = sampler.kind.texture_target() else {
+                        continue;
+                    };
+                    ctx.bind_texture_unit(unit, target, ctx.default_texture(target));
+                    unsafe {
+                        ctx.Uniform1i(sampler.location, unit as i32);
+                    }
+                }
+            }
+            units = units.max(unit + 1);
+        }
+
+        ctx.apply_state(&self.state);
+        Ok(units)
+    }
+
+    /// load a material description from a `.ron` or `.json` file.
+    pub fn load(ctx: &GlContext, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut desc = MaterialDesc::load(path)?;
+
+        if desc.name.is_empty() {
+            desc.name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        }
+
+        desc.build(ctx, path.parent().unwrap_or(Path::new("")))
+    }
+}
+
+/// a parameter value as written in a material description.
+/// matrices are given in column-major order, like in glsl.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ParamDesc {
+    Float(f32),
+    Int(i32),
+    UInt(u32),
+    Bool(bool),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Mat3([f32; 9]),
+    Mat4([f32; 16]),
+
+    /// the path of an image file
+    Texture(PathBuf),
+}
+
+/// the serialized form of a `Material`, e.g.
+///
+/// ```ron
+/// (
+///     vertex: "../shaders/vertex.glsl",
+///     fragment: "../shaders/fragment.glsl",
+///     params: {
+///         "texture1": Texture("../textures/container.jpg"),
+///         "tint": Vec3((1.0, 0.5, 0.5)),
+///     },
+///     state: (cull: None),
+/// )
+/// ```
+///
+/// shader and texture paths are relative to the description file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaterialDesc {
+    #[serde(default)]
+    pub name: String,
+
+    pub vertex: PathBuf,
+    pub fragment: PathBuf,
+
+    #[serde(default)]
+    pub params: BTreeMap<String, ParamDesc>,
+
+    #[serde(default)]
+    pub state: RenderState,
+}
+
+impl MaterialDesc {
+    pub fn from_ron(source: &str) -> Result<Self> {
+        ron::from_str(source).map_err(|err| Error::AssetParse(format!("Invalid ron material. {}", err)))
+    }
+
+    pub fn from_json(source: &str) -> Result<Self> {
+        serde_json::from_str(source).map_err(|err| Error::AssetParse(format!("Invalid json material. {}", err)))
+    }
+
+    /// read a description, choosing the format by the file extension.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        log::debug!("Loading material from file... {}", path.display());
+
+        let source = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("ron") => Self::from_ron(&source),
+            Some("json") => Self::from_json(&source),
+            _ => Err(Error::AssetParse(format!(
+                "{}: unknown material format, expected .ron or .json.",
+                path.display()
+            ))),
+        }
+    }
+
+    /// compile the program and load the textures, resolving
+    /// paths relative to `base`.
+    pub fn build(&self, ctx: &GlContext, base: &Path) -> Result<Material> {
+        let vertex = std::fs::read_to_string(base.join(&self.vertex))?;
+        let fragment = std::fs::read_to_string(base.join(&self.fragment))?;
+
+        let mut program = Program::new();
+        program.link(ctx, &vertex, &fragment)?;
+
+        let mut material = Material::new(&self.name, Rc::new(program)).with_state(self.state);
+        let mut textures: HashMap<PathBuf, Rc<Texture>> = HashMap::new();
+
+        for (name, param) in &self.params {
+            let param = match param {
+                ParamDesc::Float(v) => MaterialParam::Float(*v),
+                ParamDesc::Int(v) => MaterialParam::Int(*v),
+                ParamDesc::UInt(v) => MaterialParam::UInt(*v),
+                ParamDesc::Bool(v) => MaterialParam::Bool(*v),
+                ParamDesc::Vec2(v) => MaterialParam::Vec2(Vector2::from(*v)),
+                ParamDesc::Vec3(v) => MaterialParam::Vec3(Vector3::from(*v)),
+                ParamDesc::Vec4(v) => MaterialParam::Vec4(Vector4::from(*v)),
+                ParamDesc::Mat3(m) => MaterialParam::Mat3(Matrix3::from_column_slice(m)),
+                ParamDesc::Mat4(m) => MaterialParam::Mat4(Matrix4::from_column_slice(m)),
+                ParamDesc::Texture(path) => {
+                    let path = base.join(path);
+                    let texture = match textures.get(&path) {
+                        Some(texture) => texture.clone(),
+                        None => {
+                            let texture = Rc::new(Texture::load_file(ctx, &path.to_string_lossy())?);
+                            texture.set_label(ctx, &path.to_string_lossy());
+                            textures.insert(path, texture.clone());
+                            texture
+                        },
+                    };
+                    MaterialParam::Texture(texture)
+                },
+            };
+            material.set(name, param);
+        }
+
+        material.validate()?;
+        log::info!("Loaded material \"{}\" ({} parameters).", material.name, material.params.len());
+
+        Ok(material)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::display::state::CullMode;
+
+    use super::*;
+
+    #[test]
+    fn parses_ron_descriptions() {
+        let desc = MaterialDesc::from_ron(
+            r#"(
+                vertex: "shaders/vertex.glsl",
+                fragment: "shaders/fragment.glsl",
+                params: {
+                    "albedo": Texture("textures/albedo.png"),
+                    "tint": Vec3((1.0, 0.5, 0.5)),
+                    "lit": Bool(true),
+                },
+                state: (cull: None),
+            )"#,
+        )
+        .unwrap();
+
+        assert_eq!(desc.name, "");
+        assert_eq!(desc.vertex, Path::new("shaders/vertex.glsl"));
+        assert_eq!(desc.params["albedo"], ParamDesc::Texture("textures/albedo.png".into()));
+        assert_eq!(desc.params["tint"], ParamDesc::Vec3([1.0, 0.5, 0.5]));
+        assert_eq!(desc.params["lit"], ParamDesc::Bool(true));
+        assert_eq!(desc.state.cull, CullMode::None);
+        assert_eq!(desc.state.front_face, RenderState::default().front_face);
+    }
+
+    #[test]
+    fn parses_json_descriptions() {
+        let desc = MaterialDesc::from_json(
+            r#"{
+                "name": "metal",
+                "vertex": "vertex.glsl",
+                "fragment": "fragment.glsl",
+                "params": { "roughness": { "Float": 0.25 } }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(desc.name, "metal");
+        assert_eq!(desc.params["roughness"], ParamDesc::Float(0.25));
+        assert_eq!(desc.state, RenderState::default());
+    }
+
+    #[test]
+    fn rejects_invalid_descriptions() {
+        assert!(matches!(MaterialDesc::from_ron("(vertex: \"a.glsl\")"), Err(Error::AssetParse(_))));
+        assert!(matches!(MaterialDesc::from_json("{\"vertex\": 1}"), Err(Error::AssetParse(_))));
+        assert!(matches!(
+            MaterialDesc::from_ron("(vertex: \"a\", fragment: \"b\", params: {\"x\": Float(\"y\")})"),
+            Err(Error::AssetParse(_))
+        ));
+    }
+
+    #[test]
+    fn params_match_uniform_types() {
+        assert!(MaterialParam::Float(1.0).matches(UniformType::Float));
+        assert!(!MaterialParam::Float(1.0).matches(UniformType::Int));
+        assert!(MaterialParam::Bool(true).matches(UniformType::Bool));
+        assert!(MaterialParam::Bool(true).matches(UniformType::Int));
+        assert!(!MaterialParam::Int(1).matches(UniformType::Bool));
+        assert!(!MaterialParam::UInt(1).matches(UniformType::Int));
+        assert!(MaterialParam::Vec3(Vector3::zeros()).matches(UniformType::Vec3));
+        assert!(!MaterialParam::Vec3(Vector3::zeros()).matches(UniformType::Vec4));
+        assert!(MaterialParam::Mat4(Matrix4::identity()).matches(UniformType::Mat4));
+        assert!(!MaterialParam::Mat4(Matrix4::identity()).matches(UniformType::Mat3));
+    }
+}
@@ -0,0 +1,512 @@
+use std::ops::Range;
+
+use gl33::GLenum;
+use nalgebra::{Vector2, Vector3};
+
+use crate::{Error, Result};
+
+use super::{context::GlContext, vertex::{Buffer, MeshVertex, VertexArray, VertexLayout}};
+
+/// how vertices are assembled into primitives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    Points,
+    Lines,
+    LineStrip,
+    LineLoop,
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+}
+
+impl Topology {
+    fn gl(self) -> GLenum {
+        match self {
+            Topology::Points => gl33::GL_POINTS,
+            Topology::Lines => gl33::GL_LINES,
+            Topology::LineStrip => gl33::GL_LINE_STRIP,
+            Topology::LineLoop => gl33::GL_LINE_LOOP,
+            Topology::Triangles => gl33::GL_TRIANGLES,
+            Topology::TriangleStrip => gl33::GL_TRIANGLE_STRIP,
+            Topology::TriangleFan => gl33::GL_TRIANGLE_FAN,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexType {
+    U16,
+    U32,
+}
+
+impl IndexType {
+    fn gl(self) -> GLenum {
+        match self {
+            IndexType::U16 => gl33::GL_UNSIGNED_SHORT,
+            IndexType::U32 => gl33::GL_UNSIGNED_INT,
+        }
+    }
+
+    pub fn size(self) -> usize {
+        match self {
+            IndexType::U16 => std::mem::size_of::<u16>(),
+            IndexType::U32 => std::mem::size_of::<u32>(),
+        }
+    }
+}
+
+/// index data handed to `Mesh::new`.
+#[derive(Debug, Clone, Copy)]
+pub enum Indices<'a> {
+    U16(&'a [u16]),
+    U32(&'a [u32]),
+}
+
+impl Indices<'_> {
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn index_type(&self) -> IndexType {
+        match self {
+            Indices::U16(_) => IndexType::U16,
+            Indices::U32(_) => IndexType::U32,
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Indices::U16(indices) => bytemuck::cast_slice(indices),
+            Indices::U32(indices) => bytemuck::cast_slice(indices),
+        }
+    }
+}
+
+/// a vertex array together with the buffers it reads
+/// from and everything needed to issue a draw call.
+pub struct Mesh {
+    pub vao: VertexArray,
+    pub vertices: Buffer<u8>,
+
+    /// the index buffer, `None` for non-indexed meshes
+    pub indices: Option<Buffer<u8>>,
+
+    pub index_type: IndexType,
+    pub topology: Topology,
+    pub layout: VertexLayout,
+    pub vertex_count: usize,
+    pub index_count: usize,
+}
+
+impl Mesh {
+    pub fn new<V: bytemuck::Pod>(
+        ctx: &GlContext,
+        layout: VertexLayout,
+        vertices: &[V],
+        indices: Option<Indices>,
+        topology: Topology,
+    ) -> Self {
+        log::debug!(
+            "Creating mesh... (vertices = {}, indices = {})",
+            vertices.len(),
+            indices.map_or(0, |i| i.len())
+        );
+
+        let vao = VertexArray::new(ctx);
+        vao.bind(ctx);
+
+        let mut vertex_buffer = Buffer::<u8>::new_vertex(ctx);
+        vertex_buffer.bind(ctx);
+        vertex_buffer.data(ctx, bytemuck::cast_slice(vertices));
+
+        layout.apply(ctx);
+
+        let index_buffer = indices.map(|indices| {
+            let mut buffer = Buffer::<u8>::new_element(ctx);
+            buffer.bind(ctx);
+            buffer.data(ctx, indices.as_bytes());
+            buffer
+        });
+
+        VertexArray::unbind(ctx);
+
+        Self {
+            vao,
+            vertices: vertex_buffer,
+            indices: index_buffer,
+            index_type: indices.map_or(IndexType::U32, |i| i.index_type()),
+            topology,
+            layout,
+            vertex_count: vertices.len(),
+            index_count: indices.map_or(0, |i| i.len()),
+        }
+    }
+
+    /// make the vertex array read per-instance attributes from `buffer`.
+    /// the attributes of `layout` should use a non-zero divisor.
+    pub fn attach_instances<T>(&self, ctx: &GlContext, buffer: &Buffer<T>, layout: &VertexLayout) {
+        log::debug!("Attaching instance buffer {} to mesh.", buffer.id);
+
+        self.vao.bind(ctx);
+        buffer.bind(ctx);
+        layout.apply(ctx);
+        VertexArray::unbind(ctx);
+    }
+
+    /// name the vertex array and buffers in debug messages
+    /// and graphics debuggers.
+    pub fn set_label(&self, ctx: &GlContext, label: &str) {
+        self.vao.set_label(ctx, label);
+        self.vertices.set_label(ctx, &format!("{} vertices", label));
+        if let Some(indices) = &self.indices {
+            indices.set_label(ctx, &format!("{} indices", label));
+        }
+    }
+
+    pub fn is_indexed(&self) -> bool {
+        self.indices.is_some()
+    }
+
+    /// the number of elements a full draw consumes, i.e. the
+    /// index count for indexed meshes and the vertex count otherwise.
+    pub fn element_count(&self) -> usize {
+        if self.is_indexed() {
+            self.index_count
+        } else {
+            self.vertex_count
+        }
+    }
+
+    fn index_offset(&self, start: usize) -> *const std::ffi::c_void {
+        (start * self.index_type.size()) as *const _
+    }
+
+    #[track_caller]
+    pub fn draw(&self, ctx: &GlContext) {
+        self.vao.bind(ctx);
+
+        unsafe {
+            if self.is_indexed() {
+                ctx.DrawElements(
+                    self.topology.gl(),
+                    self.index_count as i32,
+                    self.index_type.gl(),
+                    std::ptr::null(),
+                );
+            } else {
+                ctx.DrawArrays(self.topology.gl(), 0, self.vertex_count as i32);
+            }
+        }
+        ctx.check_errors();
+    }
+
+    /// draw the whole mesh `instances` times.
+    #[track_caller]
+    pub fn draw_instanced(&self, ctx: &GlContext, instances: usize) {
+        self.vao.bind(ctx);
+
+        unsafe {
+            if self.is_indexed() {
+                ctx.DrawElementsInstanced(
+                    self.topology.gl(),
+                    self.index_count as i32,
+                    self.index_type.gl(),
+                    std::ptr::null(),
+                    instances as i32,
+                );
+            } else {
+                ctx.DrawArraysInstanced(self.topology.gl(), 0, self.vertex_count as i32, instances as i32);
+            }
+        }
+        ctx.check_errors();
+    }
+
+    /// draw a sub-range of the indices, or of the vertices
+    /// for non-indexed meshes.
+    #[track_caller]
+    pub fn draw_range(&self, ctx: &GlContext, range: Range<usize>) -> Result<()> {
+        check_range(&range, self.element_count())?;
+        self.vao.bind(ctx);
+
+        let count = (range.end - range.start) as i32;
+        unsafe {
+            if self.is_indexed() {
+                ctx.DrawElements(self.topology.gl(), count, self.index_type.gl(), self.index_offset(range.start));
+            } else {
+                ctx.DrawArrays(self.topology.gl(), range.start as i32, count);
+            }
+        }
+        ctx.check_errors();
+
+        Ok(())
+    }
+
+    /// like `draw_range`, but `base_vertex` is added to every index
+    /// before fetching the vertex. for non-indexed meshes the base
+    /// vertex offsets the first vertex drawn.
+    #[track_caller]
+    pub fn draw_base_vertex(&self, ctx: &GlContext, range: Range<usize>, base_vertex: i32) -> Result<()> {
+        check_range(&range, self.element_count())?;
+        let first = if self.is_indexed() { 0 } else { first_vertex(&range, base_vertex, self.element_count())? };
+
+        self.vao.bind(ctx);
+        let count = (range.end - range.start) as i32;
+        unsafe {
+            if self.is_indexed() {
+                ctx.DrawElementsBaseVertex(
+                    self.topology.gl(),
+                    count,
+                    self.index_type.gl(),
+                    self.index_offset(range.start),
+                    base_vertex,
+                );
+            } else {
+                ctx.DrawArrays(self.topology.gl(), first, count);
+            }
+        }
+        ctx.check_errors();
+
+        Ok(())
+    }
+}
+
+/// check that a draw of `range` stays within `count` elements.
+fn check_range(range: &Range<usize>, count: usize) -> Result<()> {
+    if range.start > range.end || range.end > count {
+        log::error!("Draw range {:?} out of bounds (count = {}).", range, count);
+        return Err(Error::GlDrawRange(format!("{:?} exceeds element count {}.", range, count)));
+    }
+
+    Ok(())
+}
+
+/// the first vertex of a non-indexed draw of `range` offset by
+/// `base_vertex`. without indices the base vertex moves the drawn
+/// range itself, which has to stay within the `count` vertices.
+fn first_vertex(range: &Range<usize>, base_vertex: i32, count: usize) -> Result<i32> {
+    i32::try_from(range.start)
+        .ok()
+        .and_then(|start| start.checked_add(base_vertex))
+        .filter(|&first| first >= 0 && range.start <= range.end && first as usize + range.len() <= count)
+        .ok_or_else(|| {
+            log::error!("Draw range {:?} with base vertex {} out of bounds.", range, base_vertex);
+            Error::GlDrawRange(format!("{:?} offset by {} exceeds vertex count {}.", range, base_vertex, count))
+        })
+}
+
+/// cpu-side geometry with one entry per vertex in each
+/// attribute list and triangle list indices.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshData {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub tangents: Vec<[f32; 4]>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    pub fn push_vertex(&mut self, position: [f32; 3], normal: [f32; 3], uv: [f32; 2]) -> u32 {
+        self.positions.push(position);
+        self.normals.push(normal);
+        self.uvs.push(uv);
+        (self.positions.len() - 1) as u32
+    }
+
+    pub fn push_triangle(&mut self, a: u32, b: u32, c: u32) {
+        self.indices.extend_from_slice(&[a, b, c]);
+    }
+
+    /// interleave the attribute lists. missing normals, uvs
+    /// or tangents are filled with zeros.
+    pub fn vertices(&self) -> Vec<MeshVertex> {
+        (0..self.vertex_count())
+            .map(|i| MeshVertex {
+                position: self.positions[i],
+                normal: self.normals.get(i).copied().unwrap_or_default(),
+                uv: self.uvs.get(i).copied().unwrap_or_default(),
+                tangent: self.tangents.get(i).copied().unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    /// create a triangle mesh using the `MeshVertex` layout. 16 bit
+    /// indices are used whenever the vertex count allows it.
+    pub fn upload(&self, ctx: &GlContext) -> Mesh {
+        let vertices = self.vertices();
+
+        if self.vertex_count() <= u16::MAX as usize + 1 {
+            let indices: Vec<u16> = self.indices.iter().map(|&i| i as u16).collect();
+            Mesh::new(ctx, MeshVertex::layout(), &vertices, Some(Indices::U16(&indices)), Topology::Triangles)
+        } else {
+            Mesh::new(ctx, MeshVertex::layout(), &vertices, Some(Indices::U32(&self.indices)), Topology::Triangles)
+        }
+    }
+
+    /// replace the normals with the area weighted average of the
+    /// normals of the faces sharing each vertex.
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![Vector3::<f32>::zeros(); self.vertex_count()];
+
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+            let p0 = Vector3::from(self.positions[a]);
+            let face = (Vector3::from(self.positions[b]) - p0).cross(&(Vector3::from(self.positions[c]) - p0));
+            for i in [a, b, c] {
+                normals[i] += face;
+            }
+        }
+
+        self.normals = normals
+            .into_iter()
+            .map(|n| n.try_normalize(f32::EPSILON).unwrap_or_else(Vector3::y).into())
+            .collect();
+    }
+
+    /// derive per-vertex tangents from the positions, normals and uvs.
+    ///
+    /// tangents of the triangles sharing a vertex are accumulated,
+    /// then orthogonalized against the vertex normal. `w` holds the
+    /// handedness of the bitangent. without uvs every tangent is some
+    /// direction perpendicular to the normal, without normals +x.
+    pub fn compute_tangents(&mut self) {
+        let count = self.vertex_count();
+        if self.normals.len() != count {
+            self.tangents = vec![[1.0, 0.0, 0.0, 1.0]; count];
+            return;
+        }
+
+        let mut tangents = vec![Vector3::<f32>::zeros(); count];
+        let mut bitangents = vec![Vector3::<f32>::zeros(); count];
+
+        let triangles = if self.uvs.len() == count { self.indices.chunks_exact(3) } else { [].chunks_exact(3) };
+        for triangle in triangles {
+            let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+
+            let p0 = Vector3::from(self.positions[a]);
+            let e1 = Vector3::from(self.positions[b]) - p0;
+            let e2 = Vector3::from(self.positions[c]) - p0;
+
+            let uv0 = Vector2::from(self.uvs[a]);
+            let d1 = Vector2::from(self.uvs[b]) - uv0;
+            let d2 = Vector2::from(self.uvs[c]) - uv0;
+
+            let det = d1.x * d2.y - d2.x * d1.y;
+            if det.abs() < f32::EPSILON {
+                continue;
+            }
+
+            let r = 1.0 / det;
+            let tangent = (e1 * d2.y - e2 * d1.y) * r;
+            let bitangent = (e2 * d1.x - e1 * d2.x) * r;
+
+            for i in [a, b, c] {
+                tangents[i] += tangent;
+                bitangents[i] += bitangent;
+            }
+        }
+
+        self.tangents = (0..count)
+            .map(|i| {
+                let n = Vector3::from(self.normals[i]);
+
+                // Gram-Schmidt against the normal, falling back to any
+                // perpendicular direction when the uvs were degenerate
+                let t = tangents[i] - n * n.dot(&tangents[i]);
+                let t = t.try_normalize(f32::EPSILON).unwrap_or_else(|| {
+                    let axis = if n.x.abs() < 0.9 { Vector3::x() } else { Vector3::y() };
+                    n.cross(&axis).normalize()
+                });
+
+                let w = if n.cross(&t).dot(&bitangents[i]) < 0.0 { -1.0 } else { 1.0 };
+                [t.x, t.y, t.z, w]
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle() -> MeshData {
+        let mut data = MeshData::default();
+        for p in [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]] {
+            data.push_vertex(p, [0.0, 0.0, 1.0], [p[0], p[1]]);
+        }
+        data.push_triangle(0, 1, 2);
+        data
+    }
+
+    #[test]
+    fn tangents_follow_uvs() {
+        let mut data = triangle();
+        data.compute_tangents();
+        assert_eq!(data.tangents, vec![[1.0, 0.0, 0.0, 1.0]; 3]);
+    }
+
+    #[test]
+    fn tangents_without_uvs() {
+        let mut data = triangle();
+        data.uvs.clear();
+        data.compute_tangents();
+
+        assert_eq!(data.tangents.len(), 3);
+        for (tangent, normal) in data.tangents.iter().zip(&data.normals) {
+            let (t, n) = (Vector3::new(tangent[0], tangent[1], tangent[2]), Vector3::from(*normal));
+            assert!((t.norm() - 1.0).abs() < 1e-5);
+            assert!(t.dot(&n).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn tangents_without_normals() {
+        let mut data = triangle();
+        data.normals.clear();
+        data.uvs.clear();
+        data.compute_tangents();
+        assert_eq!(data.tangents, vec![[1.0, 0.0, 0.0, 1.0]; 3]);
+    }
+
+    #[test]
+    fn checks_draw_ranges() {
+        assert!(check_range(&(0..6), 6).is_ok());
+        assert!(check_range(&(6..6), 6).is_ok());
+        assert!(check_range(&(2..4), 6).is_ok());
+        assert!(matches!(check_range(&(0..7), 6), Err(Error::GlDrawRange(_))));
+        assert!(matches!(check_range(&(7..7), 6), Err(Error::GlDrawRange(_))));
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = 4..2;
+        assert!(matches!(check_range(&reversed, 6), Err(Error::GlDrawRange(_))));
+    }
+
+    #[test]
+    fn offsets_the_first_vertex() {
+        assert_eq!(first_vertex(&(0..3), 0, 6).unwrap(), 0);
+        assert_eq!(first_vertex(&(0..3), 3, 6).unwrap(), 3);
+        assert_eq!(first_vertex(&(3..6), -3, 6).unwrap(), 0);
+        assert_eq!(first_vertex(&(2..2), 4, 6).unwrap(), 6);
+
+        // past the end, before the start and overflowing offsets
+        assert!(matches!(first_vertex(&(0..3), 4, 6), Err(Error::GlDrawRange(_))));
+        assert!(matches!(first_vertex(&(2..4), -3, 6), Err(Error::GlDrawRange(_))));
+        assert!(matches!(first_vertex(&(1..2), i32::MAX, usize::MAX), Err(Error::GlDrawRange(_))));
+        assert!(matches!(first_vertex(&(0..1), i32::MIN, 6), Err(Error::GlDrawRange(_))));
+        assert!(matches!(first_vertex(&(1 << 40..1 << 40), 0, usize::MAX), Err(Error::GlDrawRange(_))));
+    }
+}
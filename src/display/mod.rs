@@ -12,3 +12,60 @@ pub mod vertex;
 /// module for abstracting away textures
 /// and their representation in opengl
 pub mod texture;
+
+/// module for cube map textures
+pub mod cubemap;
+
+/// module for offscreen render targets
+pub mod framebuffer;
+
+/// module for materials binding a program to its
+/// uniform values, textures and render state
+pub mod material;
+
+/// module for light sources, the lights uniform
+/// block and the built-in blinn-phong shader
+pub mod light;
+
+/// module for shadow maps of directional
+/// and spot lights
+pub mod shadow;
+
+/// module for the metallic-roughness pbr shader
+/// and image based lighting
+pub mod pbr;
+
+/// module for the deferred shading path with
+/// its g-buffer and light volumes
+pub mod deferred;
+
+/// module for describing depth, stencil, blend and
+/// rasterizer state and applying it to opengl
+pub mod state;
+
+/// module for opengl debug output, object
+/// labels and error checks
+pub mod debug;
+
+/// module for tracking opengl bindings and
+/// skipping redundant state changes
+pub mod context;
+
+/// module for bundling vertex arrays, buffers
+/// and topology into drawable meshes
+pub mod mesh;
+
+/// module for streaming per-instance data
+/// used by instanced draw calls
+pub mod instance;
+
+/// module for generating geometry of common shapes
+pub mod primitives;
+
+/// module for cameras, projections and
+/// screen to world unprojection
+pub mod camera;
+
+/// module for moving cameras in response
+/// to glfw input
+pub mod controller;
@@ -0,0 +1,420 @@
+use std::rc::Rc;
+
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+
+use crate::{assets::gltf::{AlphaMode, GltfMaterial, TextureRef}, Result};
+
+use super::{
+    context::GlContext,
+    cubemap::Cubemap,
+    framebuffer::{set_viewport, viewport, Framebuffer},
+    light::link_lit_program,
+    material::Material,
+    mesh::Mesh,
+    primitives,
+    shader::{with_prelude, Program},
+    state::{CompareFunc, CullMode, DepthState, RenderState},
+    texture::{Texture, TextureFilter, TextureFormat, TextureWrap},
+};
+
+const FRAG_SRC: &str = include_str!("../../res/shaders/pbr_fragment.glsl");
+
+/// the cook-torrance brdf shared by the pbr and deferred lighting shaders.
+pub(crate) const BRDF_SRC: &str = include_str!("../../res/shaders/brdf.glsl");
+
+/// the material uniforms and their sampling, shared by the pbr and g-buffer shaders.
+pub(crate) const SURFACE_SRC: &str = include_str!("../../res/shaders/pbr_surface.glsl");
+
+const CAPTURE_VERT_SRC: &str = include_str!("../../res/shaders/cubemap_capture_vertex.glsl");
+const EQUIRECT_FRAG_SRC: &str = include_str!("../../res/shaders/equirect_to_cubemap_fragment.glsl");
+const IRRADIANCE_FRAG_SRC: &str = include_str!("../../res/shaders/irradiance_fragment.glsl");
+const PREFILTER_FRAG_SRC: &str = include_str!("../../res/shaders/prefilter_fragment.glsl");
+
+const FULLSCREEN_VERT_SRC: &str = include_str!("../../res/shaders/fullscreen_vertex.glsl");
+const BRDF_LUT_FRAG_SRC: &str = include_str!("../../res/shaders/brdf_lut_fragment.glsl");
+
+/// the hammersley sequence and ggx importance sampling
+/// shared by the prefilter and brdf lut shaders.
+const IBL_SAMPLING_SRC: &str = include_str!("../../res/shaders/ibl_sampling.glsl");
+
+/// compile the built-in metallic-roughness program for up to
+/// `max_lights` lights. it expects `MeshVertex` vertices.
+///
+/// with `ibl` the ambient term is taken from an `Environment`
+/// instead of the `AmbientLight`, and materials built for the
+/// program have to be given one.
+pub fn pbr_program(ctx: &GlContext, max_lights: usize, ibl: bool) -> Result<Program> {
+    let defines = if ibl { vec![("USE_IBL", String::new())] } else { Vec::new() };
+    let fragment = with_prelude(&with_prelude(FRAG_SRC, SURFACE_SRC), BRDF_SRC);
+    let program = link_lit_program(ctx, &fragment, max_lights, &defines)?;
+    program.set_label(ctx, if ibl { "pbr ibl" } else { "pbr" });
+    Ok(program)
+}
+
+/// the parameters of the built-in pbr shader, following the
+/// gltf metallic-roughness model. the factors are multiplied
+/// with the maps; base color and emissive maps are in srgb.
+#[derive(Clone)]
+pub struct Pbr {
+    pub base_color: Vector4<f32>,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: Vector3<f32>,
+    pub normal_scale: f32,
+    pub occlusion_strength: f32,
+
+    pub base_color_map: Option<Rc<Texture>>,
+
+    /// roughness in the green channel, metalness in the blue channel
+    pub metallic_roughness_map: Option<Rc<Texture>>,
+
+    pub normal_map: Option<Rc<Texture>>,
+
+    /// ambient occlusion in the red channel
+    pub occlusion_map: Option<Rc<Texture>>,
+
+    pub emissive_map: Option<Rc<Texture>>,
+
+    pub state: RenderState,
+}
+
+impl Default for Pbr {
+    fn default() -> Self {
+        Self {
+            base_color: Vector4::repeat(1.0),
+            metallic: 0.0,
+            roughness: 0.5,
+            emissive: Vector3::zeros(),
+            normal_scale: 1.0,
+            occlusion_strength: 1.0,
+            base_color_map: None,
+            metallic_roughness_map: None,
+            normal_map: None,
+            occlusion_map: None,
+            emissive_map: None,
+            state: RenderState::default(),
+        }
+    }
+}
+
+impl Pbr {
+    /// the parameters of an imported gltf material. `textures`
+    /// are the scene's textures, indexed like `GltfScene::textures`.
+    pub fn from_gltf(material: &GltfMaterial, textures: &[Rc<Texture>]) -> Self {
+        let texture = |reference: Option<TextureRef>| {
+            let reference = reference?;
+            if reference.tex_coord != 0 {
+                log::warn!("Only the first uv set is supported, texture {} uses set {}.", reference.texture, reference.tex_coord);
+            }
+            textures.get(reference.texture).cloned()
+        };
+
+        let mut state = match material.alpha_mode {
+            AlphaMode::Blend => RenderState::transparent(),
+            AlphaMode::Opaque | AlphaMode::Mask => RenderState::opaque(),
+        };
+        if material.double_sided {
+            state.cull = CullMode::None;
+        }
+
+        Self {
+            base_color: Vector4::from(material.base_color),
+            metallic: material.metallic,
+            roughness: material.roughness,
+            emissive: Vector3::from(material.emissive),
+            normal_scale: material.normal_scale,
+            occlusion_strength: material.occlusion_strength,
+            base_color_map: texture(material.base_color_texture),
+            metallic_roughness_map: texture(material.metallic_roughness_texture),
+            normal_map: texture(material.normal_texture),
+            occlusion_map: texture(material.occlusion_texture),
+            emissive_map: texture(material.emissive_texture),
+            state,
+        }
+    }
+
+    /// a material using `program`, which should come from `pbr_program`,
+    /// compiled with ibl exactly when an `environment` is given.
+    pub fn material(&self, name: &str, program: Rc<Program>, environment: Option<&Environment>) -> Material {
+        let mut material = Material::new(name, program)
+            .with_state(self.state)
+            .with("base_color", self.base_color)
+            .with("metallic", self.metallic)
+            .with("roughness", self.roughness)
+            .with("emissive", self.emissive)
+            .with("normal_scale", self.normal_scale)
+            .with("occlusion_strength", self.occlusion_strength);
+
+        for (map, flag, texture) in [
+            ("base_color_map", "use_base_color_map", &self.base_color_map),
+            ("metallic_roughness_map", "use_metallic_roughness_map", &self.metallic_roughness_map),
+            ("normal_map", "use_normal_map", &self.normal_map),
+            ("occlusion_map", "use_occlusion_map", &self.occlusion_map),
+            ("emissive_map", "use_emissive_map", &self.emissive_map),
+        ] {
+            material.set(flag, texture.is_some());
+            if let Some(texture) = texture {
+                material.set(map, texture.clone());
+            }
+        }
+
+        if let Some(environment) = environment {
+            material.set("irradiance_map", environment.irradiance.clone());
+            material.set("prefiltered_map", environment.prefiltered.clone());
+            material.set("brdf_lut", environment.brdf_lut.clone());
+            material.set("max_reflection_lod", environment.max_reflection_lod());
+        }
+
+        material
+    }
+}
+
+/// the resolutions and sample counts used to preprocess an `Environment`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IblSettings {
+    /// the face size of the cube map the equirectangular image is projected to
+    pub environment_size: u32,
+
+    pub irradiance_size: u32,
+
+    /// the angle in radians between the samples of the irradiance convolution
+    pub irradiance_sample_delta: f32,
+
+    pub prefiltered_size: u32,
+
+    /// the number of roughness levels in the prefiltered mip chain
+    pub prefiltered_levels: u32,
+
+    pub brdf_lut_size: u32,
+
+    /// the number of importance samples per texel of the
+    /// prefiltered map and the brdf lut
+    pub sample_count: u32,
+}
+
+impl Default for IblSettings {
+    fn default() -> Self {
+        Self {
+            environment_size: 512,
+            irradiance_size: 32,
+            irradiance_sample_delta: 0.025,
+            prefiltered_size: 128,
+            prefiltered_levels: 5,
+            brdf_lut_size: 512,
+            sample_count: 1024,
+        }
+    }
+}
+
+/// the image based lighting of a hdr environment, preprocessed
+/// for the split sum approximation of the pbr shader.
+pub struct Environment {
+    /// the environment itself, with a full mip chain, e.g. for a skybox
+    pub environment: Rc<Cubemap>,
+
+    /// the cosine weighted irradiance for diffuse lighting
+    pub irradiance: Rc<Cubemap>,
+
+    /// the environment convolved with the ggx lobe, with
+    /// increasing roughness in every mip level
+    pub prefiltered: Rc<Cubemap>,
+
+    /// the scale and bias to F0 by view angle and roughness
+    pub brdf_lut: Rc<Texture>,
+}
+
+impl Environment {
+    /// load an equirectangular hdr image and preprocess it.
+    pub fn load(ctx: &GlContext, path: &str, settings: &IblSettings) -> Result<Self> {
+        let texture = Texture::load_hdr(ctx, path)?;
+        Self::from_equirectangular(ctx, &texture, settings)
+    }
+
+    /// project an equirectangular hdr texture to a cube map and
+    /// compute the irradiance map, the prefiltered specular map and
+    /// the brdf lut from it. everything is rendered offscreen; the
+    /// default framebuffer and the viewport are restored afterwards.
+    pub fn from_equirectangular(ctx: &GlContext, equirectangular: &Texture, settings: &IblSettings) -> Result<Self> {
+        log::debug!(
+            "Preprocessing environment... ({}x{}, {:?})",
+            equirectangular.width,
+            equirectangular.height,
+            settings
+        );
+
+        let saved_viewport = viewport(ctx);
+        let mut baker = Baker::new(ctx);
+        let result = Self::bake(ctx, &mut baker, equirectangular, settings);
+
+        Framebuffer::unbind(ctx);
+        set_viewport(ctx, saved_viewport);
+
+        let environment = result?;
+        log::info!("Preprocessed environment. ({} prefiltered levels)", environment.prefiltered.levels);
+        Ok(environment)
+    }
+
+    fn bake(ctx: &GlContext, baker: &mut Baker, equirectangular: &Texture, settings: &IblSettings) -> Result<Self> {
+        let environment = Cubemap::new(
+            ctx,
+            settings.environment_size,
+            TextureFormat::Rgb16F,
+            Cubemap::mip_levels(settings.environment_size),
+        );
+
+        let program = capture_program(ctx, EQUIRECT_FRAG_SRC)?;
+        program.bind(ctx);
+        ctx.bind_texture_unit(0, gl33::GL_TEXTURE_2D, equirectangular.id);
+        program.uniform_1i(ctx, "equirectangular_map", 0)?;
+        baker.render_cubemap(ctx, &program, &environment, 0)?;
+
+        // the prefilter samples lower mips where its samples are sparse
+        environment.bind(ctx);
+        environment.generate_mipmap(ctx);
+
+        let irradiance = Cubemap::new(ctx, settings.irradiance_size, TextureFormat::Rgb16F, 1);
+        let program = capture_program(ctx, IRRADIANCE_FRAG_SRC)?;
+        program.bind(ctx);
+        ctx.bind_texture_unit(0, gl33::GL_TEXTURE_CUBE_MAP, environment.id);
+        program.uniform_1i(ctx, "environment_map", 0)?;
+        program.uniform_1f(ctx, "sample_delta", settings.irradiance_sample_delta.max(1e-3))?;
+        baker.render_cubemap(ctx, &program, &irradiance, 0)?;
+
+        let prefiltered = Cubemap::new(ctx, settings.prefiltered_size, TextureFormat::Rgb16F, settings.prefiltered_levels);
+        let program = capture_program(ctx, &with_prelude(PREFILTER_FRAG_SRC, IBL_SAMPLING_SRC))?;
+        program.bind(ctx);
+        ctx.bind_texture_unit(0, gl33::GL_TEXTURE_CUBE_MAP, environment.id);
+        program.uniform_1i(ctx, "environment_map", 0)?;
+        program.uniform_1f(ctx, "resolution", environment.size as f32)?;
+        program.uniform_1ui(ctx, "sample_count", settings.sample_count.max(1))?;
+
+        for level in 0..prefiltered.levels {
+            let roughness = if prefiltered.levels > 1 { level as f32 / (prefiltered.levels - 1) as f32 } else { 0.0 };
+            program.uniform_1f(ctx, "roughness", roughness)?;
+            baker.render_cubemap(ctx, &program, &prefiltered, level)?;
+        }
+
+        let brdf_lut = render_brdf_lut(ctx, &mut baker.framebuffer, settings.brdf_lut_size, settings.sample_count)?;
+
+        Ok(Self {
+            environment: Rc::new(environment),
+            irradiance: Rc::new(irradiance),
+            prefiltered: Rc::new(prefiltered),
+            brdf_lut: Rc::new(brdf_lut),
+        })
+    }
+
+    /// the mip level of the prefiltered map for a roughness of 1.
+    pub fn max_reflection_lod(&self) -> f32 {
+        (self.prefiltered.levels - 1) as f32
+    }
+}
+
+/// compute the `Rg16F` brdf integration lut of the split sum
+/// approximation, which does not depend on the environment. the
+/// default framebuffer and the viewport are restored afterwards.
+pub fn brdf_lut(ctx: &GlContext, size: u32, sample_count: u32) -> Result<Texture> {
+    let saved_viewport = viewport(ctx);
+    let mut framebuffer = Framebuffer::new(ctx);
+    let result = render_brdf_lut(ctx, &mut framebuffer, size, sample_count);
+
+    Framebuffer::unbind(ctx);
+    set_viewport(ctx, saved_viewport);
+    result
+}
+
+fn render_brdf_lut(ctx: &GlContext, framebuffer: &mut Framebuffer, size: u32, sample_count: u32) -> Result<Texture> {
+    let size = size.max(1);
+    let lut = Texture::empty(ctx, size, size, TextureFormat::Rg16F, TextureFilter::Linear, TextureWrap::ClampToEdge);
+
+    let mut program = Program::new();
+    program.link(ctx, FULLSCREEN_VERT_SRC, &with_prelude(BRDF_LUT_FRAG_SRC, IBL_SAMPLING_SRC))?;
+    program.bind(ctx);
+    program.uniform_1ui(ctx, "sample_count", sample_count.max(1))?;
+
+    let quad = primitives::quad(2.0, 2.0).upload(ctx);
+
+    framebuffer.bind(ctx);
+    framebuffer.attach_texture(ctx, gl33::GL_COLOR_ATTACHMENT0, &lut, 0);
+    framebuffer.check(ctx)?;
+
+    ctx.apply_state(&bake_state());
+    set_viewport(ctx, [0, 0, size as i32, size as i32]);
+    unsafe {
+        ctx.Clear(gl33::GL_COLOR_BUFFER_BIT);
+    }
+    quad.draw(ctx);
+
+    Ok(lut)
+}
+
+fn capture_program(ctx: &GlContext, fragment: &str) -> Result<Program> {
+    let mut program = Program::new();
+    program.link(ctx, CAPTURE_VERT_SRC, fragment)?;
+    Ok(program)
+}
+
+/// no depth testing or culling, the capture cube is seen from inside.
+fn bake_state() -> RenderState {
+    RenderState {
+        depth: DepthState { test: false, write: false, func: CompareFunc::Always },
+        cull: CullMode::None,
+        ..RenderState::default()
+    }
+}
+
+/// renders a unit cube from its center into each face of a cube map.
+struct Baker {
+    cube: Mesh,
+    framebuffer: Framebuffer,
+    projection: Matrix4<f32>,
+    views: [Matrix4<f32>; 6],
+}
+
+impl Baker {
+    fn new(ctx: &GlContext) -> Self {
+        let eye = Point3::origin();
+        let view = |target: Vector3<f32>, up: Vector3<f32>| Matrix4::look_at_rh(&eye, &Point3::from(target), &up);
+
+        // the cube map face orientations, in the order of `Cubemap::face_target`
+        let views = [
+            view(Vector3::x(), -Vector3::y()),
+            view(-Vector3::x(), -Vector3::y()),
+            view(Vector3::y(), Vector3::z()),
+            view(-Vector3::y(), -Vector3::z()),
+            view(Vector3::z(), -Vector3::y()),
+            view(-Vector3::z(), -Vector3::y()),
+        ];
+
+        Self {
+            cube: primitives::cube(2.0).upload(ctx),
+            framebuffer: Framebuffer::new(ctx),
+            projection: Matrix4::new_perspective(1.0, 90f32.to_radians(), 0.1, 10.0),
+            views,
+        }
+    }
+
+    /// draw `program`, which must be bound with its inputs set,
+    /// into every face of mip `level` of `target`.
+    fn render_cubemap(&mut self, ctx: &GlContext, program: &Program, target: &Cubemap, level: u32) -> Result<()> {
+        let size = target.level_size(level) as i32;
+
+        self.framebuffer.bind(ctx);
+        ctx.apply_state(&bake_state());
+        set_viewport(ctx, [0, 0, size, size]);
+        program.uniform_mat4(ctx, "projection", &self.projection)?;
+
+        for (face, view) in self.views.iter().enumerate() {
+            self.framebuffer.attach_cubemap_face(ctx, gl33::GL_COLOR_ATTACHMENT0, target, face as u32, level);
+            self.framebuffer.check(ctx)?;
+
+            program.uniform_mat4(ctx, "view", view)?;
+            unsafe {
+                ctx.Clear(gl33::GL_COLOR_BUFFER_BIT);
+            }
+            self.cube.draw(ctx);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,428 @@
+use std::{collections::HashMap, f32::consts::{PI, TAU}};
+
+use nalgebra::Vector3;
+
+use super::mesh::MeshData;
+
+// all generators produce counter-clockwise triangles when seen from
+// outside, outward facing normals and uvs in [0, 1], centered on the
+// origin with +y as the up axis.
+
+/// a `width` by `height` rectangle in the xy plane, facing +z.
+pub fn quad(width: f32, height: f32) -> MeshData {
+    let (w, h) = (width * 0.5, height * 0.5);
+    let normal = [0.0, 0.0, 1.0];
+
+    let mut data = MeshData::default();
+    data.push_vertex([-w, -h, 0.0], normal, [0.0, 0.0]);
+    data.push_vertex([w, -h, 0.0], normal, [1.0, 0.0]);
+    data.push_vertex([w, h, 0.0], normal, [1.0, 1.0]);
+    data.push_vertex([-w, h, 0.0], normal, [0.0, 1.0]);
+
+    data.push_triangle(0, 1, 2);
+    data.push_triangle(0, 2, 3);
+
+    data.compute_tangents();
+    data
+}
+
+/// a `width` by `depth` grid in the xz plane, facing +y, split
+/// into `subdivisions_x` by `subdivisions_z` cells.
+pub fn plane(width: f32, depth: f32, subdivisions_x: u32, subdivisions_z: u32) -> MeshData {
+    let (sx, sz) = (subdivisions_x.max(1), subdivisions_z.max(1));
+    let normal = [0.0, 1.0, 0.0];
+
+    let mut data = MeshData::default();
+    for j in 0..=sz {
+        for i in 0..=sx {
+            let (u, v) = (i as f32 / sx as f32, j as f32 / sz as f32);
+            data.push_vertex([(u - 0.5) * width, 0.0, (v - 0.5) * depth], normal, [u, 1.0 - v]);
+        }
+    }
+
+    let row = sx + 1;
+    for j in 0..sz {
+        for i in 0..sx {
+            let a = j * row + i;
+            let (b, c, d) = (a + 1, a + row + 1, a + row);
+            data.push_triangle(a, d, c);
+            data.push_triangle(a, c, b);
+        }
+    }
+
+    data.compute_tangents();
+    data
+}
+
+/// an axis aligned cube with edge length `size`. every face has its
+/// own four vertices, so normals and uvs are not shared across edges.
+pub fn cube(size: f32) -> MeshData {
+    let h = size * 0.5;
+
+    // (normal, u axis, v axis) per face, with u x v = normal
+    let faces: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+        ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+        ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+        ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+        ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ];
+
+    let mut data = MeshData::default();
+    for (normal, u, v) in faces {
+        let (n, u, v) = (Vector3::from(normal), Vector3::from(u), Vector3::from(v));
+        let base = data.vertex_count() as u32;
+
+        for (su, sv) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            let p = (n + u * su + v * sv) * h;
+            data.push_vertex(p.into(), normal, [(su + 1.0) * 0.5, (sv + 1.0) * 0.5]);
+        }
+
+        data.push_triangle(base, base + 1, base + 2);
+        data.push_triangle(base, base + 2, base + 3);
+    }
+
+    data.compute_tangents();
+    data
+}
+
+/// connect consecutive rows of `segments + 1` vertices into quads,
+/// skipping the degenerate triangles touching a pole row.
+fn stitch_rows(data: &mut MeshData, rows: u32, segments: u32, top_pole: bool, bottom_pole: bool) {
+    let row = segments + 1;
+    for r in 0..rows - 1 {
+        for s in 0..segments {
+            let a = r * row + s;
+            let (b, c, d) = (a + 1, a + row + 1, a + row);
+
+            if !(bottom_pole && r == rows - 2) {
+                data.push_triangle(a, d, c);
+            }
+            if !(top_pole && r == 0) {
+                data.push_triangle(a, c, b);
+            }
+        }
+    }
+}
+
+/// a sphere made of `rings` latitude bands and `segments`
+/// longitude slices.
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> MeshData {
+    let (segments, rings) = (segments.max(3), rings.max(2));
+
+    let mut data = MeshData::default();
+    for r in 0..=rings {
+        let phi = PI * r as f32 / rings as f32;
+        for s in 0..=segments {
+            let theta = TAU * s as f32 / segments as f32;
+            let n = [phi.sin() * theta.sin(), phi.cos(), phi.sin() * theta.cos()];
+            let p = [n[0] * radius, n[1] * radius, n[2] * radius];
+            data.push_vertex(p, n, [s as f32 / segments as f32, 1.0 - r as f32 / rings as f32]);
+        }
+    }
+
+    stitch_rows(&mut data, rings + 1, segments, true, true);
+
+    data.compute_tangents();
+    data
+}
+
+/// a sphere made by repeatedly subdividing an icosahedron, which
+/// gives evenly sized triangles. the uvs are a spherical projection
+/// and are not split along the seam.
+pub fn icosphere(radius: f32, subdivisions: u32) -> MeshData {
+    let t = (1.0 + 5.0_f32.sqrt()) * 0.5;
+
+    let mut points: Vec<Vector3<f32>> = [
+        [-1.0, t, 0.0], [1.0, t, 0.0], [-1.0, -t, 0.0], [1.0, -t, 0.0],
+        [0.0, -1.0, t], [0.0, 1.0, t], [0.0, -1.0, -t], [0.0, 1.0, -t],
+        [t, 0.0, -1.0], [t, 0.0, 1.0], [-t, 0.0, -1.0], [-t, 0.0, 1.0],
+    ]
+    .iter()
+    .map(|&p| Vector3::from(p).normalize())
+    .collect();
+
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut midpoint = |a: u32, b: u32, points: &mut Vec<Vector3<f32>>| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                points.push(((points[a as usize] + points[b as usize]) * 0.5).normalize());
+                (points.len() - 1) as u32
+            })
+        };
+
+        triangles = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let ab = midpoint(a, b, &mut points);
+                let bc = midpoint(b, c, &mut points);
+                let ca = midpoint(c, a, &mut points);
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let mut data = MeshData::default();
+    for n in &points {
+        let u = 0.5 + n.x.atan2(n.z) / TAU;
+        let v = 0.5 + n.y.asin() / PI;
+        data.push_vertex((n * radius).into(), (*n).into(), [u, v]);
+    }
+
+    for [a, b, c] in triangles {
+        data.push_triangle(a, b, c);
+    }
+
+    data.compute_tangents();
+    data
+}
+
+/// a truncated cone along the y axis. a top radius of zero gives a
+/// cone, in which case the top cap is omitted.
+fn frustum(bottom_radius: f32, top_radius: f32, height: f32, segments: u32) -> MeshData {
+    let segments = segments.max(3);
+    let h = height * 0.5;
+
+    let mut data = MeshData::default();
+
+    // side, with a seam column so the uvs can wrap
+    for s in 0..=segments {
+        let theta = TAU * s as f32 / segments as f32;
+        let (sin, cos) = theta.sin_cos();
+        let u = s as f32 / segments as f32;
+
+        let n = Vector3::new(height * sin, bottom_radius - top_radius, height * cos).normalize();
+        data.push_vertex([bottom_radius * sin, -h, bottom_radius * cos], n.into(), [u, 0.0]);
+        data.push_vertex([top_radius * sin, h, top_radius * cos], n.into(), [u, 1.0]);
+    }
+
+    for s in 0..segments {
+        let (b0, t0) = (2 * s, 2 * s + 1);
+        let (b1, t1) = (b0 + 2, t0 + 2);
+        data.push_triangle(b0, b1, t1);
+        if top_radius > 0.0 {
+            data.push_triangle(b0, t1, t0);
+        }
+    }
+
+    // caps
+    let mut cap = |radius: f32, y: f32, up: bool| {
+        let normal = [0.0, if up { 1.0 } else { -1.0 }, 0.0];
+        let center = data.push_vertex([0.0, y, 0.0], normal, [0.5, 0.5]);
+
+        for s in 0..=segments {
+            let (sin, cos) = (TAU * s as f32 / segments as f32).sin_cos();
+            let v = if up { 0.5 - 0.5 * cos } else { 0.5 + 0.5 * cos };
+            data.push_vertex([radius * sin, y, radius * cos], normal, [0.5 + 0.5 * sin, v]);
+        }
+
+        for s in 0..segments {
+            let (a, b) = (center + 1 + s, center + 2 + s);
+            if up {
+                data.push_triangle(center, a, b);
+            } else {
+                data.push_triangle(center, b, a);
+            }
+        }
+    };
+
+    cap(bottom_radius, -h, false);
+    if top_radius > 0.0 {
+        cap(top_radius, h, true);
+    }
+
+    data.compute_tangents();
+    data
+}
+
+/// a closed cylinder along the y axis.
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> MeshData {
+    frustum(radius, radius, height, segments)
+}
+
+/// a cone along the y axis with its tip pointing up.
+pub fn cone(radius: f32, height: f32, segments: u32) -> MeshData {
+    frustum(radius, 0.0, height, segments)
+}
+
+/// a cylinder of length `height` capped with hemispheres, each
+/// made of `rings` latitude bands. the total height is
+/// `height + 2 * radius`.
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> MeshData {
+    let (segments, rings) = (segments.max(3), rings.max(1));
+    let (h, total) = (height * 0.5, height + 2.0 * radius);
+
+    let mut data = MeshData::default();
+
+    // the top hemisphere rows end at the upper equator and the bottom
+    // ones start at the lower equator, so stitching every consecutive
+    // pair of rows also produces the cylindrical body
+    for (offset, start) in [(h, 0.0), (-h, PI * 0.5)] {
+        for r in 0..=rings {
+            let phi = start + PI * 0.5 * r as f32 / rings as f32;
+            for s in 0..=segments {
+                let theta = TAU * s as f32 / segments as f32;
+                let n = [phi.sin() * theta.sin(), phi.cos(), phi.sin() * theta.cos()];
+                let y = n[1] * radius + offset;
+                let uv = [s as f32 / segments as f32, (y + total * 0.5) / total];
+                data.push_vertex([n[0] * radius, y, n[2] * radius], n, uv);
+            }
+        }
+    }
+
+    stitch_rows(&mut data, 2 * (rings + 1), segments, true, true);
+
+    data.compute_tangents();
+    data
+}
+
+/// a torus around the y axis. `major_radius` is the distance from
+/// the center to the middle of the tube, `minor_radius` the tube radius.
+pub fn torus(major_radius: f32, minor_radius: f32, major_segments: u32, minor_segments: u32) -> MeshData {
+    let (major, minor) = (major_segments.max(3), minor_segments.max(3));
+
+    let mut data = MeshData::default();
+    for i in 0..=major {
+        let theta = TAU * i as f32 / major as f32;
+        let center = Vector3::new(theta.sin(), 0.0, theta.cos()) * major_radius;
+
+        for j in 0..=minor {
+            let phi = TAU * j as f32 / minor as f32;
+            let n = Vector3::new(phi.cos() * theta.sin(), phi.sin(), phi.cos() * theta.cos());
+            let uv = [i as f32 / major as f32, j as f32 / minor as f32];
+            data.push_vertex((center + n * minor_radius).into(), n.into(), uv);
+        }
+    }
+
+    let row = minor + 1;
+    for i in 0..major {
+        for j in 0..minor {
+            let a = i * row + j;
+            let (b, c, d) = (a + row, a + row + 1, a + 1);
+            data.push_triangle(a, b, c);
+            data.push_triangle(a, c, d);
+        }
+    }
+
+    data.compute_tangents();
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+
+    use super::*;
+
+    /// assert every triangle winds counter-clockwise seen from outside,
+    /// i.e. its face normal points away from `center(centroid)` and
+    /// agrees with the normals stored at its vertices.
+    fn assert_outward(data: &MeshData, center: impl Fn(Vector3<f32>) -> Vector3<f32>) {
+        assert_eq!(data.normals.len(), data.positions.len());
+
+        for triangle in data.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vector3::from(data.positions[triangle[i] as usize]));
+            let face = (b - a).cross(&(c - a));
+            if face.norm() < 1e-6 {
+                continue;
+            }
+
+            let centroid = (a + b + c) / 3.0;
+            assert!(face.dot(&(centroid - center(centroid))) > 0.0, "triangle {:?} faces inward", triangle);
+
+            for &i in triangle {
+                let normal = Vector3::from(data.normals[i as usize]);
+                assert!(face.dot(&normal) > 0.0, "triangle {:?} disagrees with the normal of {}", triangle, i);
+            }
+        }
+    }
+
+    fn origin(_: Vector3<f32>) -> Vector3<f32> {
+        Vector3::zeros()
+    }
+
+    fn assert_counts(data: &MeshData, vertices: usize, indices: usize) {
+        assert_eq!(data.positions.len(), vertices);
+        assert_eq!(data.indices.len(), indices);
+        assert!(data.indices.iter().all(|&i| (i as usize) < vertices));
+    }
+
+    #[test]
+    fn quad() {
+        let data = super::quad(2.0, 1.0);
+        assert_counts(&data, 4, 6);
+        assert_outward(&data, |_| -Vector3::z());
+    }
+
+    #[test]
+    fn plane() {
+        let data = super::plane(4.0, 2.0, 3, 2);
+        assert_counts(&data, 4 * 3, 6 * 3 * 2);
+        assert_outward(&data, |_| -Vector3::y());
+    }
+
+    #[test]
+    fn cube() {
+        let data = super::cube(1.0);
+        assert_counts(&data, 24, 36);
+        assert_outward(&data, origin);
+    }
+
+    #[test]
+    fn uv_sphere() {
+        let (segments, rings) = (16, 8);
+        let data = super::uv_sphere(1.0, segments, rings);
+        assert_counts(&data, 9 * 17, 6 * 16 * 7);
+        assert_outward(&data, origin);
+    }
+
+    #[test]
+    fn icosphere() {
+        for (subdivisions, vertices, triangles) in [(0, 12, 20), (1, 42, 80), (2, 162, 320)] {
+            let data = super::icosphere(1.0, subdivisions);
+            assert_counts(&data, vertices, 3 * triangles);
+            assert_outward(&data, origin);
+        }
+    }
+
+    #[test]
+    fn cylinder() {
+        let data = super::cylinder(0.5, 2.0, 12);
+        // side columns, then a center and a ring per cap
+        assert_counts(&data, 2 * 13 + 2 * (1 + 13), 3 * (2 * 12 + 2 * 12));
+        assert_outward(&data, origin);
+    }
+
+    #[test]
+    fn cone() {
+        let data = super::cone(0.5, 2.0, 12);
+        assert_counts(&data, 2 * 13 + (1 + 13), 3 * (12 + 12));
+        assert_outward(&data, origin);
+    }
+
+    #[test]
+    fn capsule() {
+        let (segments, rings) = (12, 4);
+        let data = super::capsule(0.5, 1.0, segments, rings);
+        assert_counts(&data, 2 * 5 * 13, 3 * 4 * 12 * 4);
+        assert_outward(&data, origin);
+    }
+
+    #[test]
+    fn torus() {
+        let major_radius = 1.0;
+        let data = super::torus(major_radius, 0.25, 16, 8);
+        assert_counts(&data, 17 * 9, 6 * 16 * 8);
+
+        // outward from the middle of the tube
+        assert_outward(&data, |p| Vector3::new(p.x, 0.0, p.z).normalize() * major_radius);
+    }
+}
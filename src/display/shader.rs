@@ -1,7 +1,14 @@
-use std::{ffi::CString, marker::PhantomData};
+use std::{collections::HashMap, ffi::CString, marker::PhantomData};
+
+use nalgebra::Matrix4;
 
 use crate::{Error, Result};
 
+use super::{
+    context::GlContext,
+    shadow::SHADOW_MAP_UNIT,
+};
+
 pub struct Shader<T> {
     pub id: u32,
 
@@ -12,6 +19,77 @@ pub struct Shader<T> {
 struct VertexShader;
 struct FragmentShader;
 
+/// the type of an active uniform, as reported by opengl.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UniformType {
+    Float,
+    Vec2,
+    Vec3,
+    Vec4,
+    Int,
+    UInt,
+    Bool,
+    Mat3,
+    Mat4,
+    Sampler2D,
+    Sampler2DShadow,
+    Sampler2DArray,
+    SamplerCube,
+
+    /// any other type, with its raw gl enum
+    Other(u32),
+}
+
+impl UniformType {
+    fn from_gl(kind: gl33::GLenum) -> Self {
+        match kind {
+            gl33::GL_FLOAT => UniformType::Float,
+            gl33::GL_FLOAT_VEC2 => UniformType::Vec2,
+            gl33::GL_FLOAT_VEC3 => UniformType::Vec3,
+            gl33::GL_FLOAT_VEC4 => UniformType::Vec4,
+            gl33::GL_INT => UniformType::Int,
+            gl33::GL_UNSIGNED_INT => UniformType::UInt,
+            gl33::GL_BOOL => UniformType::Bool,
+            gl33::GL_FLOAT_MAT3 => UniformType::Mat3,
+            gl33::GL_FLOAT_MAT4 => UniformType::Mat4,
+            gl33::GL_SAMPLER_2D => UniformType::Sampler2D,
+            gl33::GL_SAMPLER_2D_SHADOW => UniformType::Sampler2DShadow,
+            gl33::GL_SAMPLER_2D_ARRAY => UniformType::Sampler2DArray,
+            gl33::GL_SAMPLER_CUBE => UniformType::SamplerCube,
+            other => UniformType::Other(other.0),
+        }
+    }
+
+    /// the texture target a sampler of this type reads, if the
+    /// engine can assign textures to it.
+    pub fn texture_target(&self) -> Option<gl33::GLenum> {
+        match self {
+            UniformType::Sampler2D => Some(gl33::GL_TEXTURE_2D),
+            UniformType::SamplerCube => Some(gl33::GL_TEXTURE_CUBE_MAP),
+            _ => None,
+        }
+    }
+
+    pub fn is_sampler(&self) -> bool {
+        matches!(
+            self,
+            UniformType::Sampler2D | UniformType::Sampler2DShadow | UniformType::Sampler2DArray | UniformType::SamplerCube
+        )
+    }
+}
+
+/// an active uniform of a linked program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniformInfo {
+    /// the name without a trailing `[0]` for arrays
+    pub name: String,
+    pub kind: UniformType,
+
+    /// the number of array elements, 1 for non-arrays
+    pub size: i32,
+    pub location: i32,
+}
+
 pub struct Program {
     /// the internal open-gl program id
     pub id: u32,
@@ -21,6 +99,20 @@ pub struct Program {
 
     /// the fragment shader abstraction
     fragment: Shader<FragmentShader>,
+
+    /// the active uniforms outside of uniform blocks,
+    /// reflected after linking
+    uniforms: HashMap<String, UniformInfo>,
+
+    /// the fixed texture unit of every 2d and cube map sampler,
+    /// assigned after linking
+    sampler_units: HashMap<String, u32>,
+}
+
+impl Default for Program {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Program {
@@ -60,12 +152,14 @@ impl Program {
             id: 0,
             vertex: Shader::<VertexShader>::new(),
             fragment: Shader::<FragmentShader>::new(),
+            uniforms: HashMap::new(),
+            sampler_units: HashMap::new(),
         }
     }
 
     pub fn link(
         &mut self,
-        gl: &gl33::GlFns,
+        gl: &GlContext,
         vertex_source: &str,
         fragment_source: &str,
     ) -> Result<()> {
@@ -104,8 +198,111 @@ impl Program {
         gl.DeleteShader(self.vertex.id);
         gl.DeleteShader(self.fragment.id);
 
-        log::debug!("Linked shader program successfully.");
+        self.uniforms = self.reflect_uniforms(gl);
+        self.sampler_units = assign_sampler_units(&self.uniforms, gl.texture_units());
+
+        log::debug!("Linked shader program successfully. ({} active uniforms)", self.uniforms.len());
+
+        Ok(())
+    }
+
+    pub fn bind(&self, ctx: &GlContext) {
+        ctx.use_program(self.id);
+    }
+
+    /// name the program in debug messages and graphics debuggers.
+    pub fn set_label(&self, ctx: &GlContext, label: &str) {
+        ctx.label(gl33::GL_PROGRAM, self.id, label);
+    }
+
+    fn reflect_uniforms(&self, gl: &gl33::GlFns) -> HashMap<String, UniformInfo> {
+        let mut count = 0;
+        let mut max_len = 0;
+        unsafe {
+            gl.GetProgramiv(self.id, gl33::GL_ACTIVE_UNIFORMS, &mut count);
+            gl.GetProgramiv(self.id, gl33::GL_ACTIVE_UNIFORM_MAX_LENGTH, &mut max_len);
+        }
+
+        let mut uniforms = HashMap::new();
+        let mut buffer: Vec<u8> = vec![0; max_len.max(1) as usize];
+
+        for index in 0..count.max(0) as u32 {
+            let mut len = 0;
+            let mut size = 0;
+            let mut kind = gl33::GLenum(0);
+
+            unsafe {
+                gl.GetActiveUniform(
+                    self.id,
+                    index,
+                    buffer.len() as i32,
+                    &mut len,
+                    &mut size,
+                    &mut kind,
+                    buffer.as_mut_ptr().cast(),
+                );
+            }
+
+            let name = String::from_utf8_lossy(&buffer[..len as usize]).to_string();
+            let name = name.strip_suffix("[0]").map(str::to_string).unwrap_or(name);
+
+            // members of uniform blocks have no location
+            let location = {
+                let c_name = CString::new(name.as_str()).unwrap();
+                unsafe { gl.GetUniformLocation(self.id, c_name.as_ptr().cast()) }
+            };
+            if location == -1 {
+                continue;
+            }
+
+            let kind = UniformType::from_gl(kind);
+            log::debug!("Active uniform \"{}\": {:?}[{}] at {}", name, kind, size, location);
+            uniforms.insert(name.clone(), UniformInfo { name, kind, size, location });
+        }
+
+        uniforms
+    }
+
+    /// the texture unit the sampler `name` is assigned to.
+    pub fn sampler_unit(&self, name: &str) -> Option<u32> {
+        self.sampler_units.get(name).copied()
+    }
+
+    /// the samplers with an assigned texture unit.
+    pub fn samplers(&self) -> impl Iterator<Item = (&UniformInfo, u32)> {
+        self.sampler_units.iter().filter_map(|(name, unit)| Some((self.uniforms.get(name)?, *unit)))
+    }
+
+    /// the reflected active uniform called `name`.
+    pub fn uniform_info(&self, name: &str) -> Option<&UniformInfo> {
+        self.uniforms.get(name)
+    }
+
+    pub fn uniforms(&self) -> impl Iterator<Item = &UniformInfo> {
+        self.uniforms.values()
+    }
 
+    /// make the uniform block `name` read from the buffer
+    /// bound to the indexed uniform buffer `binding`.
+    pub fn uniform_block_binding(
+        &self,
+        gl: &gl33::GlFns,
+        name: &str,
+        binding: u32,
+    ) -> Result<()> {
+        let c_name = CString::new(name).unwrap();
+        let index = unsafe {
+            gl.GetUniformBlockIndex(self.id, c_name.as_ptr().cast())
+        };
+
+        if index == gl33::GL_INVALID_INDEX {
+            log::error!("Failed to get uniform block index for \"{}\"", name);
+            return Err(Error::GlUniformBlock(format!("\"{}\"", name)));
+        }
+
+        unsafe {
+            gl.UniformBlockBinding(self.id, index, binding);
+        }
         Ok(())
     }
 
@@ -144,6 +341,21 @@ impl Program {
         Ok(())
     }
 
+    pub fn uniform_mat4(
+        &self,
+        gl: &gl33::GlFns,
+        name: &str,
+        data: &Matrix4<f32>,
+    ) -> Result<()> {
+        let location = self.uniform_location(gl, name)?;
+
+        unsafe {
+            gl.UniformMatrix4fv(location, 1, 0, data.as_ptr());
+        }
+
+        Ok(())
+    }
+
     pub fn uniform_1f(
         &self,
         gl: &gl33::GlFns,
@@ -205,6 +417,12 @@ impl Program {
     }
 }
 
+impl<T> Default for Shader<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> Shader<T> {
     pub fn new() -> Self {
         Self {
@@ -315,3 +533,74 @@ impl Shader<FragmentShader> {
         Ok(())
     }
 }
+
+/// give every sampler a texture can be assigned to its own unit below
+/// `units`, in the order of their locations. `SHADOW_MAP_UNIT` is left out.
+fn assign_sampler_units(uniforms: &HashMap<String, UniformInfo>, units: u32) -> HashMap<String, u32> {
+    let mut samplers: Vec<&UniformInfo> = uniforms
+        .values()
+        .filter(|uniform| uniform.kind.texture_target().is_some() && uniform.size == 1)
+        .collect();
+    samplers.sort_by_key(|uniform| uniform.location);
+
+    let available = (0..units).filter(|unit| *unit != SHADOW_MAP_UNIT).count();
+    let mut units = (0..units).filter(|unit| *unit != SHADOW_MAP_UNIT);
+    let mut sampler_units = HashMap::new();
+    for sampler in samplers {
+        let Some(unit) = units.next() else {
+            log::warn!("Sampler \"{}\" gets no texture unit, all {} are taken.", sampler.name, available);
+            continue;
+        };
+        sampler_units.insert(sampler.name.clone(), unit);
+    }
+    sampler_units
+}
+
+/// insert `#define` lines into glsl `source`, right after
+/// its `#version` directive.
+pub fn with_defines(source: &str, defines: &[(&str, String)]) -> String {
+    let defines: String = defines.iter().map(|(name, value)| format!("#define {} {}\n", name, value)).collect();
+    with_prelude(source, &defines)
+}
+
+/// insert glsl code, e.g. shared functions, into `source`
+/// right after its `#version` directive.
+pub fn with_prelude(source: &str, prelude: &str) -> String {
+    match source.split_once('\n') {
+        Some((version, rest)) if version.trim_start().starts_with("#version") => {
+            format!("{}\n{}{}", version, prelude, rest)
+        },
+        _ => format!("{}{}", prelude, source),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uniforms(kinds: &[(&str, UniformType)]) -> HashMap<String, UniformInfo> {
+        kinds
+            .iter()
+            .enumerate()
+            .map(|(location, (name, kind))| {
+                let info = UniformInfo { name: name.to_string(), kind: *kind, size: 1, location: location as i32 };
+                (name.to_string(), info)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn assigns_samplers_below_the_unit_limit() {
+        let names: Vec<String> = (0..20).map(|i| format!("sampler{:02}", i)).collect();
+        let mut kinds: Vec<(&str, UniformType)> = names.iter().map(|name| (name.as_str(), UniformType::Sampler2D)).collect();
+        kinds.insert(3, ("tint", UniformType::Vec3));
+
+        let units = assign_sampler_units(&uniforms(&kinds), 16);
+        assert_eq!(units.len(), 15);
+        assert!(!units.contains_key("tint"));
+        assert!(units.values().all(|&unit| unit < 16 && unit != SHADOW_MAP_UNIT));
+        assert_eq!(units["sampler00"], 0);
+        assert_eq!(units["sampler14"], 14);
+        assert!(!units.contains_key("sampler15"));
+    }
+}
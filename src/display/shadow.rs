@@ -0,0 +1,544 @@
+use std::rc::Rc;
+
+use bytemuck::{Pod, Zeroable};
+use nalgebra::{Matrix4, Orthographic3, Perspective3, Point3, Vector3, Vector4};
+use serde::{Deserialize, Serialize};
+
+use crate::Result;
+
+use super::{
+    camera::Camera,
+    context::GlContext,
+    framebuffer::{set_viewport, viewport, Framebuffer},
+    light::{LightKind, PlacedLight},
+    mesh::Mesh,
+    primitives,
+    shader::Program,
+    state::{CullMode, DepthState, RenderState},
+    texture::{set_sampling, TextureFilter, TextureWrap},
+    vertex::{Buffer, BufferUsage},
+};
+
+const DEPTH_VERT_SRC: &str = include_str!("../../res/shaders/shadow_depth_vertex.glsl");
+const DEPTH_FRAG_SRC: &str = include_str!("../../res/shaders/shadow_depth_fragment.glsl");
+const DEBUG_VERT_SRC: &str = include_str!("../../res/shaders/shadow_debug_vertex.glsl");
+const DEBUG_FRAG_SRC: &str = include_str!("../../res/shaders/shadow_debug_fragment.glsl");
+
+/// the uniform buffer binding point the `Shadows` block reads from.
+pub const SHADOWS_BINDING: u32 = 1;
+
+/// the texture unit lit shaders sample the shadow maps from. materials
+/// bind their textures from unit 0 and must stay below it.
+pub const SHADOW_MAP_UNIT: u32 = 15;
+
+/// the most cascades the directional light can be split into.
+pub const MAX_CASCADES: usize = 4;
+
+/// the most shadow map layers, cascades and spot lights together.
+pub const MAX_SHADOW_MAPS: usize = 8;
+
+/// where the cascades of the directional light end.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CascadeSplits {
+    /// the practical split scheme, blending logarithmic (1.0)
+    /// and uniform (0.0) split distances
+    Practical(f32),
+
+    /// the far end of each cascade as a fraction of the shadow
+    /// distance, in increasing order
+    Manual(Vec<f32>),
+}
+
+/// how shadows are rendered, used as a resource by the lighting system.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShadowSettings {
+    /// the width and height of every shadow map
+    pub resolution: u32,
+
+    /// the number of cascades of the directional light, at most `MAX_CASCADES`
+    pub cascades: usize,
+
+    pub splits: CascadeSplits,
+
+    /// the view distance up to which the directional light casts shadows
+    pub max_distance: f32,
+
+    /// fit the cascades to bounding spheres snapped to shadow map
+    /// texels, so that shadow edges do not shimmer as the camera
+    /// moves, at the cost of resolution
+    pub stable: bool,
+
+    /// the most spot lights casting shadows at once
+    pub max_spot_shadows: usize,
+
+    /// subtracted from the depth of a fragment before it is compared
+    pub depth_bias: f32,
+
+    /// the slope-scaled polygon offset applied while rendering depth
+    pub slope_bias: f32,
+
+    /// how far fragments are moved along their normal, in
+    /// world units, before they are looked up
+    pub normal_bias: f32,
+
+    /// the radius of the percentage closer filtering kernel in texels,
+    /// 0 for a single tap
+    pub pcf_radius: u32,
+
+    /// draw the shadow maps in the lower left corner of the window
+    pub debug_view: bool,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 2048,
+            cascades: 4,
+            splits: CascadeSplits::Practical(0.75),
+            max_distance: 50.0,
+            stable: true,
+            max_spot_shadows: 4,
+            depth_bias: 0.0005,
+            slope_bias: 2.0,
+            normal_bias: 0.02,
+            pcf_radius: 1,
+            debug_view: false,
+        }
+    }
+}
+
+impl ShadowSettings {
+    pub fn cascade_count(&self) -> usize {
+        self.cascades.clamp(1, MAX_CASCADES)
+    }
+
+    /// the number of shadow map layers the settings need.
+    pub fn layers(&self) -> usize {
+        (self.cascade_count() + self.max_spot_shadows).min(MAX_SHADOW_MAPS)
+    }
+
+    /// the view depth at which each cascade ends, for a camera
+    /// with the given clip planes.
+    pub fn cascade_ends(&self, near: f32, far: f32) -> Vec<f32> {
+        let far = far.min(self.max_distance).max(near);
+        let count = self.cascade_count();
+
+        (1..=count)
+            .map(|i| {
+                let end = match &self.splits {
+                    CascadeSplits::Practical(lambda) => {
+                        let p = i as f32 / count as f32;
+                        let log = near * (far / near).powf(p);
+                        let uniform = near + (far - near) * p;
+                        lambda * log + (1.0 - lambda) * uniform
+                    },
+                    CascadeSplits::Manual(fractions) => {
+                        let fraction = fractions.get(i - 1).copied().unwrap_or(1.0);
+                        near + (far - near) * fraction.clamp(0.0, 1.0)
+                    },
+                };
+                if i == count { far } else { end }
+            })
+            .collect()
+    }
+}
+
+/// the depth texture array holding every shadow map layer.
+pub struct ShadowMaps {
+    pub id: u32,
+    pub resolution: u32,
+    pub layers: u32,
+}
+
+impl ShadowMaps {
+    pub fn new(ctx: &GlContext, resolution: u32, layers: u32) -> Self {
+        let mut id = 0;
+        unsafe {
+            ctx.GenTextures(1, &mut id);
+        }
+
+        let maps = Self { id, resolution, layers };
+        maps.bind(ctx);
+
+        unsafe {
+            ctx.TexImage3D(
+                gl33::GL_TEXTURE_2D_ARRAY,
+                0,
+                gl33::GL_DEPTH_COMPONENT32F.0 as i32,
+                resolution as i32,
+                resolution as i32,
+                layers as i32,
+                0,
+                gl33::GL_DEPTH_COMPONENT,
+                gl33::GL_FLOAT,
+                std::ptr::null(),
+            );
+        }
+
+        // linear filtering makes the comparison itself a 2x2 pcf
+        set_sampling(ctx, gl33::GL_TEXTURE_2D_ARRAY, TextureFilter::Linear, TextureWrap::ClampToEdge);
+        maps.set_compare(ctx, true);
+
+        maps
+    }
+
+    pub fn bind(&self, ctx: &GlContext) {
+        ctx.bind_texture(gl33::GL_TEXTURE_2D_ARRAY, self.id);
+    }
+
+    /// name the maps in debug messages and graphics debuggers.
+    pub fn set_label(&self, ctx: &GlContext, label: &str) {
+        ctx.label(gl33::GL_TEXTURE, self.id, label);
+    }
+
+    /// whether the bound maps are sampled as depth comparisons,
+    /// as by `sampler2DArrayShadow`, or as plain depth values.
+    fn set_compare(&self, ctx: &GlContext, compare: bool) {
+        let mode = if compare { gl33::GL_COMPARE_REF_TO_TEXTURE } else { gl33::GL_NONE };
+        unsafe {
+            ctx.TexParameteri(gl33::GL_TEXTURE_2D_ARRAY, gl33::GL_TEXTURE_COMPARE_MODE, mode.0 as i32);
+            ctx.TexParameteri(gl33::GL_TEXTURE_2D_ARRAY, gl33::GL_TEXTURE_COMPARE_FUNC, gl33::GL_LEQUAL.0 as i32);
+        }
+    }
+}
+
+/// the std140 layout of the `Shadows` block.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct GpuShadows {
+    matrices: [[f32; 16]; MAX_SHADOW_MAPS],
+    cascade_ends: [f32; 4],
+    camera_view: [f32; 16],
+
+    /// depth bias, normal bias, pcf radius
+    params: [f32; 4],
+}
+
+// SAFETY: `repr(C)` and made up of 4 byte floats only,
+// so there is no padding and every bit pattern is valid.
+unsafe impl Zeroable for GpuShadows {}
+unsafe impl Pod for GpuShadows {}
+
+/// renders the shadow maps of the shadow casting lights and
+/// provides them, with the `Shadows` block, to the lit shaders.
+///
+/// the first shadow casting directional light gets cascades
+/// following the camera; spot lights get a single layer each.
+pub struct ShadowRenderer {
+    depth_program: Program,
+    debug_program: Program,
+    framebuffer: Framebuffer,
+    buffer: Buffer<u8>,
+    quad: Mesh,
+    maps: Option<ShadowMaps>,
+
+    /// the number of layers rendered in the last frame
+    used_layers: u32,
+}
+
+impl ShadowRenderer {
+    pub fn new(ctx: &GlContext) -> Result<Self> {
+        let mut depth_program = Program::new();
+        depth_program.link(ctx, DEPTH_VERT_SRC, DEPTH_FRAG_SRC)?;
+
+        let mut debug_program = Program::new();
+        debug_program.link(ctx, DEBUG_VERT_SRC, DEBUG_FRAG_SRC)?;
+
+        let mut buffer = Buffer::new_uniform(ctx);
+        buffer.bind(ctx);
+        buffer.allocate(ctx, size_of::<GpuShadows>(), BufferUsage::Dynamic);
+
+        depth_program.set_label(ctx, "shadow depth");
+        debug_program.set_label(ctx, "shadow debug");
+        buffer.set_label(ctx, "shadows");
+        let quad = primitives::quad(2.0, 2.0).upload(ctx);
+        quad.set_label(ctx, "shadow debug quad");
+
+        Ok(Self {
+            depth_program,
+            debug_program,
+            framebuffer: Framebuffer::new(ctx),
+            buffer,
+            quad,
+            maps: None,
+            used_layers: 0,
+        })
+    }
+
+    pub fn maps(&self) -> Option<&ShadowMaps> {
+        self.maps.as_ref()
+    }
+
+    /// assign shadow map layers to the shadow casting `lights`, render
+    /// the `casters` into them and upload the `Shadows` block. cascades
+    /// need a `camera`. the default framebuffer and the viewport are
+    /// restored afterwards.
+    pub fn render(
+        &mut self,
+        ctx: &GlContext,
+        settings: &ShadowSettings,
+        camera: Option<&Camera>,
+        lights: &mut [PlacedLight],
+        casters: &[(Rc<Mesh>, Matrix4<f32>)],
+    ) -> Result<()> {
+        let resolution = settings.resolution.max(1);
+        let layers = settings.layers() as u32;
+        if self.maps.as_ref().is_none_or(|maps| maps.resolution != resolution || maps.layers != layers) {
+            log::debug!("Allocating shadow maps... ({}x{}, {} layers)", resolution, resolution, layers);
+            if let Some(maps) = self.maps.take() {
+                ctx.forget_texture(maps.id);
+                unsafe {
+                    ctx.DeleteTextures(1, &maps.id);
+                }
+            }
+            let maps = ShadowMaps::new(ctx, resolution, layers);
+            maps.set_label(ctx, "shadow maps");
+            self.maps = Some(maps);
+        }
+
+        let (matrices, cascade_ends) = assign_layers(settings, camera, lights);
+        self.used_layers = matrices.len() as u32;
+
+        if !matrices.is_empty() {
+            let saved_viewport = viewport(ctx);
+            let result = self.render_layers(ctx, settings, &matrices, casters);
+
+            Framebuffer::unbind(ctx);
+            set_viewport(ctx, saved_viewport);
+            unsafe {
+                ctx.Disable(gl33::GL_POLYGON_OFFSET_FILL);
+            }
+            result?;
+        }
+
+        let mut data = GpuShadows::zeroed();
+        for (target, matrix) in data.matrices.iter_mut().zip(&matrices) {
+            target.copy_from_slice(matrix.as_slice());
+        }
+        for (target, end) in data.cascade_ends.iter_mut().zip(&cascade_ends) {
+            *target = *end;
+        }
+        if let Some(camera) = camera {
+            data.camera_view.copy_from_slice(camera.view_matrix().as_slice());
+        }
+        data.params = [settings.depth_bias, settings.normal_bias, settings.pcf_radius as f32, 0.0];
+
+        self.buffer.bind(ctx);
+        self.buffer.sub_data(ctx, 0, bytemuck::bytes_of(&data));
+        self.bind(ctx);
+
+        Ok(())
+    }
+
+    fn render_layers(
+        &mut self,
+        ctx: &GlContext,
+        settings: &ShadowSettings,
+        matrices: &[Matrix4<f32>],
+        casters: &[(Rc<Mesh>, Matrix4<f32>)],
+    ) -> Result<()> {
+        let Some(maps) = &self.maps else {
+            return Ok(());
+        };
+
+        self.framebuffer.bind(ctx);
+        self.framebuffer.disable_color(ctx);
+
+        // casters are rendered double sided, so that open meshes and
+        // planes seen from behind still cast shadows
+        ctx.apply_state(&RenderState { depth: DepthState::default(), cull: CullMode::None, ..RenderState::default() });
+        set_viewport(ctx, [0, 0, maps.resolution as i32, maps.resolution as i32]);
+        unsafe {
+            ctx.Enable(gl33::GL_POLYGON_OFFSET_FILL);
+            ctx.PolygonOffset(settings.slope_bias, 1.0);
+        }
+
+        self.depth_program.bind(ctx);
+        for (layer, matrix) in matrices.iter().enumerate() {
+            self.framebuffer.attach_texture_layer(ctx, gl33::GL_DEPTH_ATTACHMENT, maps.id, 0, layer as u32);
+            self.framebuffer.check(ctx)?;
+
+            unsafe {
+                ctx.Clear(gl33::GL_DEPTH_BUFFER_BIT);
+            }
+
+            self.depth_program.uniform_mat4(ctx, "light_space", matrix)?;
+            for (mesh, model) in casters {
+                self.depth_program.uniform_mat4(ctx, "model", model)?;
+                mesh.draw(ctx);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// bind the shadow maps to `SHADOW_MAP_UNIT` and the `Shadows`
+    /// block to `SHADOWS_BINDING`.
+    pub fn bind(&self, ctx: &GlContext) {
+        if let Some(maps) = &self.maps {
+            ctx.bind_texture_unit(SHADOW_MAP_UNIT, gl33::GL_TEXTURE_2D_ARRAY, maps.id);
+        }
+        self.buffer.bind_base(ctx, SHADOWS_BINDING);
+    }
+
+    /// draw the layers rendered last frame side by side along
+    /// the bottom of the viewport, near as black and far as white.
+    pub fn draw_debug(&self, ctx: &GlContext) -> Result<()> {
+        let Some(maps) = &self.maps else {
+            return Ok(());
+        };
+
+        let [_, _, width, height] = viewport(ctx);
+        let size = (height as f32 / 5.0).min(width as f32 / self.used_layers.max(1) as f32);
+        let (w, h) = (2.0 * size / width.max(1) as f32, 2.0 * size / height.max(1) as f32);
+
+        ctx.apply_state(&RenderState::overlay());
+        self.debug_program.bind(ctx);
+        self.debug_program.uniform_1i(ctx, "shadow_map", 0)?;
+
+        ctx.bind_texture_unit(0, gl33::GL_TEXTURE_2D_ARRAY, maps.id);
+        maps.set_compare(ctx, false);
+
+        for layer in 0..self.used_layers {
+            self.debug_program.uniform_1f(ctx, "layer", layer as f32)?;
+
+            let location = self.debug_program.uniform_location(ctx, "rect")?;
+            unsafe {
+                ctx.Uniform4f(location, -1.0 + layer as f32 * w, -1.0, w, h);
+            }
+            self.quad.draw(ctx);
+        }
+
+        maps.set_compare(ctx, true);
+        Ok(())
+    }
+}
+
+/// give the shadow casting lights their layers and compute the
+/// light space matrix of every layer, followed by the view depth
+/// at which each cascade ends.
+fn assign_layers(
+    settings: &ShadowSettings,
+    camera: Option<&Camera>,
+    lights: &mut [PlacedLight],
+) -> (Vec<Matrix4<f32>>, Vec<f32>) {
+    let mut matrices = Vec::new();
+    let mut cascade_ends = Vec::new();
+    let mut spot_shadows = 0;
+
+    for placed in lights.iter_mut() {
+        placed.shadow_layers = None;
+        if !placed.light.cast_shadows {
+            continue;
+        }
+
+        match placed.light.kind {
+            LightKind::Directional => {
+                let Some(camera) = camera else { continue };
+                if !cascade_ends.is_empty() {
+                    log::trace!("Only the first directional light casts shadows.");
+                    continue;
+                }
+
+                let (near, far) = camera.clip_planes();
+                cascade_ends = settings.cascade_ends(near, far);
+
+                let start = matrices.len() as u32;
+                let mut slice_near = near;
+                for &slice_far in &cascade_ends {
+                    matrices.push(cascade_matrix(settings, camera, &placed.direction(), slice_near, slice_far));
+                    slice_near = slice_far;
+                }
+                placed.shadow_layers = Some(start..matrices.len() as u32);
+            },
+            LightKind::Spot { attenuation, outer, .. } => {
+                if spot_shadows >= settings.max_spot_shadows || matrices.len() >= settings.layers() {
+                    log::trace!("Out of shadow map layers for spot lights.");
+                    continue;
+                }
+
+                let far = attenuation.reach().min(settings.max_distance).max(0.1);
+                let fov = (2.0 * outer).clamp(1f32.to_radians(), 170f32.to_radians());
+                let projection = Perspective3::new(1.0, fov, 0.05, far).to_homogeneous();
+
+                let start = matrices.len() as u32;
+                matrices.push(projection * light_view(&placed.position(), &placed.direction()));
+                placed.shadow_layers = Some(start..start + 1);
+                spot_shadows += 1;
+            },
+            LightKind::Point { .. } => {},
+        }
+    }
+
+    (matrices, cascade_ends)
+}
+
+/// a view matrix at `position` looking along `direction`.
+fn light_view(position: &Point3<f32>, direction: &Vector3<f32>) -> Matrix4<f32> {
+    let up = if direction.y.abs() > 0.99 { Vector3::x() } else { Vector3::y() };
+    Matrix4::look_at_rh(position, &(position + direction), &up)
+}
+
+/// the light space matrix of the cascade covering the view
+/// depths from `near` to `far` of `camera`.
+fn cascade_matrix(settings: &ShadowSettings, camera: &Camera, direction: &Vector3<f32>, near: f32, far: f32) -> Matrix4<f32> {
+    let corners = frustum_slice(camera, near, far);
+    let center = corners.iter().fold(Vector3::zeros(), |sum, corner| sum + corner.coords) / 8.0;
+    let center = Point3::from(center);
+
+    // casters between the light and the slice must not be clipped
+    let caster_range = settings.max_distance;
+
+    if settings.stable {
+        let radius = corners.iter().map(|corner| (corner - center).norm()).fold(0.0, f32::max);
+        let radius = (radius * 16.0).ceil() / 16.0;
+
+        let eye = center - direction * (radius + caster_range);
+        let view = light_view(&eye, direction);
+        let mut projection = Orthographic3::new(-radius, radius, -radius, radius, 0.0, 2.0 * radius + caster_range).to_homogeneous();
+
+        // snap the world origin to a texel, so the map only ever moves by whole texels
+        let half_resolution = settings.resolution.max(1) as f32 * 0.5;
+        let origin = projection * view * Vector4::new(0.0, 0.0, 0.0, 1.0) * half_resolution;
+        let offset = (origin.xy().map(f32::round) - origin.xy()) / half_resolution;
+        projection[(0, 3)] += offset.x;
+        projection[(1, 3)] += offset.y;
+
+        projection * view
+    } else {
+        let view = light_view(&center, direction);
+        let (mut min, mut max) = (Vector3::repeat(f32::MAX), Vector3::repeat(f32::MIN));
+        for corner in &corners {
+            let corner = view.transform_point(corner).coords;
+            min = min.inf(&corner);
+            max = max.sup(&corner);
+        }
+
+        let projection = Orthographic3::new(min.x, max.x, min.y, max.y, -max.z - caster_range, -min.z).to_homogeneous();
+        projection * view
+    }
+}
+
+/// the world space corners of the part of the camera frustum
+/// between the view depths `near` and `far`.
+fn frustum_slice(camera: &Camera, near: f32, far: f32) -> [Point3<f32>; 8] {
+    let inverse = camera.view_projection().try_inverse().unwrap_or_else(Matrix4::identity);
+    let (camera_near, camera_far) = camera.clip_planes();
+    let range = (camera_far - camera_near).max(f32::EPSILON);
+    let (t0, t1) = ((near - camera_near) / range, (far - camera_near) / range);
+
+    let mut corners = [Point3::origin(); 8];
+    for (i, (x, y)) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].into_iter().enumerate() {
+        let unproject = |z: f32| {
+            let point = inverse * Vector4::new(x, y, z, 1.0);
+            Point3::from(point.xyz() / point.w)
+        };
+
+        // view depth changes linearly along each edge of the frustum
+        let (edge_near, edge_far) = (unproject(-1.0), unproject(1.0));
+        corners[i] = edge_near + (edge_far - edge_near) * t0;
+        corners[i + 4] = edge_near + (edge_far - edge_near) * t1;
+    }
+    corners
+}
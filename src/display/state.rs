@@ -0,0 +1,475 @@
+use gl33::{GLenum, GlFns};
+use serde::{Deserialize, Serialize};
+
+/// comparison function used by the depth and stencil tests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompareFunc {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl CompareFunc {
+    fn gl(self) -> GLenum {
+        match self {
+            CompareFunc::Never => gl33::GL_NEVER,
+            CompareFunc::Less => gl33::GL_LESS,
+            CompareFunc::Equal => gl33::GL_EQUAL,
+            CompareFunc::LessEqual => gl33::GL_LEQUAL,
+            CompareFunc::Greater => gl33::GL_GREATER,
+            CompareFunc::NotEqual => gl33::GL_NOTEQUAL,
+            CompareFunc::GreaterEqual => gl33::GL_GEQUAL,
+            CompareFunc::Always => gl33::GL_ALWAYS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DepthState {
+    /// whether fragments are tested against the depth buffer
+    pub test: bool,
+
+    /// whether passing fragments write their depth
+    pub write: bool,
+
+    pub func: CompareFunc,
+}
+
+impl Default for DepthState {
+    fn default() -> Self {
+        Self { test: true, write: true, func: CompareFunc::Less }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    Replace,
+    Increment,
+    IncrementWrap,
+    Decrement,
+    DecrementWrap,
+    Invert,
+}
+
+impl StencilOp {
+    fn gl(self) -> GLenum {
+        match self {
+            StencilOp::Keep => gl33::GL_KEEP,
+            StencilOp::Zero => gl33::GL_ZERO,
+            StencilOp::Replace => gl33::GL_REPLACE,
+            StencilOp::Increment => gl33::GL_INCR,
+            StencilOp::IncrementWrap => gl33::GL_INCR_WRAP,
+            StencilOp::Decrement => gl33::GL_DECR,
+            StencilOp::DecrementWrap => gl33::GL_DECR_WRAP,
+            StencilOp::Invert => gl33::GL_INVERT,
+        }
+    }
+}
+
+/// stencil configuration for a single face orientation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StencilFace {
+    pub func: CompareFunc,
+    pub reference: i32,
+    pub read_mask: u32,
+    pub write_mask: u32,
+
+    /// operation when the stencil test fails
+    pub fail: StencilOp,
+
+    /// operation when the stencil test passes but the depth test fails
+    pub depth_fail: StencilOp,
+
+    /// operation when both tests pass
+    pub pass: StencilOp,
+}
+
+impl Default for StencilFace {
+    fn default() -> Self {
+        Self {
+            func: CompareFunc::Always,
+            reference: 0,
+            read_mask: !0,
+            write_mask: !0,
+            fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StencilState {
+    pub test: bool,
+    pub front: StencilFace,
+    pub back: StencilFace,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlendEquation {
+    Add,
+    Subtract,
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+impl BlendEquation {
+    fn gl(self) -> GLenum {
+        match self {
+            BlendEquation::Add => gl33::GL_FUNC_ADD,
+            BlendEquation::Subtract => gl33::GL_FUNC_SUBTRACT,
+            BlendEquation::ReverseSubtract => gl33::GL_FUNC_REVERSE_SUBTRACT,
+            BlendEquation::Min => gl33::GL_MIN,
+            BlendEquation::Max => gl33::GL_MAX,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    DstColor,
+    OneMinusDstColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstAlpha,
+    OneMinusDstAlpha,
+    ConstantColor,
+    OneMinusConstantColor,
+    ConstantAlpha,
+    OneMinusConstantAlpha,
+    SrcAlphaSaturate,
+}
+
+impl BlendFactor {
+    fn gl(self) -> GLenum {
+        match self {
+            BlendFactor::Zero => gl33::GL_ZERO,
+            BlendFactor::One => gl33::GL_ONE,
+            BlendFactor::SrcColor => gl33::GL_SRC_COLOR,
+            BlendFactor::OneMinusSrcColor => gl33::GL_ONE_MINUS_SRC_COLOR,
+            BlendFactor::DstColor => gl33::GL_DST_COLOR,
+            BlendFactor::OneMinusDstColor => gl33::GL_ONE_MINUS_DST_COLOR,
+            BlendFactor::SrcAlpha => gl33::GL_SRC_ALPHA,
+            BlendFactor::OneMinusSrcAlpha => gl33::GL_ONE_MINUS_SRC_ALPHA,
+            BlendFactor::DstAlpha => gl33::GL_DST_ALPHA,
+            BlendFactor::OneMinusDstAlpha => gl33::GL_ONE_MINUS_DST_ALPHA,
+            BlendFactor::ConstantColor => gl33::GL_CONSTANT_COLOR,
+            BlendFactor::OneMinusConstantColor => gl33::GL_ONE_MINUS_CONSTANT_COLOR,
+            BlendFactor::ConstantAlpha => gl33::GL_CONSTANT_ALPHA,
+            BlendFactor::OneMinusConstantAlpha => gl33::GL_ONE_MINUS_CONSTANT_ALPHA,
+            BlendFactor::SrcAlphaSaturate => gl33::GL_SRC_ALPHA_SATURATE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlendState {
+    pub enabled: bool,
+    pub color_equation: BlendEquation,
+    pub alpha_equation: BlendEquation,
+    pub src_color: BlendFactor,
+    pub dst_color: BlendFactor,
+    pub src_alpha: BlendFactor,
+    pub dst_alpha: BlendFactor,
+
+    /// the color used by the `Constant*` blend factors
+    pub constant: [f32; 4],
+}
+
+impl BlendState {
+    /// standard "over" alpha blending.
+    pub fn alpha() -> Self {
+        Self {
+            enabled: true,
+            src_color: BlendFactor::SrcAlpha,
+            dst_color: BlendFactor::OneMinusSrcAlpha,
+            src_alpha: BlendFactor::One,
+            dst_alpha: BlendFactor::OneMinusSrcAlpha,
+            ..Self::default()
+        }
+    }
+
+    /// additive blending, e.g. for accumulating light.
+    pub fn additive() -> Self {
+        Self {
+            enabled: true,
+            src_color: BlendFactor::One,
+            dst_color: BlendFactor::One,
+            src_alpha: BlendFactor::One,
+            dst_alpha: BlendFactor::One,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for BlendState {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            color_equation: BlendEquation::Add,
+            alpha_equation: BlendEquation::Add,
+            src_color: BlendFactor::One,
+            dst_color: BlendFactor::Zero,
+            src_alpha: BlendFactor::One,
+            dst_alpha: BlendFactor::Zero,
+            constant: [0.0; 4],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CullMode {
+    None,
+    Front,
+    Back,
+    FrontAndBack,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FrontFace {
+    CounterClockwise,
+    Clockwise,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PolygonMode {
+    Fill,
+    Line,
+    Point,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Scissor {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// the complete fixed-function pipeline state used for a draw.
+///
+/// a `RenderState` is plain data; it only touches opengl
+/// when handed to a `StateCache`, which issues the calls
+/// needed to get from the previous state to this one.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderState {
+    pub depth: DepthState,
+    pub stencil: StencilState,
+    pub blend: BlendState,
+    pub cull: CullMode,
+    pub front_face: FrontFace,
+    pub polygon_mode: PolygonMode,
+
+    /// the scissor rectangle, or `None` to disable the scissor test
+    pub scissor: Option<Scissor>,
+
+    /// which of the red, green, blue and alpha channels are written
+    pub color_mask: [bool; 4],
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        Self {
+            depth: DepthState::default(),
+            stencil: StencilState::default(),
+            blend: BlendState::default(),
+            cull: CullMode::Back,
+            front_face: FrontFace::CounterClockwise,
+            polygon_mode: PolygonMode::Fill,
+            scissor: None,
+            color_mask: [true; 4],
+        }
+    }
+}
+
+impl RenderState {
+    /// depth tested, depth writing, back-face culled geometry.
+    pub fn opaque() -> Self {
+        Self::default()
+    }
+
+    /// alpha blended geometry that is depth tested but
+    /// does not write depth.
+    pub fn transparent() -> Self {
+        Self {
+            depth: DepthState { write: false, ..DepthState::default() },
+            blend: BlendState::alpha(),
+            cull: CullMode::None,
+            ..Self::default()
+        }
+    }
+
+    /// geometry drawn on top of everything else, e.g. ui or debug views.
+    pub fn overlay() -> Self {
+        Self {
+            depth: DepthState { test: false, write: false, func: CompareFunc::Always },
+            blend: BlendState::alpha(),
+            cull: CullMode::None,
+            ..Self::default()
+        }
+    }
+}
+
+/// tracks the render state last applied to opengl and
+/// only issues the calls for the parts that changed.
+#[derive(Default)]
+pub struct StateCache {
+    /// `None` when the gl state is unknown, in which case
+    /// the next `apply` sets every piece of state.
+    current: Option<RenderState>,
+}
+
+fn gl_bool(value: bool) -> u8 {
+    value as u8
+}
+
+fn set_capability(gl: &GlFns, cap: GLenum, enabled: bool) {
+    unsafe {
+        if enabled {
+            gl.Enable(cap);
+        } else {
+            gl.Disable(cap);
+        }
+    }
+}
+
+impl StateCache {
+    pub fn new() -> Self {
+        Self { current: None }
+    }
+
+    /// forget the tracked state, e.g. after foreign code touched opengl.
+    pub fn invalidate(&mut self) {
+        self.current = None;
+    }
+
+    /// the state that was last applied, if known.
+    pub fn current(&self) -> Option<&RenderState> {
+        self.current.as_ref()
+    }
+
+    pub fn apply(&mut self, gl: &GlFns, state: &RenderState) {
+        let previous = self.current;
+
+        if previous.is_none_or(|p| p.depth != state.depth) {
+            Self::apply_depth(gl, &state.depth);
+        }
+
+        if previous.is_none_or(|p| p.stencil != state.stencil) {
+            Self::apply_stencil(gl, &state.stencil);
+        }
+
+        if previous.is_none_or(|p| p.blend != state.blend) {
+            Self::apply_blend(gl, &state.blend);
+        }
+
+        if previous.is_none_or(|p| p.cull != state.cull) {
+            Self::apply_cull(gl, state.cull);
+        }
+
+        if previous.is_none_or(|p| p.front_face != state.front_face) {
+            unsafe {
+                gl.FrontFace(match state.front_face {
+                    FrontFace::CounterClockwise => gl33::GL_CCW,
+                    FrontFace::Clockwise => gl33::GL_CW,
+                });
+            }
+        }
+
+        if previous.is_none_or(|p| p.polygon_mode != state.polygon_mode) {
+            unsafe {
+                gl.PolygonMode(gl33::GL_FRONT_AND_BACK, match state.polygon_mode {
+                    PolygonMode::Fill => gl33::GL_FILL,
+                    PolygonMode::Line => gl33::GL_LINE,
+                    PolygonMode::Point => gl33::GL_POINT,
+                });
+            }
+        }
+
+        if previous.is_none_or(|p| p.scissor != state.scissor) {
+            set_capability(gl, gl33::GL_SCISSOR_TEST, state.scissor.is_some());
+            if let Some(scissor) = state.scissor {
+                unsafe {
+                    gl.Scissor(scissor.x, scissor.y, scissor.width, scissor.height);
+                }
+            }
+        }
+
+        if previous.is_none_or(|p| p.color_mask != state.color_mask) {
+            let [r, g, b, a] = state.color_mask;
+            unsafe {
+                gl.ColorMask(gl_bool(r), gl_bool(g), gl_bool(b), gl_bool(a));
+            }
+        }
+
+        self.current = Some(*state);
+    }
+
+    fn apply_depth(gl: &GlFns, depth: &DepthState) {
+        set_capability(gl, gl33::GL_DEPTH_TEST, depth.test);
+        unsafe {
+            gl.DepthMask(gl_bool(depth.write));
+            gl.DepthFunc(depth.func.gl());
+        }
+    }
+
+    fn apply_stencil(gl: &GlFns, stencil: &StencilState) {
+        set_capability(gl, gl33::GL_STENCIL_TEST, stencil.test);
+
+        for (face, config) in [(gl33::GL_FRONT, &stencil.front), (gl33::GL_BACK, &stencil.back)] {
+            unsafe {
+                gl.StencilFuncSeparate(face, config.func.gl(), config.reference, config.read_mask);
+                gl.StencilMaskSeparate(face, config.write_mask);
+                gl.StencilOpSeparate(face, config.fail.gl(), config.depth_fail.gl(), config.pass.gl());
+            }
+        }
+    }
+
+    fn apply_blend(gl: &GlFns, blend: &BlendState) {
+        set_capability(gl, gl33::GL_BLEND, blend.enabled);
+
+        let [r, g, b, a] = blend.constant;
+        unsafe {
+            gl.BlendEquationSeparate(blend.color_equation.gl(), blend.alpha_equation.gl());
+            gl.BlendFuncSeparate(
+                blend.src_color.gl(),
+                blend.dst_color.gl(),
+                blend.src_alpha.gl(),
+                blend.dst_alpha.gl(),
+            );
+            gl.BlendColor(r, g, b, a);
+        }
+    }
+
+    fn apply_cull(gl: &GlFns, cull: CullMode) {
+        set_capability(gl, gl33::GL_CULL_FACE, cull != CullMode::None);
+
+        let face = match cull {
+            CullMode::None => return,
+            CullMode::Front => gl33::GL_FRONT,
+            CullMode::Back => gl33::GL_BACK,
+            CullMode::FrontAndBack => gl33::GL_FRONT_AND_BACK,
+        };
+
+        unsafe {
+            gl.CullFace(face);
+        }
+    }
+}
@@ -1,9 +1,9 @@
 use gl33::{GLenum, GlFns};
-use image::ColorType;
+use image::{ColorType, DynamicImage};
 
 use crate::{Error, Result};
 
-use super::shader::Program;
+use super::{context::GlContext, shader::Program};
 
 pub struct Texture {
     pub id: u32,
@@ -12,9 +12,99 @@ pub struct Texture {
     pub format: TextureFormat,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TextureFormat {
     Rgb,
     Rgba,
+
+    /// half float formats, uploaded from `f32` data
+    Rg16F,
+    Rgb16F,
+    Rgba16F,
+
+    /// a depth and stencil attachment, matching the default framebuffer
+    Depth24Stencil8,
+}
+
+impl TextureFormat {
+    pub fn internal_format(self) -> i32 {
+        let format = match self {
+            TextureFormat::Rgb => gl33::GL_RGB,
+            TextureFormat::Rgba => gl33::GL_RGBA,
+            TextureFormat::Rg16F => gl33::GL_RG16F,
+            TextureFormat::Rgb16F => gl33::GL_RGB16F,
+            TextureFormat::Rgba16F => gl33::GL_RGBA16F,
+            TextureFormat::Depth24Stencil8 => gl33::GL_DEPTH24_STENCIL8,
+        };
+        format.0 as i32
+    }
+
+    /// the layout of the pixel data handed to opengl.
+    pub fn pixel_format(self) -> GLenum {
+        match self {
+            TextureFormat::Rg16F => gl33::GL_RG,
+            TextureFormat::Rgb | TextureFormat::Rgb16F => gl33::GL_RGB,
+            TextureFormat::Rgba | TextureFormat::Rgba16F => gl33::GL_RGBA,
+            TextureFormat::Depth24Stencil8 => gl33::GL_DEPTH_STENCIL,
+        }
+    }
+
+    /// the component type of the pixel data handed to opengl.
+    pub fn pixel_type(self) -> GLenum {
+        match self {
+            TextureFormat::Rgb | TextureFormat::Rgba => gl33::GL_UNSIGNED_BYTE,
+            TextureFormat::Rg16F | TextureFormat::Rgb16F | TextureFormat::Rgba16F => gl33::GL_FLOAT,
+            TextureFormat::Depth24Stencil8 => gl33::GL_UNSIGNED_INT_24_8,
+        }
+    }
+
+    pub fn is_float(self) -> bool {
+        matches!(self, TextureFormat::Rg16F | TextureFormat::Rgb16F | TextureFormat::Rgba16F)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFilter {
+    Nearest,
+    Linear,
+
+    /// linear filtering between and within mip levels
+    Trilinear,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureWrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+impl TextureWrap {
+    fn gl(self) -> GLenum {
+        match self {
+            TextureWrap::Repeat => gl33::GL_REPEAT,
+            TextureWrap::MirroredRepeat => gl33::GL_MIRRORED_REPEAT,
+            TextureWrap::ClampToEdge => gl33::GL_CLAMP_TO_EDGE,
+        }
+    }
+}
+
+/// set the filtering and wrapping of the texture bound to `target`.
+pub(crate) fn set_sampling(gl: &GlFns, target: GLenum, filter: TextureFilter, wrap: TextureWrap) {
+    let (min, mag) = match filter {
+        TextureFilter::Nearest => (gl33::GL_NEAREST, gl33::GL_NEAREST),
+        TextureFilter::Linear => (gl33::GL_LINEAR, gl33::GL_LINEAR),
+        TextureFilter::Trilinear => (gl33::GL_LINEAR_MIPMAP_LINEAR, gl33::GL_LINEAR),
+    };
+    let wrap = wrap.gl();
+
+    unsafe {
+        gl.TexParameteri(target, gl33::GL_TEXTURE_MIN_FILTER, min.0 as i32);
+        gl.TexParameteri(target, gl33::GL_TEXTURE_MAG_FILTER, mag.0 as i32);
+        gl.TexParameteri(target, gl33::GL_TEXTURE_WRAP_S, wrap.0 as i32);
+        gl.TexParameteri(target, gl33::GL_TEXTURE_WRAP_T, wrap.0 as i32);
+        gl.TexParameteri(target, gl33::GL_TEXTURE_WRAP_R, wrap.0 as i32);
+    }
 }
 
 impl Texture {
@@ -30,61 +120,97 @@ impl Texture {
         self.id - 1
     }
 
-    pub fn activate(&self, gl: &GlFns) -> Result<()> {
-        unsafe {
-            gl.ActiveTexture(match self.unit() {
-                0..31 => GLenum(gl33::GL_TEXTURE0.0 + self.unit()),
-                _ => Err(Error::GlTextureActivation(format!(
-                    "Texture ID out of range: {}.",
-                    self.id
-                )))?,
-            })
+    pub fn activate(&self, ctx: &GlContext) -> Result<()> {
+        if self.unit() >= ctx.texture_units() {
+            return Err(Error::GlTextureActivation(format!(
+                "Texture ID out of range: {}.",
+                self.id
+            )));
         }
 
+        ctx.active_texture(self.unit());
         Ok(())
     }
 
-    pub fn bind(&self, gl: &GlFns) {
-        unsafe {
-            gl.BindTexture(gl33::GL_TEXTURE_2D, self.id);
-        }
+    pub fn bind(&self, ctx: &GlContext) {
+        ctx.bind_texture(gl33::GL_TEXTURE_2D, self.id);
     }
 
-    pub fn unbind(gl: &GlFns) {
-        unsafe {
-            gl.BindTexture(gl33::GL_TEXTURE_2D, 0);
-        }
+    pub fn unbind(ctx: &GlContext) {
+        ctx.bind_texture(gl33::GL_TEXTURE_2D, 0);
     }
 
+    /// upload the pixels of the bound texture. float formats
+    /// expect the bytes of `f32` components. rows are tightly
+    /// packed, whatever their width.
     pub fn data(&self, gl: &GlFns, data: &[u8]) {
         unsafe {
+            gl.PixelStorei(gl33::GL_UNPACK_ALIGNMENT, 1);
             gl.TexImage2D(
                 gl33::GL_TEXTURE_2D,
                 0,
-                match self.format {
-                    TextureFormat::Rgb => gl33::GL_RGB.0 as i32,
-                    TextureFormat::Rgba => gl33::GL_RGBA.0 as i32,
-                },
+                self.format.internal_format(),
                 self.width as i32,
                 self.height as i32,
                 0,
-                match self.format {
-                    TextureFormat::Rgb => gl33::GL_RGB,
-                    TextureFormat::Rgba => gl33::GL_RGBA,
-                },
-                gl33::GL_UNSIGNED_BYTE,
+                self.format.pixel_format(),
+                self.format.pixel_type(),
                 data.as_ptr() as *const _,
             );
         }
     }
 
+    /// allocate storage for the bound texture without uploading pixels.
+    pub fn allocate(&self, gl: &GlFns) {
+        unsafe {
+            gl.TexImage2D(
+                gl33::GL_TEXTURE_2D,
+                0,
+                self.format.internal_format(),
+                self.width as i32,
+                self.height as i32,
+                0,
+                self.format.pixel_format(),
+                self.format.pixel_type(),
+                std::ptr::null(),
+            );
+        }
+    }
+
+    /// set the filtering and wrapping of the bound texture.
+    pub fn set_sampling(&self, gl: &GlFns, filter: TextureFilter, wrap: TextureWrap) {
+        set_sampling(gl, gl33::GL_TEXTURE_2D, filter, wrap);
+    }
+
+    /// set the horizontal and vertical wrapping of the bound texture
+    /// separately.
+    pub fn set_wrap(&self, gl: &GlFns, wrap_s: TextureWrap, wrap_t: TextureWrap) {
+        unsafe {
+            gl.TexParameteri(gl33::GL_TEXTURE_2D, gl33::GL_TEXTURE_WRAP_S, wrap_s.gl().0 as i32);
+            gl.TexParameteri(gl33::GL_TEXTURE_2D, gl33::GL_TEXTURE_WRAP_T, wrap_t.gl().0 as i32);
+        }
+    }
+
+    /// name the texture in debug messages and graphics debuggers.
+    pub fn set_label(&self, ctx: &GlContext, label: &str) {
+        ctx.label(gl33::GL_TEXTURE, self.id, label);
+    }
+
+    /// delete the texture, forgetting any tracked binding of it.
+    pub fn delete(self, ctx: &GlContext) {
+        ctx.forget_texture(self.id);
+        unsafe {
+            ctx.DeleteTextures(1, &self.id);
+        }
+    }
+
     pub fn generate_mipmap(&self, gl: &GlFns) {
         unsafe {
             gl.GenerateMipmap(gl33::GL_TEXTURE_2D);
         }
     }
 
-    pub fn load_data(gl: &GlFns, data: &[u8], width: u32, height: u32, format: TextureFormat) -> Self {
+    pub fn load_data(gl: &GlContext, data: &[u8], width: u32, height: u32, format: TextureFormat) -> Self {
         let texture = Texture::new(gl, width, height, format);
         texture.bind(gl);
         texture.data(gl, data);
@@ -92,35 +218,36 @@ impl Texture {
         texture
     }
 
-    pub fn load_file(gl: &GlFns, path: &str) -> Result<Self> {
+    /// an uninitialized texture, e.g. to render into.
+    pub fn empty(gl: &GlContext, width: u32, height: u32, format: TextureFormat, filter: TextureFilter, wrap: TextureWrap) -> Self {
+        let texture = Texture::new(gl, width, height, format);
+        texture.bind(gl);
+        texture.allocate(gl);
+        texture.set_sampling(gl, filter, wrap);
+        texture
+    }
+
+    /// load a high dynamic range image, e.g. a `.hdr` or `.exr`
+    /// file, into an `Rgb16F` texture without mipmaps.
+    pub fn load_hdr(gl: &GlContext, path: &str) -> Result<Self> {
+        log::debug!("Loading hdr texture from file... {}", path);
+
+        let img = image::open(path)?.flipv().to_rgb32f();
+        let (width, height) = img.dimensions();
+
+        let texture = Texture::new(gl, width, height, TextureFormat::Rgb16F);
+        texture.bind(gl);
+        texture.data(gl, bytemuck::cast_slice(img.as_raw()));
+        texture.set_sampling(gl, TextureFilter::Linear, TextureWrap::ClampToEdge);
+        Ok(texture)
+    }
+
+    pub fn load_file(gl: &GlContext, path: &str) -> Result<Self> {
         log::debug!("Loading texture from file... {}", path);
 
-        let img = image::open(path)?;
-        let img = img.flipv();
-
-        match img.color() {
-            ColorType::Rgb8 => {
-                let img = img.to_rgb8();
-                let (width, height) = img.dimensions();
-                let data = img.into_raw();
-                let texture = Texture::load_data(gl, &data, width, height, TextureFormat::Rgb);
-                Ok(texture)
-            },
-            ColorType::Rgba8 => {
-                let img = img.to_rgba8();
-                let (width, height) = img.dimensions();
-                let data = img.into_raw();
-                let texture = Texture::load_data(gl, &data, width, height, TextureFormat::Rgba);
-                Ok(texture)
-            },
-            _ => {
-                log::error!("Unsupported image format: {:?}", img.color());
-                Err(Error::ImageFormat(format!(
-                    "Unsupported image format: {:?}. Only RGB(8) and RGBA(8) are supported.",
-                    img.color()
-                )))
-            }
-        }
+        let img = image::open(path)?.flipv();
+        let (data, format) = pixels(&img)?;
+        Ok(Texture::load_data(gl, &data, img.width(), img.height(), format))
     }
 
     pub fn uniform(&self, gl: &GlFns, program: &Program, name: &str) -> Result<()> {
@@ -128,3 +255,46 @@ impl Texture {
         program.uniform_1i(gl, name, self.unit() as i32)
     }
 }
+
+/// the 8 bit pixel data of `img` and its texture format. grayscale
+/// images are expanded so that single channel maps, e.g. alpha or
+/// bump maps, sample the same as in other formats.
+fn pixels(img: &DynamicImage) -> Result<(Vec<u8>, TextureFormat)> {
+    match img.color() {
+        ColorType::Rgb8 | ColorType::L8 => Ok((img.to_rgb8().into_raw(), TextureFormat::Rgb)),
+        ColorType::Rgba8 | ColorType::La8 => Ok((img.to_rgba8().into_raw(), TextureFormat::Rgba)),
+        color => {
+            log::error!("Unsupported image format: {:?}", color);
+            Err(Error::ImageFormat(format!(
+                "Unsupported image format: {:?}. Only 8 bit grayscale, RGB and RGBA images are supported.",
+                color
+            )))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayAlphaImage, GrayImage, ImageBuffer, Rgb};
+
+    use super::*;
+
+    #[test]
+    fn expands_grayscale() {
+        let gray = DynamicImage::ImageLuma8(GrayImage::from_raw(2, 1, vec![10, 200]).unwrap());
+        let (data, format) = pixels(&gray).unwrap();
+        assert_eq!(format, TextureFormat::Rgb);
+        assert_eq!(data, [10, 10, 10, 200, 200, 200]);
+
+        let gray_alpha = DynamicImage::ImageLumaA8(GrayAlphaImage::from_raw(1, 1, vec![10, 128]).unwrap());
+        let (data, format) = pixels(&gray_alpha).unwrap();
+        assert_eq!(format, TextureFormat::Rgba);
+        assert_eq!(data, [10, 10, 10, 128]);
+    }
+
+    #[test]
+    fn rejects_16_bit_images() {
+        let img = DynamicImage::ImageRgb16(ImageBuffer::<Rgb<u16>, _>::new(1, 1));
+        assert!(matches!(pixels(&img), Err(Error::ImageFormat(_))));
+    }
+}
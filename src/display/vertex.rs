@@ -4,12 +4,21 @@ use gl33::{GLenum, GlFns};
 
 use crate::common::raw::AsRaw;
 
+use super::context::GlContext;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
 pub struct Vertex {
     pub position: [f32; 3],
     pub color: [f32; 3],
     pub texture: [f32; 2],
 }
 
+// SAFETY: `Vertex` is `repr(C)` and made up of `f32`s only, so it
+// has no padding and every bit pattern is valid.
+unsafe impl bytemuck::Zeroable for Vertex {}
+unsafe impl bytemuck::Pod for Vertex {}
+
 impl Vertex {
     pub fn new(position: [f32; 3], color: [f32; 3], texture: [f32; 2]) -> Self {
         Vertex { position, color, texture }
@@ -18,6 +27,82 @@ impl Vertex {
     pub fn size() -> usize {
         std::mem::size_of::<Vertex>()
     }
+
+    /// position at location 0, color at 1 and texture coordinates at 2.
+    pub fn layout() -> VertexLayout {
+        VertexLayout::new(Vertex::size())
+            .attribute(0, 3, AttributeType::Float, std::mem::offset_of!(Vertex, position))
+            .attribute(1, 3, AttributeType::Float, std::mem::offset_of!(Vertex, color))
+            .attribute(2, 2, AttributeType::Float, std::mem::offset_of!(Vertex, texture))
+    }
+}
+
+/// vertex format used for lit geometry, e.g. generated
+/// primitives and loaded models.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+
+    /// tangent in xyz, bitangent handedness in w
+    pub tangent: [f32; 4],
+}
+
+// SAFETY: `MeshVertex` is `repr(C)` and made up of `f32`s only.
+unsafe impl bytemuck::Zeroable for MeshVertex {}
+unsafe impl bytemuck::Pod for MeshVertex {}
+
+impl MeshVertex {
+    /// position at location 0, normal at 1, uv at 2 and tangent at 3.
+    pub fn layout() -> VertexLayout {
+        VertexLayout::new(std::mem::size_of::<MeshVertex>())
+            .attribute(0, 3, AttributeType::Float, std::mem::offset_of!(MeshVertex, position))
+            .attribute(1, 3, AttributeType::Float, std::mem::offset_of!(MeshVertex, normal))
+            .attribute(2, 2, AttributeType::Float, std::mem::offset_of!(MeshVertex, uv))
+            .attribute(3, 4, AttributeType::Float, std::mem::offset_of!(MeshVertex, tangent))
+    }
+}
+
+/// a `MeshVertex` bound to up to four joints of a skin.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkinnedVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+    pub tangent: [f32; 4],
+
+    /// indices into the joints of the skin
+    pub joints: [u16; 4],
+
+    /// how much each joint moves the vertex, summing up to 1
+    pub weights: [f32; 4],
+}
+
+// SAFETY: `SkinnedVertex` is `repr(C)` and made up of `f32`s and
+// four `u16`s, which end on a 4 byte boundary, so it has no padding.
+unsafe impl bytemuck::Zeroable for SkinnedVertex {}
+unsafe impl bytemuck::Pod for SkinnedVertex {}
+
+impl SkinnedVertex {
+    pub fn new(vertex: MeshVertex, joints: [u16; 4], weights: [f32; 4]) -> Self {
+        let MeshVertex { position, normal, uv, tangent } = vertex;
+        Self { position, normal, uv, tangent, joints, weights }
+    }
+
+    /// the `MeshVertex` layout, with the joints as integers
+    /// at location 4 and the weights at 5.
+    pub fn layout() -> VertexLayout {
+        VertexLayout::new(std::mem::size_of::<SkinnedVertex>())
+            .attribute(0, 3, AttributeType::Float, std::mem::offset_of!(SkinnedVertex, position))
+            .attribute(1, 3, AttributeType::Float, std::mem::offset_of!(SkinnedVertex, normal))
+            .attribute(2, 2, AttributeType::Float, std::mem::offset_of!(SkinnedVertex, uv))
+            .attribute(3, 4, AttributeType::Float, std::mem::offset_of!(SkinnedVertex, tangent))
+            .attribute(4, 4, AttributeType::UnsignedShort, std::mem::offset_of!(SkinnedVertex, joints))
+            .attribute(5, 4, AttributeType::Float, std::mem::offset_of!(SkinnedVertex, weights))
+    }
 }
 
 impl AsRaw<f32> for Vertex {
@@ -30,6 +115,136 @@ impl AsRaw<f32> for Vertex {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeType {
+    Float,
+    Byte,
+    UnsignedByte,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+}
+
+impl AttributeType {
+    fn gl(self) -> GLenum {
+        match self {
+            AttributeType::Float => gl33::GL_FLOAT,
+            AttributeType::Byte => gl33::GL_BYTE,
+            AttributeType::UnsignedByte => gl33::GL_UNSIGNED_BYTE,
+            AttributeType::Short => gl33::GL_SHORT,
+            AttributeType::UnsignedShort => gl33::GL_UNSIGNED_SHORT,
+            AttributeType::Int => gl33::GL_INT,
+            AttributeType::UnsignedInt => gl33::GL_UNSIGNED_INT,
+        }
+    }
+
+    fn is_integer(self) -> bool {
+        self != AttributeType::Float
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexAttribute {
+    /// the shader attribute location
+    pub location: u32,
+
+    /// the number of components, 1 to 4
+    pub components: i32,
+
+    pub kind: AttributeType,
+
+    /// whether integer data is normalized to [0, 1] / [-1, 1] floats.
+    /// integer attributes that are not normalized are exposed to the
+    /// shader as integers.
+    pub normalized: bool,
+
+    /// byte offset of the attribute within a vertex
+    pub offset: usize,
+
+    /// 0 to advance the attribute per vertex, otherwise
+    /// the number of instances that share one value
+    pub divisor: u32,
+}
+
+/// describes how the attributes of one vertex
+/// are laid out inside a vertex buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexLayout {
+    /// the size of one vertex in bytes
+    pub stride: usize,
+    pub attributes: Vec<VertexAttribute>,
+}
+
+impl VertexLayout {
+    pub fn new(stride: usize) -> Self {
+        Self { stride, attributes: Vec::new() }
+    }
+
+    pub fn attribute(mut self, location: u32, components: i32, kind: AttributeType, offset: usize) -> Self {
+        self.attributes.push(VertexAttribute { location, components, kind, normalized: false, offset, divisor: 0 });
+        self
+    }
+
+    pub fn normalized_attribute(mut self, location: u32, components: i32, kind: AttributeType, offset: usize) -> Self {
+        self.attributes.push(VertexAttribute { location, components, kind, normalized: true, offset, divisor: 0 });
+        self
+    }
+
+    /// a 4x4 float matrix occupies four consecutive locations,
+    /// one per column, starting at `location`.
+    pub fn matrix4(mut self, location: u32, offset: usize) -> Self {
+        let column = 4 * std::mem::size_of::<f32>();
+        for i in 0..4 {
+            self = self.attribute(location + i, 4, AttributeType::Float, offset + i as usize * column);
+        }
+        self
+    }
+
+    /// advance every attribute once per instance instead of once per vertex.
+    pub fn per_instance(self) -> Self {
+        self.divisor(1)
+    }
+
+    pub fn divisor(mut self, divisor: u32) -> Self {
+        for attribute in &mut self.attributes {
+            attribute.divisor = divisor;
+        }
+        self
+    }
+
+    /// set up the attribute pointers of the bound vertex
+    /// array to read from the bound vertex buffer.
+    pub fn apply(&self, gl: &GlFns) {
+        let stride = self.stride as i32;
+
+        for attribute in &self.attributes {
+            unsafe {
+                if attribute.kind.is_integer() && !attribute.normalized {
+                    gl.VertexAttribIPointer(
+                        attribute.location,
+                        attribute.components,
+                        attribute.kind.gl(),
+                        stride,
+                        attribute.offset as *const _,
+                    );
+                } else {
+                    gl.VertexAttribPointer(
+                        attribute.location,
+                        attribute.components,
+                        attribute.kind.gl(),
+                        attribute.normalized as u8,
+                        stride,
+                        attribute.offset as *const _,
+                    );
+                }
+                gl.EnableVertexAttribArray(attribute.location);
+                gl.VertexAttribDivisor(attribute.location, attribute.divisor);
+            }
+        }
+    }
+}
+
 pub struct VertexArray {
     pub id: u32
 }
@@ -43,12 +258,17 @@ impl VertexArray {
         } }
     }
 
-    pub fn bind(&mut self, gl: &GlFns) {
-        gl.BindVertexArray(self.id);
+    pub fn bind(&self, ctx: &GlContext) {
+        ctx.bind_vertex_array(self.id);
+    }
+
+    pub fn unbind(ctx: &GlContext) {
+        ctx.bind_vertex_array(0);
     }
 
-    pub fn unbind(gl: &GlFns) {
-        gl.BindVertexArray(0);
+    /// name the vertex array in debug messages and graphics debuggers.
+    pub fn set_label(&self, ctx: &GlContext, label: &str) {
+        ctx.label(gl33::GL_VERTEX_ARRAY, self.id, label);
     }
 }
 
@@ -61,6 +281,30 @@ pub struct Buffer<T> {
 pub enum BufferType {
     Vertex,
     Element,
+    Uniform,
+}
+
+/// hint for how often the contents of a buffer change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferUsage {
+    /// uploaded once, drawn many times
+    Static,
+
+    /// updated occasionally
+    Dynamic,
+
+    /// rewritten every frame
+    Stream,
+}
+
+impl BufferUsage {
+    fn gl(self) -> GLenum {
+        match self {
+            BufferUsage::Static => gl33::GL_STATIC_DRAW,
+            BufferUsage::Dynamic => gl33::GL_DYNAMIC_DRAW,
+            BufferUsage::Stream => gl33::GL_STREAM_DRAW,
+        }
+    }
 }
 
 impl<T> Buffer<T> {
@@ -68,6 +312,7 @@ impl<T> Buffer<T> {
         match self.type_ {
             BufferType::Vertex => gl33::GL_ARRAY_BUFFER,
             BufferType::Element => gl33::GL_ELEMENT_ARRAY_BUFFER,
+            BufferType::Uniform => gl33::GL_UNIFORM_BUFFER,
         }
     }
 
@@ -87,25 +332,65 @@ impl<T> Buffer<T> {
         Self::new(gl, BufferType::Element)
     }
 
-    pub fn bind(&self, gl: &GlFns) {
+    pub fn new_uniform(gl: &GlFns) -> Self {
+        Self::new(gl, BufferType::Uniform)
+    }
+
+    pub fn bind(&self, ctx: &GlContext) {
+        ctx.bind_buffer(self.target(), self.id);
+    }
+
+    pub fn unbind(&self, ctx: &GlContext) {
+        ctx.bind_buffer(self.target(), 0);
+    }
+
+    /// name the buffer in debug messages and graphics debuggers.
+    pub fn set_label(&self, ctx: &GlContext, label: &str) {
+        ctx.label(gl33::GL_BUFFER, self.id, label);
+    }
+
+    /// bind the whole buffer to an indexed binding point,
+    /// e.g. the one a uniform block reads from.
+    pub fn bind_base(&self, ctx: &GlContext, index: u32) {
+        ctx.bind_buffer_base(self.target(), index, self.id);
+    }
+
+    pub fn data(&mut self, gl: &GlFns, data: &[T]) {
+        self.data_with_usage(gl, data, BufferUsage::Static);
+    }
+
+    pub fn data_with_usage(&mut self, gl: &GlFns, data: &[T], usage: BufferUsage) {
         unsafe {
-            gl.BindBuffer(self.target(), self.id);
+            gl.BufferData(
+                self.target(),
+                std::mem::size_of_val(data) as isize,
+                data.as_ptr().cast(),
+                usage.gl(),
+            );
         }
     }
 
-    pub fn unbind(&self, gl: &GlFns) {
+    /// allocate `capacity` elements of uninitialized storage,
+    /// orphaning the previous storage of the bound buffer.
+    pub fn allocate(&mut self, gl: &GlFns, capacity: usize, usage: BufferUsage) {
         unsafe {
-            gl.BindBuffer(self.target(), 0);
+            gl.BufferData(
+                self.target(),
+                (capacity * std::mem::size_of::<T>()) as isize,
+                std::ptr::null(),
+                usage.gl(),
+            );
         }
     }
 
-    pub fn data(&mut self, gl: &GlFns, data: &[T]) {
+    /// overwrite part of the bound buffer, starting at element `offset`.
+    pub fn sub_data(&mut self, gl: &GlFns, offset: usize, data: &[T]) {
         unsafe {
-            gl.BufferData(
+            gl.BufferSubData(
                 self.target(),
+                (offset * std::mem::size_of::<T>()) as isize,
                 std::mem::size_of_val(data) as isize,
                 data.as_ptr().cast(),
-                gl33::GL_STATIC_DRAW,
             );
         }
     }
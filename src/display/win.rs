@@ -1,13 +1,15 @@
-use std::{cell::{Cell, RefCell}, ffi::CStr, rc::Rc};
+use std::{cell::{Cell, RefCell}, ffi::CStr, ops::{Deref, DerefMut}, path::{Path, PathBuf}, rc::Rc};
 
 use gl33::GlFns;
-use glfw::{fail_on_errors, Context, Glfw, GlfwReceiver, PWindow, WindowEvent, WindowHint};
+use glfw::{fail_on_errors, Context, Glfw, GlfwReceiver, Monitor, PWindow, PixelImage, SwapInterval, VidMode, WindowEvent, WindowHint, WindowMode};
+use serde::{Deserialize, Serialize};
 use crate::*;
 
 pub const GLFW_VERSION_MAJOR: u32 = 3;
 pub const GLFW_VERSION_MINOR: u32 = 3;
 
-pub fn initialize_glfw() -> Result<Glfw> {
+/// initialize glfw, asking for a debug context if `debug_context`.
+pub fn initialize_glfw(debug_context: bool) -> Result<Glfw> {
     log::debug!("Initializing GLFW...");
     let mut glfw = glfw::init(fail_on_errors!())?;
     log::info!("Initialized GLFW.");
@@ -24,21 +26,293 @@ pub fn initialize_glfw() -> Result<Glfw> {
     glfw.window_hint(WindowHint::ContextVersionMajor(GLFW_VERSION_MAJOR));
     glfw.window_hint(WindowHint::ContextVersionMinor(GLFW_VERSION_MINOR));
     glfw.window_hint(WindowHint::OpenGlProfile(glfw::OpenGlProfileHint::Core));
-    log::debug!("Set GLFW window hints.");
+    glfw.window_hint(WindowHint::OpenGlDebugContext(debug_context));
+    log::debug!("Set GLFW window hints. (debug context = {})", debug_context);
 
     Ok(glfw)
 }
 
-pub struct GlfwCreateWindowProps {
+/// a video mode of a monitor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VideoMode {
     pub width: u32,
     pub height: u32,
-    pub title: &'static str,
-    pub mode: glfw::WindowMode<'static>,
+    pub refresh_rate: u32,
 }
 
-pub fn initialize_window(glfw: &mut Glfw, props: GlfwCreateWindowProps) -> Result<(PWindow, GlfwReceiver<(f64, WindowEvent)>)> {
+impl From<VidMode> for VideoMode {
+    fn from(mode: VidMode) -> Self {
+        Self { width: mode.width, height: mode.height, refresh_rate: mode.refresh_rate }
+    }
+}
+
+/// a connected monitor, as listed by `monitors`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorInfo {
+    /// the index to refer to the monitor by in a `DisplayMode`,
+    /// 0 being the primary monitor
+    pub index: usize,
+    pub name: String,
+
+    /// the position of the monitor on the virtual desktop
+    pub position: (i32, i32),
+
+    /// the physical size in millimetres
+    pub physical_size: (i32, i32),
+
+    /// the ratio of pixels to screen coordinates
+    pub content_scale: (f32, f32),
+
+    pub current_mode: Option<VideoMode>,
+    pub video_modes: Vec<VideoMode>,
+}
+
+/// list the connected monitors, the primary one first.
+pub fn monitors(glfw: &mut Glfw) -> Vec<MonitorInfo> {
+    glfw.with_connected_monitors(|_, monitors| {
+        monitors
+            .iter()
+            .enumerate()
+            .map(|(index, monitor)| MonitorInfo {
+                index,
+                name: monitor.get_name().unwrap_or_default(),
+                position: monitor.get_pos(),
+                physical_size: monitor.get_physical_size(),
+                content_scale: monitor.get_content_scale(),
+                current_mode: monitor.get_video_mode().map(VideoMode::from),
+                video_modes: monitor.get_video_modes().into_iter().map(VideoMode::from).collect(),
+            })
+            .collect()
+    })
+}
+
+/// how a window occupies the screen.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DisplayMode {
+    #[default]
+    Windowed,
+
+    /// exclusive fullscreen on the monitor with the given index,
+    /// switching it to `video_mode` unless that is `None`
+    Fullscreen { monitor: usize, video_mode: Option<VideoMode> },
+
+    /// an undecorated window covering the monitor with the given index
+    Borderless { monitor: usize },
+}
+
+/// how a window is created.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowSettings {
+    pub title: String,
+
+    /// the size in screen coordinates while windowed
+    pub width: u32,
+    pub height: u32,
+
+    pub mode: DisplayMode,
+    pub vsync: bool,
+    pub resizable: bool,
+    pub decorated: bool,
+
+    /// let the framebuffer's alpha blend with what is behind the window
+    pub transparent: bool,
+
+    /// scale the window size by the monitor's content scale
+    pub scale_to_monitor: bool,
+
+    /// show the window, which hidden still renders offscreen
+    pub visible: bool,
+
+    /// an image file to use as the window icon
+    pub icon: Option<PathBuf>,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            title: "ferra".to_string(),
+            width: 800,
+            height: 600,
+            mode: DisplayMode::Windowed,
+            vsync: true,
+            resizable: true,
+            decorated: true,
+            transparent: false,
+            scale_to_monitor: true,
+            visible: true,
+            icon: None,
+        }
+    }
+}
+
+/// a glfw window with the state needed to switch display modes.
+///
+/// derefs to the glfw window for everything else.
+pub struct Window {
+    window: PWindow,
+    mode: DisplayMode,
+    vsync: bool,
+    decorated: bool,
+
+    /// position and size to restore when going back to windowed
+    windowed: (i32, i32, i32, i32),
+}
+
+impl Deref for Window {
+    type Target = PWindow;
+
+    fn deref(&self) -> &Self::Target {
+        &self.window
+    }
+}
+
+impl DerefMut for Window {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.window
+    }
+}
+
+impl Window {
+    pub fn display_mode(&self) -> DisplayMode {
+        self.mode
+    }
+
+    pub fn vsync(&self) -> bool {
+        self.vsync
+    }
+
+    /// the size of the framebuffer in pixels, which differs from
+    /// the window size by the content scale.
+    pub fn framebuffer_size(&self) -> (u32, u32) {
+        let (width, height) = self.window.get_framebuffer_size();
+        (width.max(0) as u32, height.max(0) as u32)
+    }
+
+    /// wait for the vertical blank when swapping buffers, or not.
+    /// the window's context has to be current.
+    pub fn set_vsync(&mut self, glfw: &mut Glfw, vsync: bool) {
+        log::debug!("Setting vsync... (vsync = {})", vsync);
+        glfw.set_swap_interval(if vsync { SwapInterval::Sync(1) } else { SwapInterval::None });
+        self.vsync = vsync;
+    }
+
+    /// move the window between windowed, fullscreen and borderless.
+    /// a monitor that is not connected falls back to the primary one.
+    pub fn set_display_mode(&mut self, glfw: &mut Glfw, mode: DisplayMode) {
+        log::debug!("Setting display mode... ({:?} -> {:?})", self.mode, mode);
+
+        if self.mode == DisplayMode::Windowed {
+            let (x, y) = self.window.get_pos();
+            let (width, height) = self.window.get_size();
+            self.windowed = (x, y, width, height);
+        }
+
+        let window = &mut self.window;
+        let applied = match mode {
+            DisplayMode::Windowed => {
+                let (x, y, width, height) = self.windowed;
+                window.set_monitor(WindowMode::Windowed, x, y, width as u32, height as u32, None);
+                window.set_decorated(self.decorated);
+                true
+            },
+            DisplayMode::Fullscreen { monitor, video_mode } => glfw.with_connected_monitors(|_, monitors| {
+                let Some(monitor) = pick_monitor(monitors, monitor) else { return false };
+                let Some(video_mode) = video_mode.or_else(|| monitor.get_video_mode().map(VideoMode::from)) else {
+                    return false;
+                };
+
+                window.set_monitor(
+                    WindowMode::FullScreen(monitor),
+                    0,
+                    0,
+                    video_mode.width,
+                    video_mode.height,
+                    Some(video_mode.refresh_rate),
+                );
+                true
+            }),
+            DisplayMode::Borderless { monitor } => glfw.with_connected_monitors(|_, monitors| {
+                let Some(monitor) = pick_monitor(monitors, monitor) else { return false };
+                let Some(video_mode) = monitor.get_video_mode() else { return false };
+                let (x, y) = monitor.get_pos();
+
+                window.set_decorated(false);
+                window.set_monitor(WindowMode::Windowed, x, y, video_mode.width, video_mode.height, None);
+                true
+            }),
+        };
+
+        if applied {
+            self.mode = mode;
+            log::info!("Set display mode. ({:?})", mode);
+        } else {
+            log::warn!("No monitor to show the window on. (mode = {:?})", mode);
+        }
+    }
+
+    /// switch between windowed and fullscreen on the monitor
+    /// the window is mostly on.
+    pub fn toggle_fullscreen(&mut self, glfw: &mut Glfw) {
+        let mode = match self.mode {
+            DisplayMode::Windowed => DisplayMode::Fullscreen { monitor: self.current_monitor(glfw), video_mode: None },
+            _ => DisplayMode::Windowed,
+        };
+        self.set_display_mode(glfw, mode);
+    }
+
+    /// the index of the monitor containing the window's center.
+    pub fn current_monitor(&self, glfw: &mut Glfw) -> usize {
+        let (x, y) = self.window.get_pos();
+        let (width, height) = self.window.get_size();
+        let center = (x + width / 2, y + height / 2);
+
+        monitors(glfw)
+            .iter()
+            .find(|monitor| {
+                monitor.current_mode.is_some_and(|mode| {
+                    let (mx, my) = monitor.position;
+                    (mx..mx + mode.width as i32).contains(&center.0) && (my..my + mode.height as i32).contains(&center.1)
+                })
+            })
+            .map_or(0, |monitor| monitor.index)
+    }
+
+    /// use an image file as the window icon.
+    pub fn set_icon(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        log::debug!("Loading window icon... ({})", path.display());
+
+        let image = image::open(path)?.into_rgba8();
+        let (width, height) = image.dimensions();
+
+        // glfw reads the pixels as rgba bytes
+        let pixels = image.pixels().map(|pixel| u32::from_ne_bytes(pixel.0)).collect();
+        self.window.set_icon_from_pixels(vec![PixelImage { width, height, pixels }]);
+
+        log::debug!("Set window icon. ({}x{})", width, height);
+        Ok(())
+    }
+}
+
+/// the monitor with `index`, or the primary one if it is not connected.
+fn pick_monitor<'a>(monitors: &'a [&'a mut Monitor], index: usize) -> Option<&'a Monitor> {
+    if index >= monitors.len() {
+        log::warn!("Monitor {} is not connected, using the primary one.", index);
+    }
+    monitors.get(index).or(monitors.first()).map(|monitor| &**monitor)
+}
+
+pub fn initialize_window(glfw: &mut Glfw, settings: &WindowSettings) -> Result<(Window, GlfwReceiver<(f64, WindowEvent)>)> {
+    log::debug!("Setting GLFW window hints... (resizable = {}, decorated = {}, transparent = {})", settings.resizable, settings.decorated, settings.transparent);
+    glfw.window_hint(WindowHint::Resizable(settings.resizable));
+    glfw.window_hint(WindowHint::Decorated(settings.decorated));
+    glfw.window_hint(WindowHint::TransparentFramebuffer(settings.transparent));
+    glfw.window_hint(WindowHint::ScaleToMonitor(settings.scale_to_monitor));
+    glfw.window_hint(WindowHint::Visible(settings.visible));
+
     log::debug!("Initializing GLFW window...");
-    let window = glfw.create_window(props.width, props.height, props.title, props.mode);
+    let window = glfw.create_window(settings.width, settings.height, &settings.title, WindowMode::Windowed);
     log::debug!("Initialized GLFW window.");
 
     if window.is_none() {
@@ -52,6 +326,29 @@ pub fn initialize_window(glfw: &mut Glfw, props: GlfwCreateWindowProps) -> Resul
     window.make_current();
     log::debug!("Set GLFW window context.");
 
+    let (x, y) = window.get_pos();
+    let (width, height) = window.get_size();
+    let mut window = Window {
+        window,
+        mode: DisplayMode::Windowed,
+        vsync: settings.vsync,
+        decorated: settings.decorated,
+        windowed: (x, y, width, height),
+    };
+
+    window.set_vsync(glfw, settings.vsync);
+    if settings.mode != DisplayMode::Windowed {
+        window.set_display_mode(glfw, settings.mode);
+    }
+
+    if let Some(icon) = &settings.icon
+        && let Err(err) = window.set_icon(icon)
+    {
+        log::warn!("Failed to set window icon: {}", err);
+    }
+
+    log::info!("Window content scale: {:?}", window.get_content_scale());
+
     Ok((window, events))
 }
 
@@ -82,11 +379,31 @@ pub fn initialize_opengl(window: &mut PWindow) -> Result<GlFns> {
         }
     };
 
+    for (key, name) in [
+        ("GL vendor", gl33::GL_VENDOR),
+        ("GL renderer", gl33::GL_RENDERER),
+        ("GL version", gl33::GL_VERSION),
+        ("GLSL version", gl33::GL_SHADING_LANGUAGE_VERSION),
+    ] {
+        let value = gl_string(&gl, name);
+        log::info!("{}: {}", key, value);
+        common::log::set_crash_info(key, value);
+    }
+
     log::debug!("Initialized OpenGL.");
 
     Ok(gl)
 }
 
+/// a string queried with `glGetString`, empty if there is none.
+fn gl_string(gl: &GlFns, name: gl33::GLenum) -> String {
+    let value = unsafe { gl.GetString(name) };
+    if value.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(value as *const _) }.to_string_lossy().into_owned()
+}
+
 fn callback(_error: glfw::Error, description: String) {
     log::error!("GLFW Error: {}", description);
 }
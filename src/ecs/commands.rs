@@ -0,0 +1,140 @@
+use super::{entity::Entity, world::{Bundle, World}};
+
+type Command = Box<dyn FnOnce(&mut World)>;
+
+/// structural changes recorded by `Commands`, waiting to be applied.
+#[derive(Default)]
+pub struct CommandQueue {
+    commands: Vec<Command>,
+}
+
+impl CommandQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn push(&mut self, command: impl FnOnce(&mut World) + 'static) {
+        self.commands.push(Box::new(command));
+    }
+
+    /// apply the commands in the order they were recorded.
+    pub fn apply(self, world: &mut World) {
+        for command in self.commands {
+            command(world);
+        }
+    }
+}
+
+/// defers spawning, despawning, inserting and removing until
+/// the world can be borrowed mutably again, e.g. after a system ran.
+pub struct Commands<'w> {
+    world: &'w World,
+    queue: CommandQueue,
+}
+
+impl<'w> Commands<'w> {
+    pub fn new(world: &'w World) -> Self {
+        Self { world, queue: CommandQueue::new() }
+    }
+
+    /// the entity is allocated right away, so it can be referred
+    /// to, but its components only appear once the commands are applied.
+    pub fn spawn(&mut self, bundle: impl Bundle + 'static) -> Entity {
+        let entity = self.world.reserve();
+        self.insert_bundle(entity, bundle);
+        entity
+    }
+
+    pub fn despawn(&mut self, entity: Entity) {
+        self.queue.push(move |world| {
+            world.despawn(entity);
+        });
+    }
+
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) {
+        self.queue.push(move |world| world.insert(entity, component));
+    }
+
+    pub fn insert_bundle(&mut self, entity: Entity, bundle: impl Bundle + 'static) {
+        self.queue.push(move |world| world.insert_bundle(entity, bundle));
+    }
+
+    pub fn remove<T: 'static>(&mut self, entity: Entity) {
+        self.queue.push(move |world| {
+            world.remove::<T>(entity);
+        });
+    }
+
+    pub fn insert_resource<R: 'static>(&mut self, resource: R) {
+        self.queue.push(move |world| world.insert_resource(resource));
+    }
+
+    pub fn remove_resource<R: 'static>(&mut self) {
+        self.queue.push(|world| {
+            world.remove_resource::<R>();
+        });
+    }
+
+    /// run arbitrary code with mutable access to the world.
+    pub fn add(&mut self, command: impl FnOnce(&mut World) + 'static) {
+        self.queue.push(command);
+    }
+
+    pub fn into_queue(self) -> CommandQueue {
+        self.queue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Health(i32);
+
+    #[test]
+    fn applies_in_order() {
+        let mut world = World::new();
+        let existing = world.spawn((Health(1),));
+
+        let mut commands = Commands::new(&world);
+        let spawned = commands.spawn((Health(5),));
+        commands.insert(existing, Health(2));
+        commands.remove::<Health>(existing);
+        commands.insert(existing, Health(3));
+        commands.insert_resource(Health(10));
+        let queue = commands.into_queue();
+
+        // reserved right away, filled in once applied
+        assert!(world.is_alive(spawned));
+        assert!(!world.has::<Health>(spawned));
+        assert_eq!(queue.len(), 5);
+
+        queue.apply(&mut world);
+        assert_eq!(*world.get::<Health>(spawned).unwrap(), Health(5));
+        assert_eq!(*world.get::<Health>(existing).unwrap(), Health(3));
+        assert_eq!(*world.resource::<Health>().unwrap(), Health(10));
+    }
+
+    #[test]
+    fn despawns() {
+        let mut world = World::new();
+        let entity = world.spawn((Health(1),));
+
+        let mut commands = Commands::new(&world);
+        commands.despawn(entity);
+        commands.insert(entity, Health(2));
+        commands.into_queue().apply(&mut world);
+
+        assert!(!world.is_alive(entity));
+        assert!(world.storage::<Health>().unwrap().is_empty());
+    }
+}
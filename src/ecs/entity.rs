@@ -0,0 +1,109 @@
+/// a handle to an entity in a `World`.
+///
+/// the generation tells an entity apart from later entities
+/// that reuse its index after it was despawned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+/// hands out entities, reusing the indices of despawned ones.
+#[derive(Debug, Default)]
+pub struct Entities {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+}
+
+impl Entities {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.alive.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn alloc(&mut self) -> Entity {
+        match self.free.pop() {
+            Some(index) => {
+                self.alive[index as usize] = true;
+                Entity { index, generation: self.generations[index as usize] }
+            },
+            None => {
+                let index = self.alive.len() as u32;
+                self.generations.push(0);
+                self.alive.push(true);
+                Entity { index, generation: 0 }
+            },
+        }
+    }
+
+    /// returns false if the entity was already dead.
+    pub fn free(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+
+        let index = entity.index as usize;
+        self.alive[index] = false;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free.push(entity.index);
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        self.alive.get(index).copied().unwrap_or(false) && self.generations[index] == entity.generation
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.alive
+            .iter()
+            .zip(&self.generations)
+            .enumerate()
+            .filter(|(_, (alive, _))| **alive)
+            .map(|(index, (_, &generation))| Entity { index: index as u32, generation })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_indices_with_new_generations() {
+        let mut entities = Entities::new();
+        let a = entities.alloc();
+        let b = entities.alloc();
+        assert_eq!((a.index(), b.index()), (0, 1));
+        assert_eq!(entities.len(), 2);
+
+        assert!(entities.free(a));
+        assert!(!entities.free(a));
+        assert!(!entities.is_alive(a));
+        assert_eq!(entities.len(), 1);
+
+        let c = entities.alloc();
+        assert_eq!(c.index(), a.index());
+        assert_eq!(c.generation(), a.generation() + 1);
+        assert!(entities.is_alive(c));
+        assert!(!entities.is_alive(a));
+        assert_eq!(entities.iter().collect::<Vec<_>>(), vec![c, b]);
+    }
+}
@@ -0,0 +1,226 @@
+use std::{any::type_name, marker::PhantomData};
+
+/// a queue of events of type `T`, kept as a resource.
+///
+/// events live for two updates of the queue, so every reader running
+/// once between updates sees each event exactly once, no matter if it
+/// runs before or after the sender. `World::update_events` updates all
+/// queues added with `World::add_event` and is called once a frame.
+pub struct Events<T> {
+    /// sent before the last update
+    previous: Vec<T>,
+
+    /// sent since the last update
+    current: Vec<T>,
+
+    /// the id of the first event in `previous`
+    previous_start: usize,
+
+    /// the id after the last event taken by `drain` or `clear`, which
+    /// readers skip without having missed them
+    drained_end: usize,
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self { previous: Vec::new(), current: Vec::new(), previous_start: 0, drained_end: 0 }
+    }
+}
+
+impl<T> Events<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+    }
+
+    pub fn extend(&mut self, events: impl IntoIterator<Item = T>) {
+        self.current.extend(events);
+    }
+
+    /// drop the events sent before the last update.
+    pub fn update(&mut self) {
+        self.previous_start += self.previous.len();
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    /// the number of events held.
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// drop all held events without any reader seeing them.
+    pub fn clear(&mut self) {
+        self.drain().for_each(drop);
+    }
+
+    /// take all held events, oldest first, without any reader seeing them.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.previous_start = self.next_id();
+        self.drained_end = self.previous_start;
+        self.previous.drain(..).chain(self.current.drain(..))
+    }
+
+    /// the events sent since the last update.
+    pub fn iter_current(&self) -> impl Iterator<Item = &T> {
+        self.current.iter()
+    }
+
+    /// the id the next event will get.
+    fn next_id(&self) -> usize {
+        self.previous_start + self.len()
+    }
+
+    /// a reader seeing every event still held.
+    pub fn reader(&self) -> EventReader<T> {
+        EventReader { next: Some(self.previous_start), marker: PhantomData }
+    }
+
+    /// a reader seeing only events sent from now on.
+    pub fn reader_current(&self) -> EventReader<T> {
+        EventReader { next: Some(self.next_id()), marker: PhantomData }
+    }
+}
+
+/// a cursor into an `Events<T>` queue, usually kept in a system.
+pub struct EventReader<T> {
+    /// the id of the next event to read, `None` until the first
+    /// read, which sees every event still held
+    next: Option<usize>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for EventReader<T> {
+    fn default() -> Self {
+        Self { next: None, marker: PhantomData }
+    }
+}
+
+impl<T> EventReader<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the events sent since the last read, oldest first.
+    pub fn read<'a>(&mut self, events: &'a Events<T>) -> impl Iterator<Item = &'a T> + use<'a, T> {
+        let missed = self.missed(events);
+        if missed > 0 {
+            log::warn!(
+                "Missed {} events of type `{}`, the reader was not read for two updates.",
+                missed,
+                type_name::<T>()
+            );
+        }
+
+        let skip = self.next.unwrap_or(0).saturating_sub(events.previous_start);
+        self.next = Some(events.next_id());
+
+        let previous = events.previous.iter().skip(skip);
+        let current = events.current.iter().skip(skip.saturating_sub(events.previous.len()));
+        previous.chain(current)
+    }
+
+    /// the number of events dropped by updates before this reader got
+    /// to them. a reader that never read has not missed anything, and
+    /// drained events were taken on purpose.
+    pub fn missed(&self, events: &Events<T>) -> usize {
+        self.next
+            .map(|next| next.max(events.drained_end))
+            .map_or(0, |next| events.previous_start.saturating_sub(next))
+    }
+
+    /// the number of events the next read returns.
+    pub fn len(&self, events: &Events<T>) -> usize {
+        events.next_id() - self.next.unwrap_or(0).max(events.previous_start)
+    }
+
+    pub fn is_empty(&self, events: &Events<T>) -> bool {
+        self.len(events) == 0
+    }
+
+    /// skip the events not read yet.
+    pub fn clear(&mut self, events: &Events<T>) {
+        self.next = Some(events.next_id());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ecs::world::World;
+
+    use super::*;
+
+    fn read(reader: &mut EventReader<u32>, events: &Events<u32>) -> Vec<u32> {
+        reader.read(events).copied().collect()
+    }
+
+    #[test]
+    fn events_live_for_two_updates() {
+        let mut world = World::new();
+        world.add_event::<u32>();
+        world.resource_mut::<Events<u32>>().unwrap().send(1);
+
+        let held = |world: &World| world.resource::<Events<u32>>().unwrap().len();
+        assert_eq!(held(&world), 1);
+        world.update_events();
+        assert_eq!(held(&world), 1);
+        world.update_events();
+        assert_eq!(held(&world), 0);
+    }
+
+    #[test]
+    fn readers_see_each_event_once() {
+        let mut events = Events::new();
+        let mut before = EventReader::new();
+        let mut after = EventReader::new();
+
+        // `before` reads ahead of the sender, `after` behind it
+        assert!(read(&mut before, &events).is_empty());
+        events.send(1);
+        events.send(2);
+        assert_eq!(read(&mut after, &events), [1, 2]);
+        events.update();
+
+        assert_eq!(read(&mut before, &events), [1, 2]);
+        events.send(3);
+        assert_eq!(read(&mut after, &events), [3]);
+        events.update();
+
+        assert_eq!(read(&mut before, &events), [3]);
+        assert!(read(&mut after, &events).is_empty());
+        assert!(read(&mut before, &events).is_empty());
+    }
+
+    #[test]
+    fn counts_missed_events_of_stale_readers() {
+        let mut events = Events::new();
+        let mut stale = EventReader::new();
+        let mut fresh = EventReader::new();
+
+        assert!(read(&mut stale, &events).is_empty());
+        events.send(1);
+        events.send(2);
+        events.update();
+        events.send(3);
+        events.update();
+
+        // only a reader that read before has missed anything
+        assert_eq!(stale.missed(&events), 2);
+        assert_eq!(fresh.missed(&events), 0);
+        assert_eq!(read(&mut fresh, &events), [3]);
+        assert_eq!(read(&mut stale, &events), [3]);
+        assert_eq!(stale.missed(&events), 0);
+
+        events.send(4);
+        events.clear();
+        events.update();
+        events.update();
+        assert_eq!(stale.missed(&events), 0);
+    }
+}
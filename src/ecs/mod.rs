@@ -0,0 +1,29 @@
+/// module for entity handles and their
+/// generational allocator
+pub mod entity;
+
+/// module for sparse set component storage
+pub mod storage;
+
+/// module for the world holding entities,
+/// components and resources
+pub mod world;
+
+/// module for queries over components
+/// with `With` / `Without` filters
+pub mod query;
+
+/// module for deferred structural changes
+pub mod commands;
+
+/// module for double-buffered event
+/// queues and their readers
+pub mod event;
+
+/// module for systems, their declared
+/// access and schedules running them
+pub mod system;
+
+/// module for the built-in transform
+/// and render systems
+pub mod systems;
@@ -0,0 +1,360 @@
+use std::{
+    cell::{Ref, RefMut},
+    marker::PhantomData,
+};
+
+use super::{entity::Entity, storage::SparseSet, system::Access, world::World};
+
+/// something that can be fetched per entity by a `Query`:
+/// `&T`, `&mut T`, `Option<Q>`, `Entity` and tuples of these.
+pub trait QueryParam {
+    /// the storages borrowed for the lifetime of the query
+    type Borrow<'w>;
+
+    type Item<'b>;
+
+    /// `None` if no entity can match, e.g. because a required
+    /// component was never inserted.
+    fn borrow(world: &World) -> Option<Self::Borrow<'_>>;
+
+    /// the entities worth checking, or `None` to check them all.
+    fn candidates<'a>(borrow: &'a Self::Borrow<'_>) -> Option<&'a [Entity]>;
+
+    fn fetch<'b>(borrow: &'b mut Self::Borrow<'_>, entity: Entity) -> Option<Self::Item<'b>>;
+
+    fn access(access: &mut Access);
+}
+
+impl<T: 'static> QueryParam for &T {
+    type Borrow<'w> = Ref<'w, SparseSet<T>>;
+    type Item<'b> = &'b T;
+
+    fn borrow(world: &World) -> Option<Self::Borrow<'_>> {
+        world.storage::<T>()
+    }
+
+    fn candidates<'a>(borrow: &'a Self::Borrow<'_>) -> Option<&'a [Entity]> {
+        Some(borrow.entities())
+    }
+
+    fn fetch<'b>(borrow: &'b mut Self::Borrow<'_>, entity: Entity) -> Option<Self::Item<'b>> {
+        borrow.get(entity)
+    }
+
+    fn access(access: &mut Access) {
+        access.add_read::<T>();
+    }
+}
+
+impl<T: 'static> QueryParam for &mut T {
+    type Borrow<'w> = RefMut<'w, SparseSet<T>>;
+    type Item<'b> = &'b mut T;
+
+    fn borrow(world: &World) -> Option<Self::Borrow<'_>> {
+        world.storage_mut::<T>()
+    }
+
+    fn candidates<'a>(borrow: &'a Self::Borrow<'_>) -> Option<&'a [Entity]> {
+        Some(borrow.entities())
+    }
+
+    fn fetch<'b>(borrow: &'b mut Self::Borrow<'_>, entity: Entity) -> Option<Self::Item<'b>> {
+        borrow.get_mut(entity)
+    }
+
+    fn access(access: &mut Access) {
+        access.add_write::<T>();
+    }
+}
+
+/// matches every entity, yielding `None` where `Q` does not match.
+impl<Q: QueryParam> QueryParam for Option<Q> {
+    type Borrow<'w> = Option<Q::Borrow<'w>>;
+    type Item<'b> = Option<Q::Item<'b>>;
+
+    fn borrow(world: &World) -> Option<Self::Borrow<'_>> {
+        Some(Q::borrow(world))
+    }
+
+    fn candidates<'a>(_borrow: &'a Self::Borrow<'_>) -> Option<&'a [Entity]> {
+        None
+    }
+
+    fn fetch<'b>(borrow: &'b mut Self::Borrow<'_>, entity: Entity) -> Option<Self::Item<'b>> {
+        Some(borrow.as_mut().and_then(|borrow| Q::fetch(borrow, entity)))
+    }
+
+    fn access(access: &mut Access) {
+        Q::access(access);
+    }
+}
+
+impl QueryParam for Entity {
+    type Borrow<'w> = ();
+    type Item<'b> = Entity;
+
+    fn borrow(_world: &World) -> Option<Self::Borrow<'_>> {
+        Some(())
+    }
+
+    fn candidates<'a>(_borrow: &'a Self::Borrow<'_>) -> Option<&'a [Entity]> {
+        None
+    }
+
+    fn fetch<'b>(_borrow: &'b mut Self::Borrow<'_>, entity: Entity) -> Option<Self::Item<'b>> {
+        Some(entity)
+    }
+
+    fn access(_access: &mut Access) {}
+}
+
+macro_rules! tuple_query {
+    ($($name:ident),+) => {
+        #[allow(non_snake_case)]
+        impl<$($name: QueryParam),+> QueryParam for ($($name,)+) {
+            type Borrow<'w> = ($($name::Borrow<'w>,)+);
+            type Item<'b> = ($($name::Item<'b>,)+);
+
+            fn borrow(world: &World) -> Option<Self::Borrow<'_>> {
+                Some(($($name::borrow(world)?,)+))
+            }
+
+            fn candidates<'a>(borrow: &'a Self::Borrow<'_>) -> Option<&'a [Entity]> {
+                let ($($name,)+) = borrow;
+                [$($name::candidates($name)),+].into_iter().flatten().min_by_key(|candidates| candidates.len())
+            }
+
+            fn fetch<'b>(borrow: &'b mut Self::Borrow<'_>, entity: Entity) -> Option<Self::Item<'b>> {
+                let ($($name,)+) = borrow;
+                Some(($($name::fetch($name, entity)?,)+))
+            }
+
+            fn access(access: &mut Access) {
+                $($name::access(access);)+
+            }
+        }
+    };
+}
+
+tuple_query!(A);
+tuple_query!(A, B);
+tuple_query!(A, B, C);
+tuple_query!(A, B, C, D);
+tuple_query!(A, B, C, D, E);
+tuple_query!(A, B, C, D, E, F);
+tuple_query!(A, B, C, D, E, F, G);
+tuple_query!(A, B, C, D, E, F, G, H);
+
+/// restricts the entities a `Query` matches without fetching anything.
+pub trait QueryFilter {
+    type State<'w>;
+
+    fn prepare(world: &World) -> Self::State<'_>;
+
+    fn matches(state: &Self::State<'_>, entity: Entity) -> bool;
+
+    fn access(access: &mut Access);
+}
+
+/// matches entities that have a `T`.
+pub struct With<T>(PhantomData<T>);
+
+/// matches entities that have no `T`.
+pub struct Without<T>(PhantomData<T>);
+
+impl<T: 'static> QueryFilter for With<T> {
+    type State<'w> = Option<Ref<'w, SparseSet<T>>>;
+
+    fn prepare(world: &World) -> Self::State<'_> {
+        world.storage::<T>()
+    }
+
+    fn matches(state: &Self::State<'_>, entity: Entity) -> bool {
+        state.as_ref().is_some_and(|storage| storage.contains(entity))
+    }
+
+    fn access(access: &mut Access) {
+        access.add_read::<T>();
+    }
+}
+
+impl<T: 'static> QueryFilter for Without<T> {
+    type State<'w> = Option<Ref<'w, SparseSet<T>>>;
+
+    fn prepare(world: &World) -> Self::State<'_> {
+        world.storage::<T>()
+    }
+
+    fn matches(state: &Self::State<'_>, entity: Entity) -> bool {
+        state.as_ref().is_none_or(|storage| !storage.contains(entity))
+    }
+
+    fn access(access: &mut Access) {
+        access.add_read::<T>();
+    }
+}
+
+macro_rules! tuple_filter {
+    ($($name:ident),*) => {
+        #[allow(non_snake_case, unused_variables, clippy::unused_unit)]
+        impl<$($name: QueryFilter),*> QueryFilter for ($($name,)*) {
+            type State<'w> = ($($name::State<'w>,)*);
+
+            fn prepare(world: &World) -> Self::State<'_> {
+                ($($name::prepare(world),)*)
+            }
+
+            fn matches(state: &Self::State<'_>, entity: Entity) -> bool {
+                let ($($name,)*) = state;
+                true $(&& $name::matches($name, entity))*
+            }
+
+            fn access(access: &mut Access) {
+                $($name::access(access);)*
+            }
+        }
+    };
+}
+
+tuple_filter!();
+tuple_filter!(A);
+tuple_filter!(A, B);
+tuple_filter!(A, B, C);
+tuple_filter!(A, B, C, D);
+
+/// the entities matching `Q` and `F`, with their storages borrowed
+/// until the query is dropped.
+///
+/// items borrow from the query, so they are visited through
+/// `for_each` or fetched one at a time with `get`.
+pub struct Query<'w, Q: QueryParam, F: QueryFilter = ()> {
+    world: &'w World,
+    borrow: Option<Q::Borrow<'w>>,
+    filter: F::State<'w>,
+}
+
+impl<'w, Q: QueryParam, F: QueryFilter> Query<'w, Q, F> {
+    pub(crate) fn new(world: &'w World) -> Self {
+        Self { world, borrow: Q::borrow(world), filter: F::prepare(world) }
+    }
+
+    pub fn get(&mut self, entity: Entity) -> Option<Q::Item<'_>> {
+        if !F::matches(&self.filter, entity) {
+            return None;
+        }
+        Q::fetch(self.borrow.as_mut()?, entity)
+    }
+
+    pub fn contains(&mut self, entity: Entity) -> bool {
+        self.get(entity).is_some()
+    }
+
+    fn candidates(&self) -> Vec<Entity> {
+        match &self.borrow {
+            Some(borrow) => Q::candidates(borrow).map_or_else(|| self.world.entities(), <[Entity]>::to_vec),
+            None => Vec::new(),
+        }
+    }
+
+    /// the matching entities.
+    pub fn entities(&mut self) -> Vec<Entity> {
+        let mut entities = self.candidates();
+        entities.retain(|&entity| self.contains(entity));
+        entities
+    }
+
+    pub fn count(&mut self) -> usize {
+        self.entities().len()
+    }
+
+    /// the first match, for queries expected to match one entity.
+    pub fn single(&mut self) -> Option<Q::Item<'_>> {
+        let entity = self.candidates().into_iter().find(|&entity| self.contains(entity))?;
+        self.get(entity)
+    }
+
+    pub fn for_each(&mut self, mut f: impl FnMut(Q::Item<'_>)) {
+        for entity in self.candidates() {
+            if let Some(item) = self.get(entity) {
+                f(item);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+
+    #[derive(Debug, PartialEq)]
+    struct Velocity(i32);
+
+    struct Frozen;
+
+    fn sorted<T: Ord>(mut items: Vec<T>) -> Vec<T> {
+        items.sort();
+        items
+    }
+
+    #[test]
+    fn tuples_match_entities_with_every_component() {
+        let mut world = World::new();
+        let a = world.spawn((Position(0), Velocity(1)));
+        let b = world.spawn((Position(10),));
+        let c = world.spawn((Position(20), Velocity(2)));
+
+        world.query::<(&mut Position, &Velocity)>().for_each(|(position, velocity)| position.0 += velocity.0);
+
+        assert_eq!(*world.get::<Position>(a).unwrap(), Position(1));
+        assert_eq!(*world.get::<Position>(b).unwrap(), Position(10));
+        assert_eq!(*world.get::<Position>(c).unwrap(), Position(22));
+        assert_eq!(sorted(world.query::<(Entity, &Velocity)>().entities()), vec![a, c]);
+    }
+
+    #[test]
+    fn filters() {
+        let mut world = World::new();
+        let a = world.spawn((Position(0), Frozen));
+        let b = world.spawn((Position(1),));
+
+        assert_eq!(world.query_filtered::<&Position, With<Frozen>>().entities(), vec![a]);
+        assert_eq!(world.query_filtered::<&Position, Without<Frozen>>().entities(), vec![b]);
+        assert_eq!(world.query_filtered::<&Position, (With<Frozen>, Without<Frozen>)>().count(), 0);
+
+        // without any `Velocity` stored, `Without` matches everything
+        assert_eq!(world.query_filtered::<&Position, Without<Velocity>>().count(), 2);
+        assert_eq!(world.query::<&Velocity>().count(), 0);
+    }
+
+    #[test]
+    fn options_match_every_entity() {
+        let mut world = World::new();
+        let a = world.spawn((Position(0), Velocity(1)));
+        let b = world.spawn((Position(1),));
+
+        let mut query = world.query::<(Entity, Option<&Velocity>)>();
+        let mut items = Vec::new();
+        query.for_each(|(entity, velocity)| items.push((entity, velocity.map(|v| v.0))));
+        assert_eq!(sorted(items), vec![(a, Some(1)), (b, None)]);
+    }
+
+    #[test]
+    fn queries_after_swap_remove() {
+        let mut world = World::new();
+        let entities: Vec<_> = (0..4).map(|i| world.spawn((Position(i),))).collect();
+        world.despawn(entities[0]);
+        world.remove::<Position>(entities[2]);
+
+        let mut query = world.query::<(Entity, &Position)>();
+        assert!(query.get(entities[0]).is_none());
+        assert!(query.get(entities[2]).is_none());
+        assert_eq!(query.get(entities[3]).map(|(_, p)| p.0), Some(3));
+
+        let mut found = Vec::new();
+        query.for_each(|(entity, position)| found.push((entity, position.0)));
+        assert_eq!(sorted(found), vec![(entities[1], 1), (entities[3], 3)]);
+    }
+}
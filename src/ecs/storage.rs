@@ -0,0 +1,159 @@
+use std::any::Any;
+
+use super::entity::Entity;
+
+/// densely packed components of one type, indexed by entity.
+///
+/// lookups go through a sparse array from entity index to the
+/// position in the dense arrays, removal swaps in the last element.
+#[derive(Debug)]
+pub struct SparseSet<T> {
+    sparse: Vec<Option<u32>>,
+    entities: Vec<Entity>,
+    components: Vec<T>,
+}
+
+impl<T> Default for SparseSet<T> {
+    fn default() -> Self {
+        Self { sparse: Vec::new(), entities: Vec::new(), components: Vec::new() }
+    }
+}
+
+impl<T> SparseSet<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    /// the entities with a component, in storage order.
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    fn dense_index(&self, entity: Entity) -> Option<usize> {
+        let dense = (*self.sparse.get(entity.index() as usize)?)? as usize;
+        (self.entities[dense] == entity).then_some(dense)
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.dense_index(entity).is_some()
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.dense_index(entity).map(|dense| &self.components[dense])
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.dense_index(entity).map(|dense| &mut self.components[dense])
+    }
+
+    /// insert or replace the entity's component, returning the previous one.
+    pub fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        let index = entity.index() as usize;
+        if index >= self.sparse.len() {
+            self.sparse.resize(index + 1, None);
+        }
+
+        match self.sparse[index] {
+            Some(dense) => {
+                let dense = dense as usize;
+                let previous = std::mem::replace(&mut self.components[dense], component);
+
+                // a stale slot of an older generation is simply overwritten
+                let stale = std::mem::replace(&mut self.entities[dense], entity) != entity;
+                (!stale).then_some(previous)
+            },
+            None => {
+                self.sparse[index] = Some(self.components.len() as u32);
+                self.entities.push(entity);
+                self.components.push(component);
+                None
+            },
+        }
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let dense = self.dense_index(entity)?;
+        self.sparse[entity.index() as usize] = None;
+
+        self.entities.swap_remove(dense);
+        let component = self.components.swap_remove(dense);
+
+        if let Some(moved) = self.entities.get(dense) {
+            self.sparse[moved.index() as usize] = Some(dense as u32);
+        }
+
+        Some(component)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.entities.iter().copied().zip(&self.components)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.entities.iter().copied().zip(&mut self.components)
+    }
+}
+
+/// type erased access to a `SparseSet`, so the world can
+/// remove a despawned entity from every storage.
+pub(crate) trait AnyStorage: Any {
+    fn remove_entity(&mut self, entity: Entity);
+}
+
+impl<T: 'static> AnyStorage for SparseSet<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::entity::Entities;
+
+    #[test]
+    fn remove_swaps_in_the_last_component() {
+        let mut entities = Entities::new();
+        let [a, b, c] = [entities.alloc(), entities.alloc(), entities.alloc()];
+
+        let mut set = SparseSet::new();
+        set.insert(a, "a");
+        set.insert(b, "b");
+        set.insert(c, "c");
+
+        assert_eq!(set.remove(a), Some("a"));
+        assert_eq!(set.remove(a), None);
+        assert_eq!(set.entities(), &[c, b]);
+        assert_eq!(set.get(c), Some(&"c"));
+        assert_eq!(set.get(b), Some(&"b"));
+
+        assert_eq!(set.remove(b), Some("b"));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![(c, &"c")]);
+    }
+
+    #[test]
+    fn stale_entities_miss_reused_slots() {
+        let mut entities = Entities::new();
+        let old = entities.alloc();
+        entities.free(old);
+        let new = entities.alloc();
+
+        let mut set = SparseSet::new();
+        assert_eq!(set.insert(old, 1), None);
+
+        // the newer generation overwrites the stale component
+        assert_eq!(set.insert(new, 2), None);
+        assert_eq!(set.get(old), None);
+        assert_eq!(set.get(new), Some(&2));
+        assert_eq!(set.insert(new, 3), Some(2));
+        assert_eq!(set.len(), 1);
+    }
+}
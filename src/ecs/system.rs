@@ -0,0 +1,253 @@
+use std::{
+    any::{type_name, TypeId},
+    collections::HashMap,
+};
+
+use crate::Result;
+
+use super::{
+    commands::Commands,
+    query::{QueryFilter, QueryParam},
+    world::World,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum AccessKey {
+    Component(TypeId),
+    Resource(TypeId),
+}
+
+/// the components and resources a system reads and writes.
+/// writing implies reading.
+#[derive(Debug, Clone, Default)]
+pub struct Access {
+    /// type names by key, for reporting undeclared access
+    reads: HashMap<AccessKey, &'static str>,
+    writes: HashMap<AccessKey, &'static str>,
+}
+
+impl Access {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn read<T: 'static>(mut self) -> Self {
+        self.add_read::<T>();
+        self
+    }
+
+    pub fn write<T: 'static>(mut self) -> Self {
+        self.add_write::<T>();
+        self
+    }
+
+    pub fn read_resource<R: 'static>(mut self) -> Self {
+        self.reads.insert(AccessKey::Resource(TypeId::of::<R>()), type_name::<R>());
+        self
+    }
+
+    pub fn write_resource<R: 'static>(mut self) -> Self {
+        self.writes.insert(AccessKey::Resource(TypeId::of::<R>()), type_name::<R>());
+        self
+    }
+
+    /// everything a query with the filter `F` touches.
+    pub fn query<Q: QueryParam, F: QueryFilter>(mut self) -> Self {
+        Q::access(&mut self);
+        F::access(&mut self);
+        self
+    }
+
+    pub(crate) fn add_read<T: 'static>(&mut self) {
+        self.reads.insert(AccessKey::Component(TypeId::of::<T>()), type_name::<T>());
+    }
+
+    pub(crate) fn add_write<T: 'static>(&mut self) {
+        self.writes.insert(AccessKey::Component(TypeId::of::<T>()), type_name::<T>());
+    }
+
+    fn reads(&self, key: &AccessKey) -> bool {
+        self.reads.contains_key(key) || self.writes.contains_key(key)
+    }
+
+    /// whether two systems touch the same data with at least one writing it.
+    pub fn conflicts_with(&self, other: &Access) -> bool {
+        self.writes.keys().any(|key| other.reads(key)) || other.writes.keys().any(|key| self.reads(key))
+    }
+
+    /// the name of something `required` touches that is not declared here.
+    pub(crate) fn missing(&self, required: &Access) -> Option<&'static str> {
+        let write = required.writes.iter().find(|(key, _)| !self.writes.contains_key(key));
+        let read = required.reads.iter().find(|(key, _)| !self.reads(key));
+        write.or(read).map(|(_, name)| *name)
+    }
+}
+
+/// logic run over the world once per schedule run.
+///
+/// systems see the world through a shared reference and defer
+/// structural changes to `Commands`. while a schedule runs a system,
+/// touching components or resources outside its `access` panics.
+pub trait System {
+    fn name(&self) -> &str;
+
+    fn access(&self) -> Access;
+
+    fn run(&mut self, world: &World, commands: &mut Commands) -> Result<()>;
+}
+
+/// a system from a closure.
+pub struct FnSystem<F> {
+    name: String,
+    access: Access,
+    f: F,
+}
+
+impl<F> FnSystem<F>
+where
+    F: FnMut(&World, &mut Commands) -> Result<()>,
+{
+    pub fn new(name: &str, access: Access, f: F) -> Self {
+        Self { name: name.to_string(), access, f }
+    }
+}
+
+impl<F> System for FnSystem<F>
+where
+    F: FnMut(&World, &mut Commands) -> Result<()>,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn access(&self) -> Access {
+        self.access.clone()
+    }
+
+    fn run(&mut self, world: &World, commands: &mut Commands) -> Result<()> {
+        (self.f)(world, commands)
+    }
+}
+
+/// systems run in the order they were added. the commands of
+/// each system are applied before the next one runs, or before
+/// the error of a failing system is returned.
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<Box<dyn System>>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.systems.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+    }
+
+    pub fn add_system(&mut self, system: impl System + 'static) -> &mut Self {
+        log::debug!("Added system `{}` to schedule.", system.name());
+        self.systems.push(Box::new(system));
+        self
+    }
+
+    pub fn run(&mut self, world: &mut World) -> Result<()> {
+        for system in &mut self.systems {
+            let mut commands = Commands::new(world);
+
+            world.begin_system(system.name(), system.access());
+            let result = system.run(world, &mut commands);
+            world.end_system();
+
+            // entities spawned by the commands are reserved already,
+            // so the queue is applied even if the system failed
+            commands.into_queue().apply(world);
+            result?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ecs::entity::Entity, Error};
+
+    #[derive(Debug, PartialEq)]
+    struct Counter(u32);
+
+    struct Marker;
+
+    #[test]
+    fn applies_commands_between_systems() {
+        let mut world = World::new();
+        world.insert_resource(Counter(0));
+
+        let mut schedule = Schedule::new();
+        schedule
+            .add_system(FnSystem::new("spawn", Access::new(), |_, commands| {
+                commands.spawn((Marker,));
+                Ok(())
+            }))
+            .add_system(FnSystem::new(
+                "count",
+                Access::new().read::<Marker>().write_resource::<Counter>(),
+                |world, _| {
+                    let count = world.query::<&Marker>().count() as u32;
+                    world.resource_mut::<Counter>().unwrap().0 = count;
+                    Ok(())
+                },
+            ));
+
+        schedule.run(&mut world).unwrap();
+        schedule.run(&mut world).unwrap();
+        assert_eq!(*world.resource::<Counter>().unwrap(), Counter(2));
+    }
+
+    #[test]
+    fn applies_commands_of_failing_systems() {
+        let mut world = World::new();
+        let mut spawned = None;
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(FnSystem::new("fail", Access::new(), |_, commands| {
+            commands.spawn((Marker,));
+            Err(Error::Config("failed".to_string()))
+        }));
+
+        assert!(schedule.run(&mut world).is_err());
+        world.query::<(Entity, &Marker)>().for_each(|(entity, _)| spawned = Some(entity));
+
+        let spawned = spawned.expect("the spawned entity got its components");
+        assert_eq!(world.entities(), vec![spawned]);
+    }
+
+    #[test]
+    #[should_panic(expected = "without declaring it")]
+    fn panics_on_undeclared_access() {
+        let mut world = World::new();
+        world.insert_resource(Counter(0));
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(FnSystem::new("sneaky", Access::new().read_resource::<Counter>(), |world, _| {
+            world.resource_mut::<Counter>().unwrap().0 += 1;
+            Ok(())
+        }));
+        let _ = schedule.run(&mut world);
+    }
+
+    #[test]
+    fn conflicts() {
+        let read = Access::new().read::<Counter>();
+        let write = Access::new().write::<Counter>();
+        assert!(!read.conflicts_with(&read));
+        assert!(read.conflicts_with(&write));
+        assert!(write.conflicts_with(&read));
+        assert!(!write.conflicts_with(&Access::new().write_resource::<Counter>()));
+    }
+}
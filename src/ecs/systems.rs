@@ -0,0 +1,349 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use glfw::WindowEvent;
+use nalgebra::{Matrix4, Point3};
+
+use crate::{
+    display::{
+        camera::Camera,
+        context::GlContext,
+        deferred::DeferredRenderer,
+        light::{AmbientLight, Light, LightBuffer, PlacedLight},
+        shadow::{ShadowRenderer, ShadowSettings},
+    },
+    scene::{graph::Renderable, transform::Transform},
+    Result,
+};
+
+use super::{
+    commands::Commands,
+    entity::Entity,
+    event::{EventReader, Events},
+    query::With,
+    system::{Access, System},
+    world::World,
+};
+
+/// the parent of an entity in the transform hierarchy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Parent(pub Entity);
+
+/// the local to world matrix, written by the `TransformSystem`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalTransform(pub Matrix4<f32>);
+
+/// marks the camera the `RenderSystem` draws from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MainCamera;
+
+type TransformQuery<'a> = (Entity, &'a Transform, Option<&'a Parent>);
+
+/// computes a `GlobalTransform` for every entity with a `Transform`,
+/// composing it with the transforms of its `Parent` chain. the
+/// component is inserted where missing.
+#[derive(Debug, Default)]
+pub struct TransformSystem;
+
+impl System for TransformSystem {
+    fn name(&self) -> &str {
+        "transform"
+    }
+
+    fn access(&self) -> Access {
+        Access::new().query::<TransformQuery, ()>().write::<GlobalTransform>()
+    }
+
+    fn run(&mut self, world: &World, commands: &mut Commands) -> Result<()> {
+        let mut locals = HashMap::new();
+        world.query::<TransformQuery>().for_each(|(entity, transform, parent)| {
+            locals.insert(entity, (transform.matrix(), parent.map(|parent| parent.0)));
+        });
+
+        let mut globals = HashMap::with_capacity(locals.len());
+        for &entity in locals.keys() {
+            resolve(entity, &locals, &mut globals);
+        }
+
+        world.query::<(Entity, &mut GlobalTransform)>().for_each(|(entity, global)| {
+            if let Some(matrix) = globals.remove(&entity) {
+                global.0 = matrix;
+            }
+        });
+
+        for (entity, matrix) in globals {
+            commands.insert(entity, GlobalTransform(matrix));
+        }
+
+        Ok(())
+    }
+}
+
+/// the world matrix of `entity`, computing and caching those
+/// of its ancestors on the way. parents without a `Transform`
+/// end the chain.
+fn resolve(
+    entity: Entity,
+    locals: &HashMap<Entity, (Matrix4<f32>, Option<Entity>)>,
+    globals: &mut HashMap<Entity, Matrix4<f32>>,
+) -> Matrix4<f32> {
+    if let Some(global) = globals.get(&entity) {
+        return *global;
+    }
+
+    let mut chain = vec![entity];
+    let mut matrix = Matrix4::identity();
+    let mut parent = locals[&entity].1;
+
+    while let Some(current) = parent {
+        if let Some(global) = globals.get(&current) {
+            matrix = *global;
+            break;
+        }
+
+        let Some(&(_, next)) = locals.get(&current) else { break };
+        if chain.contains(&current) {
+            log::warn!("Transform hierarchy contains a cycle through {:?}.", current);
+            break;
+        }
+
+        chain.push(current);
+        parent = next;
+    }
+
+    for entity in chain.into_iter().rev() {
+        matrix *= locals[&entity].0;
+        globals.insert(entity, matrix);
+    }
+    matrix
+}
+
+/// keeps the viewport of the `MainCamera` in step with the window,
+/// reading the window events forwarded to `Events<WindowEvent>`.
+#[derive(Default)]
+pub struct ViewportSystem {
+    reader: EventReader<WindowEvent>,
+}
+
+impl ViewportSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl System for ViewportSystem {
+    fn name(&self) -> &str {
+        "viewport"
+    }
+
+    fn access(&self) -> Access {
+        Access::new()
+            .query::<&mut Camera, With<MainCamera>>()
+            .read_resource::<Events<WindowEvent>>()
+    }
+
+    fn run(&mut self, world: &World, _commands: &mut Commands) -> Result<()> {
+        let Some(events) = world.resource::<Events<WindowEvent>>() else {
+            return Ok(());
+        };
+
+        let size = self.reader.read(&events).fold(None, |size, event| match *event {
+            WindowEvent::FramebufferSize(width, height) => Some((width, height)),
+            _ => size,
+        });
+
+        if let Some((width, height)) = size.filter(|&(width, height)| width > 0 && height > 0) {
+            world.query_filtered::<&mut Camera, With<MainCamera>>().for_each(|camera| {
+                camera.set_viewport(width as u32, height as u32);
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// which path the `RenderSystem` draws the scene with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RenderPath {
+    /// every renderable is shaded by its own material program
+    #[default]
+    Forward,
+
+    /// renderables with a deferred program go through the g-buffer
+    /// of a `DeferredRenderer`, everything else is drawn forward
+    Deferred,
+}
+
+/// the lights of the current frame with their shadow map layers,
+/// published by the `LightingSystem` for the deferred path.
+#[derive(Default, Clone)]
+pub struct PreparedLights(pub Vec<PlacedLight>);
+
+/// draws every entity with a `GlobalTransform` and a `Renderable`
+/// as seen by the `Camera` marked as `MainCamera`, along the
+/// `RenderPath` resource when given a `DeferredRenderer`.
+pub struct RenderSystem {
+    ctx: Rc<GlContext>,
+    deferred: Option<DeferredRenderer>,
+}
+
+impl RenderSystem {
+    pub fn new(ctx: Rc<GlContext>) -> Self {
+        Self { ctx, deferred: None }
+    }
+
+    pub fn with_deferred(mut self, renderer: DeferredRenderer) -> Self {
+        self.deferred = Some(renderer);
+        self
+    }
+
+    fn render_deferred(&mut self, world: &World, camera: &Camera) -> Result<()> {
+        let Some(renderer) = &mut self.deferred else {
+            return Ok(());
+        };
+
+        let ambient = world.resource::<AmbientLight>().map(|ambient| *ambient).unwrap_or_default();
+        let lights = world.resource::<PreparedLights>().map(|lights| lights.0.clone()).unwrap_or_default();
+
+        let mut renderables = Vec::new();
+        world.query::<(&GlobalTransform, &Renderable)>().for_each(|(global, renderable)| {
+            renderables.push((renderable.clone(), global.0));
+        });
+
+        renderer.render(&self.ctx, camera, &ambient, &lights, &renderables)
+    }
+}
+
+impl System for RenderSystem {
+    fn name(&self) -> &str {
+        "render"
+    }
+
+    fn access(&self) -> Access {
+        Access::new()
+            .query::<&Camera, With<MainCamera>>()
+            .query::<(&GlobalTransform, &Renderable), ()>()
+            .read_resource::<RenderPath>()
+            .read_resource::<PreparedLights>()
+            .read_resource::<AmbientLight>()
+    }
+
+    fn run(&mut self, world: &World, _commands: &mut Commands) -> Result<()> {
+        let Some(camera) = world.query_filtered::<&Camera, With<MainCamera>>().single().cloned() else {
+            return Ok(());
+        };
+
+        let path = world.resource::<RenderPath>().map(|path| *path).unwrap_or_default();
+        if path == RenderPath::Deferred && self.deferred.is_some() {
+            return self.render_deferred(world, &camera);
+        }
+
+        let view = camera.view_matrix();
+        let projection = camera.projection_matrix();
+
+        let mut result = Ok(());
+        world.query::<(&GlobalTransform, &Renderable)>().for_each(|(global, renderable)| {
+            if result.is_ok() {
+                result = renderable.draw(&self.ctx, &global.0, &view, &projection);
+            }
+        });
+        result
+    }
+}
+
+/// renders the shadow maps and uploads every `Light` with a
+/// `GlobalTransform`, the `AmbientLight` resource and the position
+/// of the `MainCamera` to the lights uniform block, and publishes
+/// them as `PreparedLights`. shadows follow the `ShadowSettings`
+/// resource. runs before the `RenderSystem`.
+pub struct LightingSystem {
+    ctx: Rc<GlContext>,
+    buffer: LightBuffer,
+    shadows: Rc<RefCell<ShadowRenderer>>,
+}
+
+impl LightingSystem {
+    pub fn new(ctx: Rc<GlContext>, max_lights: usize) -> Result<Self> {
+        let buffer = LightBuffer::new(&ctx, max_lights);
+        let shadows = Rc::new(RefCell::new(ShadowRenderer::new(&ctx)?));
+        Ok(Self { ctx, buffer, shadows })
+    }
+
+    /// a system drawing the shadow maps of this one on top of
+    /// the frame, to be scheduled after the `RenderSystem`.
+    pub fn shadow_debug(&self) -> ShadowDebugSystem {
+        ShadowDebugSystem { ctx: self.ctx.clone(), shadows: self.shadows.clone() }
+    }
+}
+
+impl System for LightingSystem {
+    fn name(&self) -> &str {
+        "lighting"
+    }
+
+    fn access(&self) -> Access {
+        Access::new()
+            .query::<&Camera, With<MainCamera>>()
+            .query::<(&Light, &GlobalTransform), ()>()
+            .query::<(&GlobalTransform, &Renderable), ()>()
+            .read_resource::<AmbientLight>()
+            .read_resource::<ShadowSettings>()
+            .write_resource::<PreparedLights>()
+    }
+
+    fn run(&mut self, world: &World, commands: &mut Commands) -> Result<()> {
+        let camera = world
+            .query_filtered::<&Camera, With<MainCamera>>()
+            .single()
+            .cloned();
+        let camera_position = camera.as_ref().map_or_else(Point3::origin, |camera| camera.position());
+
+        let ambient = world.resource::<AmbientLight>().map(|ambient| *ambient).unwrap_or_default();
+        let settings = world.resource::<ShadowSettings>().map(|settings| settings.clone()).unwrap_or_default();
+
+        let mut lights = Vec::new();
+        world.query::<(&Light, &GlobalTransform)>().for_each(|(light, global)| {
+            lights.push(PlacedLight::new(*light, global.0));
+        });
+
+        let mut casters = Vec::new();
+        if lights.iter().any(|placed| placed.light.cast_shadows) {
+            world.query::<(&GlobalTransform, &Renderable)>().for_each(|(global, renderable)| {
+                casters.push((renderable.mesh.clone(), global.0));
+            });
+        }
+
+        self.shadows.borrow_mut().render(&self.ctx, &settings, camera.as_ref(), &mut lights, &casters)?;
+
+        self.buffer.update(&self.ctx, &ambient, &camera_position, &lights);
+        self.buffer.bind(&self.ctx);
+
+        commands.insert_resource(PreparedLights(lights));
+
+        Ok(())
+    }
+}
+
+/// draws the shadow maps of a `LightingSystem` in the lower left
+/// corner while `ShadowSettings::debug_view` is set.
+pub struct ShadowDebugSystem {
+    ctx: Rc<GlContext>,
+    shadows: Rc<RefCell<ShadowRenderer>>,
+}
+
+impl System for ShadowDebugSystem {
+    fn name(&self) -> &str {
+        "shadow debug"
+    }
+
+    fn access(&self) -> Access {
+        Access::new().read_resource::<ShadowSettings>()
+    }
+
+    fn run(&mut self, world: &World, _commands: &mut Commands) -> Result<()> {
+        let enabled = world.resource::<ShadowSettings>().is_some_and(|settings| settings.debug_view);
+        if enabled {
+            self.shadows.borrow().draw_debug(&self.ctx)?;
+        }
+        Ok(())
+    }
+}
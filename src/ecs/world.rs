@@ -0,0 +1,309 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    cell::{Ref, RefCell, RefMut},
+    collections::HashMap,
+};
+
+use super::{
+    entity::{Entities, Entity},
+    event::Events,
+    query::{Query, QueryFilter, QueryParam},
+    storage::{AnyStorage, SparseSet},
+    system::Access,
+};
+
+/// a set of components inserted together, implemented for tuples.
+pub trait Bundle {
+    fn insert_into(self, world: &mut World, entity: Entity);
+}
+
+macro_rules! tuple_bundle {
+    ($($name:ident),*) => {
+        impl<$($name: 'static),*> Bundle for ($($name,)*) {
+            #[allow(non_snake_case, unused_variables)]
+            fn insert_into(self, world: &mut World, entity: Entity) {
+                let ($($name,)*) = self;
+                $(world.insert(entity, $name);)*
+            }
+        }
+    };
+}
+
+tuple_bundle!();
+tuple_bundle!(A);
+tuple_bundle!(A, B);
+tuple_bundle!(A, B, C);
+tuple_bundle!(A, B, C, D);
+tuple_bundle!(A, B, C, D, E);
+tuple_bundle!(A, B, C, D, E, F);
+tuple_bundle!(A, B, C, D, E, F, G);
+tuple_bundle!(A, B, C, D, E, F, G, H);
+
+/// updates the `Events` resource of one event type.
+type EventUpdater = fn(&mut World);
+
+/// the system currently run by a `Schedule` and what it declared.
+struct ActiveSystem {
+    name: String,
+    access: Access,
+}
+
+/// entities with their components, plus global resources.
+///
+/// components and resources live in `RefCell`s, so systems can
+/// borrow several of them through a shared reference. borrowing
+/// the same type mutably twice at once panics. structural changes
+/// (spawning, despawning, inserting and removing) need `&mut self`
+/// or are deferred through `Commands`.
+#[derive(Default)]
+pub struct World {
+    entities: RefCell<Entities>,
+    storages: HashMap<TypeId, RefCell<Box<dyn AnyStorage>>>,
+    resources: HashMap<TypeId, RefCell<Box<dyn Any>>>,
+    active: RefCell<Option<ActiveSystem>>,
+
+    /// updates the `Events` queue of every added event type
+    event_updaters: Vec<(TypeId, EventUpdater)>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// all living entities.
+    pub fn entities(&self) -> Vec<Entity> {
+        self.entities.borrow().iter().collect()
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.entities.borrow().is_alive(entity)
+    }
+
+    pub fn spawn(&mut self, bundle: impl Bundle) -> Entity {
+        let entity = self.entities.get_mut().alloc();
+        bundle.insert_into(self, entity);
+        entity
+    }
+
+    /// allocate an entity without components through a shared
+    /// reference, used by `Commands` to hand out spawned entities early.
+    pub fn reserve(&self) -> Entity {
+        self.entities.borrow_mut().alloc()
+    }
+
+    /// returns false if the entity was already dead.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.entities.get_mut().free(entity) {
+            return false;
+        }
+
+        for storage in self.storages.values_mut() {
+            storage.get_mut().remove_entity(entity);
+        }
+        true
+    }
+
+    /// insert or replace a component. components of dead entities are dropped.
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) {
+        if !self.is_alive(entity) {
+            log::warn!("Ignoring {} inserted into dead entity {:?}.", type_name::<T>(), entity);
+            return;
+        }
+
+        let storage = self
+            .storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| RefCell::new(Box::new(SparseSet::<T>::new())));
+
+        downcast_mut::<T>(&mut **storage.get_mut()).insert(entity, component);
+    }
+
+    pub fn insert_bundle(&mut self, entity: Entity, bundle: impl Bundle) {
+        bundle.insert_into(self, entity);
+    }
+
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        let storage = self.storages.get_mut(&TypeId::of::<T>())?;
+        downcast_mut::<T>(&mut **storage.get_mut()).remove(entity)
+    }
+
+    pub fn has<T: 'static>(&self, entity: Entity) -> bool {
+        self.storage::<T>().is_some_and(|storage| storage.contains(entity))
+    }
+
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<Ref<'_, T>> {
+        self.check_access(&Access::new().read::<T>());
+        Ref::filter_map(self.storage::<T>()?, |storage| storage.get(entity)).ok()
+    }
+
+    pub fn get_mut<T: 'static>(&self, entity: Entity) -> Option<RefMut<'_, T>> {
+        self.check_access(&Access::new().write::<T>());
+        RefMut::filter_map(self.storage_mut::<T>()?, |storage| storage.get_mut(entity)).ok()
+    }
+
+    /// the storage of a component type, if any component of it was inserted.
+    pub fn storage<T: 'static>(&self) -> Option<Ref<'_, SparseSet<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?.borrow();
+        Some(Ref::map(storage, |storage| downcast_ref::<T>(&**storage)))
+    }
+
+    pub fn storage_mut<T: 'static>(&self) -> Option<RefMut<'_, SparseSet<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?.borrow_mut();
+        Some(RefMut::map(storage, |storage| downcast_mut::<T>(&mut **storage)))
+    }
+
+    pub fn query<Q: QueryParam>(&self) -> Query<'_, Q> {
+        self.query_filtered::<Q, ()>()
+    }
+
+    pub fn query_filtered<Q: QueryParam, F: QueryFilter>(&self) -> Query<'_, Q, F> {
+        self.check_access(&Access::new().query::<Q, F>());
+        Query::new(self)
+    }
+
+    /// insert or replace the resource of type `R`.
+    pub fn insert_resource<R: 'static>(&mut self, resource: R) {
+        self.resources.insert(TypeId::of::<R>(), RefCell::new(Box::new(resource)));
+    }
+
+    pub fn remove_resource<R: 'static>(&mut self) -> Option<R> {
+        let resource = self.resources.remove(&TypeId::of::<R>())?.into_inner();
+        resource.downcast().ok().map(|resource| *resource)
+    }
+
+    pub fn has_resource<R: 'static>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<R>())
+    }
+
+    pub fn resource<R: 'static>(&self) -> Option<Ref<'_, R>> {
+        self.check_access(&Access::new().read_resource::<R>());
+        let resource = self.resources.get(&TypeId::of::<R>())?.borrow();
+        Ref::filter_map(resource, |resource| resource.downcast_ref()).ok()
+    }
+
+    pub fn resource_mut<R: 'static>(&self) -> Option<RefMut<'_, R>> {
+        self.check_access(&Access::new().write_resource::<R>());
+        let resource = self.resources.get(&TypeId::of::<R>())?.borrow_mut();
+        RefMut::filter_map(resource, |resource| resource.downcast_mut()).ok()
+    }
+
+    /// add an `Events<T>` resource, updated by `update_events`.
+    pub fn add_event<T: 'static>(&mut self) {
+        if self.event_updaters.iter().any(|(id, _)| *id == TypeId::of::<T>()) {
+            return;
+        }
+
+        log::debug!("Added event `{}`.", type_name::<T>());
+        if !self.has_resource::<Events<T>>() {
+            self.insert_resource(Events::<T>::new());
+        }
+        self.event_updaters.push((TypeId::of::<T>(), |world| {
+            if let Some(mut events) = world.resource_mut::<Events<T>>() {
+                events.update();
+            }
+        }));
+    }
+
+    /// queue an event for the readers of `Events<T>`. events of
+    /// types never added are dropped.
+    pub fn send_event<T: 'static>(&self, event: T) {
+        match self.resource_mut::<Events<T>>() {
+            Some(mut events) => events.send(event),
+            None => log::warn!("Dropped event of type `{}`, it was never added.", type_name::<T>()),
+        }
+    }
+
+    /// update the queues of all added events, dropping those sent
+    /// before the last update. to be called once a frame.
+    pub fn update_events(&mut self) {
+        for (_, update) in self.event_updaters.clone() {
+            update(self);
+        }
+    }
+
+    pub(crate) fn begin_system(&self, name: &str, access: Access) {
+        *self.active.borrow_mut() = Some(ActiveSystem { name: name.to_string(), access });
+    }
+
+    pub(crate) fn end_system(&self) {
+        *self.active.borrow_mut() = None;
+    }
+
+    /// panics if a system run by a schedule touches data
+    /// it did not declare in its access.
+    fn check_access(&self, required: &Access) {
+        if let Some(active) = self.active.borrow().as_ref()
+            && let Some(name) = active.access.missing(required)
+        {
+            panic!("System `{}` accessed {} without declaring it.", active.name, name);
+        }
+    }
+}
+
+fn downcast_ref<T: 'static>(storage: &dyn AnyStorage) -> &SparseSet<T> {
+    (storage as &dyn Any).downcast_ref().expect("component storage of the wrong type")
+}
+
+fn downcast_mut<T: 'static>(storage: &mut dyn AnyStorage) -> &mut SparseSet<T> {
+    (storage as &mut dyn Any).downcast_mut().expect("component storage of the wrong type")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+
+    #[derive(Debug, PartialEq)]
+    struct Name(&'static str);
+
+    #[test]
+    fn spawn_despawn_and_reuse() {
+        let mut world = World::new();
+        let a = world.spawn((Position(1), Name("a")));
+        let b = world.spawn((Position(2),));
+        assert_eq!(world.len(), 2);
+
+        assert!(world.despawn(a));
+        assert!(!world.despawn(a));
+        assert!(!world.is_alive(a));
+        assert!(world.get::<Position>(a).is_none());
+        assert!(!world.has::<Name>(a));
+
+        let c = world.spawn((Position(3),));
+        assert_eq!(c.index(), a.index());
+        assert!(world.get::<Position>(a).is_none());
+        assert_eq!(*world.get::<Position>(c).unwrap(), Position(3));
+        assert_eq!(*world.get::<Position>(b).unwrap(), Position(2));
+        assert!(!world.has::<Name>(c));
+    }
+
+    #[test]
+    fn ignores_components_of_dead_entities() {
+        let mut world = World::new();
+        let a = world.spawn(());
+        world.despawn(a);
+        world.insert(a, Position(1));
+        assert!(world.storage::<Position>().is_none());
+    }
+
+    #[test]
+    fn resources() {
+        let mut world = World::new();
+        world.insert_resource(Position(1));
+        world.resource_mut::<Position>().unwrap().0 += 1;
+        assert_eq!(*world.resource::<Position>().unwrap(), Position(2));
+        assert_eq!(world.remove_resource::<Position>(), Some(Position(2)));
+        assert!(!world.has_resource::<Position>());
+    }
+}
@@ -0,0 +1,133 @@
+use std::{collections::HashMap, path::Path};
+
+use glfw::{GamepadAxis, GamepadButton, Key, MouseButton};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+use super::state::Input;
+
+/// something that can be held, e.g. `Key(Space)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(Key),
+    Mouse(MouseButton),
+
+    /// a button of any connected gamepad
+    Gamepad(GamepadButton),
+}
+
+/// a source of an axis value.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AxisBinding {
+    /// -1 while `negative` is held, 1 while `positive` is, 0 for both
+    Buttons { negative: Binding, positive: Binding },
+
+    /// the horizontal cursor movement in screen coordinates, scaled
+    CursorX(f32),
+    CursorY(f32),
+
+    /// the scroll offset, scaled
+    ScrollX(f32),
+    ScrollY(f32),
+
+    /// an axis of the gamepad deflecting it the most, scaled
+    Gamepad(GamepadAxis, f32),
+}
+
+impl AxisBinding {
+    pub fn value(&self, input: &Input) -> f32 {
+        match *self {
+            AxisBinding::Buttons { negative, positive } => {
+                input.binding_pressed(&positive) as i32 as f32 - input.binding_pressed(&negative) as i32 as f32
+            },
+            AxisBinding::CursorX(scale) => input.cursor_delta().x * scale,
+            AxisBinding::CursorY(scale) => input.cursor_delta().y * scale,
+            AxisBinding::ScrollX(scale) => input.scroll().x * scale,
+            AxisBinding::ScrollY(scale) => input.scroll().y * scale,
+            AxisBinding::Gamepad(axis, scale) => input.gamepads().axis(axis) * scale,
+        }
+    }
+}
+
+/// named actions and axes with the bindings driving them, e.g.
+///
+/// ```ron
+/// (
+///     actions: {
+///         "jump": [Key(Space), Gamepad(ButtonA)],
+///         "fire": [Mouse(Button1), Key(LeftControl)],
+///     },
+///     axes: {
+///         "move_x": [
+///             Buttons(negative: Key(A), positive: Key(D)),
+///             Gamepad(AxisLeftX, 1.0),
+///         ],
+///         "look_x": [CursorX(0.1)],
+///     },
+/// )
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputMap {
+    pub actions: HashMap<String, Vec<Binding>>,
+    pub axes: HashMap<String, Vec<AxisBinding>>,
+}
+
+impl InputMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add a binding to an action.
+    pub fn bind(mut self, action: &str, binding: Binding) -> Self {
+        self.actions.entry(action.to_string()).or_default().push(binding);
+        self
+    }
+
+    /// add a binding to an axis.
+    pub fn bind_axis(mut self, axis: &str, binding: AxisBinding) -> Self {
+        self.axes.entry(axis.to_string()).or_default().push(binding);
+        self
+    }
+
+    /// the bindings of an action, none for an unknown one.
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.get(axis).map_or(&[], Vec::as_slice)
+    }
+
+    /// add the actions and axes of `other`, replacing the
+    /// bindings of those both have.
+    pub fn merge(&mut self, other: InputMap) {
+        self.actions.extend(other.actions);
+        self.axes.extend(other.axes);
+    }
+
+    pub fn from_ron(source: &str) -> Result<Self> {
+        ron::from_str(source).map_err(|err| Error::AssetParse(format!("Invalid ron input map. {}", err)))
+    }
+
+    pub fn from_json(source: &str) -> Result<Self> {
+        serde_json::from_str(source).map_err(|err| Error::AssetParse(format!("Invalid json input map. {}", err)))
+    }
+
+    /// load a map from a .ron or .json file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        log::debug!("Loading input map from file... {}", path.display());
+
+        let source = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("ron") => Self::from_ron(&source),
+            Some("json") => Self::from_json(&source),
+            _ => Err(Error::AssetParse(format!(
+                "{}: unknown input map format, expected .ron or .json.",
+                path.display()
+            ))),
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use std::{collections::{BTreeMap, HashSet}, path::Path};
+
+use glfw::{Action, GamepadAxis, GamepadButton, Glfw, JoystickId};
+use nalgebra::Vector2;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+const BUTTONS: [GamepadButton; 15] = [
+    GamepadButton::ButtonA,
+    GamepadButton::ButtonB,
+    GamepadButton::ButtonX,
+    GamepadButton::ButtonY,
+    GamepadButton::ButtonLeftBumper,
+    GamepadButton::ButtonRightBumper,
+    GamepadButton::ButtonBack,
+    GamepadButton::ButtonStart,
+    GamepadButton::ButtonGuide,
+    GamepadButton::ButtonLeftThumb,
+    GamepadButton::ButtonRightThumb,
+    GamepadButton::ButtonDpadUp,
+    GamepadButton::ButtonDpadRight,
+    GamepadButton::ButtonDpadDown,
+    GamepadButton::ButtonDpadLeft,
+];
+
+const JOYSTICKS: [JoystickId; 16] = [
+    JoystickId::Joystick1,
+    JoystickId::Joystick2,
+    JoystickId::Joystick3,
+    JoystickId::Joystick4,
+    JoystickId::Joystick5,
+    JoystickId::Joystick6,
+    JoystickId::Joystick7,
+    JoystickId::Joystick8,
+    JoystickId::Joystick9,
+    JoystickId::Joystick10,
+    JoystickId::Joystick11,
+    JoystickId::Joystick12,
+    JoystickId::Joystick13,
+    JoystickId::Joystick14,
+    JoystickId::Joystick15,
+    JoystickId::Joystick16,
+];
+
+/// how far sticks and triggers have to move before they count.
+/// inputs past the deadzone are rescaled to start at 0.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Deadzone {
+    /// radial deadzone of both sticks
+    pub stick: f32,
+    pub trigger: f32,
+}
+
+impl Default for Deadzone {
+    fn default() -> Self {
+        Self { stick: 0.15, trigger: 0.05 }
+    }
+}
+
+impl Deadzone {
+    fn apply_stick(&self, stick: Vector2<f32>) -> Vector2<f32> {
+        let length = stick.norm();
+        if length <= self.stick {
+            return Vector2::zeros();
+        }
+        stick * (rescale(length, self.stick) / length)
+    }
+
+    fn apply_trigger(&self, trigger: f32) -> f32 {
+        if trigger <= self.trigger { 0.0 } else { rescale(trigger, self.trigger) }
+    }
+}
+
+/// map `value` in [deadzone, 1] to [0, 1].
+fn rescale(value: f32, deadzone: f32) -> f32 {
+    ((value - deadzone) / (1.0 - deadzone).max(f32::EPSILON)).min(1.0)
+}
+
+/// the unprocessed state of a joystick with a gamepad mapping,
+/// as polled from glfw or played back from a recording.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RawGamepad {
+    pub id: JoystickId,
+    pub state: glfw::GamepadState,
+}
+
+/// a gamepad appearing or going away.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GamepadEvent {
+    Connected { id: JoystickId, name: String },
+    Disconnected { id: JoystickId },
+}
+
+/// a joystick with a gamepad mapping, in the standard layout.
+///
+/// stick axes range over [-1, 1] with y pointing down, as in glfw.
+/// triggers range over [0, 1].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GamepadState {
+    pub id: JoystickId,
+    pub name: String,
+
+    buttons: HashSet<GamepadButton>,
+    pressed: HashSet<GamepadButton>,
+    released: HashSet<GamepadButton>,
+
+    /// indexed by `GamepadAxis`
+    axes: [f32; 6],
+}
+
+impl GamepadState {
+    fn new(id: JoystickId, name: String) -> Self {
+        Self {
+            id,
+            name,
+            buttons: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            axes: [0.0; 6],
+        }
+    }
+
+    fn update(&mut self, state: &glfw::GamepadState, deadzone: &Deadzone) {
+        self.pressed.clear();
+        self.released.clear();
+
+        for button in BUTTONS {
+            let held = state.get_button_state(button) == Action::Press;
+            if held && self.buttons.insert(button) {
+                self.pressed.insert(button);
+            } else if !held && self.buttons.remove(&button) {
+                self.released.insert(button);
+            }
+        }
+
+        let stick = |x, y| deadzone.apply_stick(Vector2::new(state.get_axis(x), state.get_axis(y)));
+        let left = stick(GamepadAxis::AxisLeftX, GamepadAxis::AxisLeftY);
+        let right = stick(GamepadAxis::AxisRightX, GamepadAxis::AxisRightY);
+
+        // glfw reports triggers from -1 at rest to 1
+        let trigger = |axis| deadzone.apply_trigger((state.get_axis(axis) + 1.0) * 0.5);
+
+        self.axes = [
+            left.x,
+            left.y,
+            right.x,
+            right.y,
+            trigger(GamepadAxis::AxisLeftTrigger),
+            trigger(GamepadAxis::AxisRightTrigger),
+        ];
+    }
+
+    pub fn pressed(&self, button: GamepadButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    pub fn just_pressed(&self, button: GamepadButton) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    pub fn just_released(&self, button: GamepadButton) -> bool {
+        self.released.contains(&button)
+    }
+
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes[axis as usize]
+    }
+
+    pub fn left_stick(&self) -> Vector2<f32> {
+        Vector2::new(self.axis(GamepadAxis::AxisLeftX), self.axis(GamepadAxis::AxisLeftY))
+    }
+
+    pub fn right_stick(&self) -> Vector2<f32> {
+        Vector2::new(self.axis(GamepadAxis::AxisRightX), self.axis(GamepadAxis::AxisRightY))
+    }
+}
+
+/// the connected gamepads.
+///
+/// joysticks are polled once a frame; those without a gamepad
+/// mapping are ignored until one is loaded for them.
+#[derive(Debug, Default, Clone)]
+pub struct Gamepads {
+    pads: BTreeMap<JoystickId, GamepadState>,
+    events: Vec<GamepadEvent>,
+    deadzone: Deadzone,
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn deadzone(&self) -> &Deadzone {
+        &self.deadzone
+    }
+
+    pub fn set_deadzone(&mut self, deadzone: Deadzone) {
+        self.deadzone = deadzone;
+    }
+
+    /// poll the joysticks, noting gamepads that came or went.
+    pub fn update(&mut self, glfw: &Glfw) {
+        let raw = Self::poll(glfw);
+        self.apply(glfw, &raw);
+    }
+
+    /// the state of every joystick with a gamepad mapping.
+    pub fn poll(glfw: &Glfw) -> Vec<RawGamepad> {
+        JOYSTICKS
+            .into_iter()
+            .filter_map(|id| {
+                let joystick = glfw.get_joystick(id);
+                let state = joystick.is_gamepad().then(|| joystick.get_gamepad_state()).flatten()?;
+                Some(RawGamepad { id, state })
+            })
+            .collect()
+    }
+
+    /// take `raw` as the connected gamepads, noting those that came or
+    /// went. names are looked up in glfw, if the joystick is present.
+    pub fn apply(&mut self, glfw: &Glfw, raw: &[RawGamepad]) {
+        self.events.clear();
+
+        let disconnected: Vec<_> = self.pads.keys().copied().filter(|id| !raw.iter().any(|pad| pad.id == *id)).collect();
+        for id in disconnected {
+            self.pads.remove(&id);
+            log::info!("Gamepad disconnected: {:?}", id);
+            self.events.push(GamepadEvent::Disconnected { id });
+        }
+
+        for &RawGamepad { id, state } in raw {
+            let pad = self.pads.entry(id).or_insert_with(|| {
+                let joystick = glfw.get_joystick(id);
+                let name = joystick.get_gamepad_name().unwrap_or_default();
+                log::info!("Gamepad connected: {} ({:?}, guid = {:?})", name, id, joystick.get_guid());
+                self.events.push(GamepadEvent::Connected { id, name: name.clone() });
+                GamepadState::new(id, name)
+            });
+            pad.update(&state, &self.deadzone);
+        }
+    }
+
+    /// the gamepads that connected or disconnected during the last poll.
+    pub fn events(&self) -> &[GamepadEvent] {
+        &self.events
+    }
+
+    pub fn get(&self, id: JoystickId) -> Option<&GamepadState> {
+        self.pads.get(&id)
+    }
+
+    /// the connected gamepads, by joystick id.
+    pub fn iter(&self) -> impl Iterator<Item = &GamepadState> {
+        self.pads.values()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pads.is_empty()
+    }
+
+    /// whether any gamepad holds `button`.
+    pub fn any_pressed(&self, button: GamepadButton) -> bool {
+        self.iter().any(|pad| pad.pressed(button))
+    }
+
+    pub fn any_just_pressed(&self, button: GamepadButton) -> bool {
+        self.iter().any(|pad| pad.just_pressed(button))
+    }
+
+    pub fn any_just_released(&self, button: GamepadButton) -> bool {
+        self.iter().any(|pad| pad.just_released(button))
+    }
+
+    /// the axis of the gamepad deflecting it the most.
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        self.iter().map(|pad| pad.axis(axis)).fold(0.0, |a, b| if b.abs() > a.abs() { b } else { a })
+    }
+}
+
+/// add gamepad mappings in the SDL_GameControllerDB format,
+/// one per line, to those built into glfw.
+pub fn load_mappings(glfw: &Glfw, mappings: &str) -> Result<()> {
+    let count = mappings.lines().filter(|line| !line.trim().is_empty() && !line.starts_with('#')).count();
+    log::debug!("Updating gamepad mappings... ({} lines)", count);
+
+    if !glfw.update_gamepad_mappings(mappings) {
+        log::error!("Failed to update gamepad mappings.");
+        return Err(Error::GamepadMappings("Glfw rejected the mappings.".to_string()));
+    }
+
+    log::info!("Updated gamepad mappings. ({})", count);
+    Ok(())
+}
+
+/// load a gamecontrollerdb.txt file.
+pub fn load_mappings_file(glfw: &Glfw, path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    log::debug!("Loading gamepad mappings from file... {}", path.display());
+
+    let mappings = std::fs::read_to_string(path)?;
+    load_mappings(glfw, &mappings).map_err(|_| Error::GamepadMappings(format!("{}: invalid mappings.", path.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEADZONE: Deadzone = Deadzone { stick: 0.25, trigger: 0.5 };
+
+    #[test]
+    fn rescales_past_the_deadzone() {
+        assert_eq!(rescale(0.25, 0.25), 0.0);
+        assert_eq!(rescale(0.625, 0.25), 0.5);
+        assert_eq!(rescale(1.0, 0.25), 1.0);
+        assert_eq!(rescale(1.5, 0.25), 1.0);
+
+        // a full deadzone does not divide by zero
+        assert_eq!(rescale(1.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn sticks_use_a_radial_deadzone() {
+        assert_eq!(DEADZONE.apply_stick(Vector2::zeros()), Vector2::zeros());
+        assert_eq!(DEADZONE.apply_stick(Vector2::new(0.25, 0.0)), Vector2::zeros());
+        assert_eq!(DEADZONE.apply_stick(Vector2::new(0.0, -0.25)), Vector2::zeros());
+
+        let past = DEADZONE.apply_stick(Vector2::new(0.0, 0.26));
+        assert!(past.x == 0.0 && past.y > 0.0 && past.y < 0.02);
+
+        assert_eq!(DEADZONE.apply_stick(Vector2::new(-0.625, 0.0)), Vector2::new(-0.5, 0.0));
+        assert_eq!(DEADZONE.apply_stick(Vector2::new(1.0, 0.0)), Vector2::new(1.0, 0.0));
+
+        // the corners of square stick gates clamp to the unit circle
+        let corner = DEADZONE.apply_stick(Vector2::new(1.0, -1.0));
+        assert!((corner.norm() - 1.0).abs() < 1e-6);
+        assert!((corner.x + corner.y).abs() < 1e-6);
+    }
+
+    #[test]
+    fn triggers_start_past_the_deadzone() {
+        assert_eq!(DEADZONE.apply_trigger(0.0), 0.0);
+        assert_eq!(DEADZONE.apply_trigger(0.5), 0.0);
+
+        let past = DEADZONE.apply_trigger(0.51);
+        assert!(past > 0.0 && past < 0.03);
+
+        assert_eq!(DEADZONE.apply_trigger(0.75), 0.5);
+        assert_eq!(DEADZONE.apply_trigger(1.0), 1.0);
+    }
+}
@@ -0,0 +1,15 @@
+/// module for the keyboard, mouse and text
+/// state collected from window events
+pub mod state;
+
+/// module for gamepads, their deadzones
+/// and mappings
+pub mod gamepad;
+
+/// module for recording input to a file
+/// and replaying it
+pub mod record;
+
+/// module for named actions and axes
+/// bound to keys and buttons
+pub mod actions;
@@ -0,0 +1,233 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use glfw::WindowEvent;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+use super::gamepad::RawGamepad;
+
+/// the first bytes of every recording.
+const MAGIC: [u8; 4] = *b"FREC";
+const VERSION: u32 = 1;
+
+/// where the input of an `App` comes from.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub enum InputSource {
+    /// the window and the gamepads
+    #[default]
+    Live,
+
+    /// the window and the gamepads, saving every frame to a file
+    Record(PathBuf),
+
+    /// a recording, frame by frame. `headless` hides the window and
+    /// runs the frames back to back, as for regression runs.
+    Replay { path: PathBuf, headless: bool },
+}
+
+/// what a recording has to be replayed with to come out the same.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RecordingHeader {
+    pub tick_rate: f64,
+    pub max_fixed_steps: u32,
+    pub framebuffer_size: (u32, u32),
+}
+
+/// the input of one frame.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// the timestamp the frame started at, driving the `Clock`
+    pub time: f64,
+
+    /// the window events polled at the end of the frame
+    pub events: Vec<(f64, WindowEvent)>,
+
+    /// the gamepads polled after them
+    pub gamepads: Vec<RawGamepad>,
+}
+
+fn encode_error(err: bincode::ErrorKind) -> Error {
+    match err {
+        bincode::ErrorKind::Io(err) => Error::Io(err),
+        err => Error::Recording(err.to_string()),
+    }
+}
+
+/// writes frames to a recording as they happen. every frame is
+/// flushed, so a crash keeps everything up to the frame before it.
+pub struct Recorder {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    frames: usize,
+}
+
+impl Recorder {
+    pub fn create(path: impl AsRef<Path>, header: &RecordingHeader) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        log::debug!("Creating input recording... {}", path.display());
+
+        let mut writer = BufWriter::new(File::create(&path)?);
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        bincode::serialize_into(&mut writer, header).map_err(|err| encode_error(*err))?;
+
+        log::info!("Recording input to {}.", path.display());
+        Ok(Self { path, writer, frames: 0 })
+    }
+
+    pub fn record(&mut self, frame: &RecordedFrame) -> Result<()> {
+        bincode::serialize_into(&mut self.writer, frame).map_err(|err| encode_error(*err))?;
+        self.writer.flush()?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// flush what is left to the file.
+    pub fn finish(mut self) -> Result<()> {
+        self.writer.flush()?;
+        log::info!("Recorded {} frames to {}.", self.frames, self.path.display());
+        Ok(())
+    }
+}
+
+/// a recording read back for replay.
+pub struct Player {
+    header: RecordingHeader,
+    frames: VecDeque<RecordedFrame>,
+}
+
+impl Player {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        log::debug!("Loading input recording... {}", path.display());
+
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; 4];
+        let mut version = [0; 4];
+        reader.read_exact(&mut magic)?;
+        reader.read_exact(&mut version)?;
+
+        if magic != MAGIC {
+            return Err(Error::Recording(format!("{}: not an input recording.", path.display())));
+        }
+        let version = u32::from_le_bytes(version);
+        if version != VERSION {
+            return Err(Error::Recording(format!(
+                "{}: recording version {} is not supported, expected {}.",
+                path.display(),
+                version,
+                VERSION
+            )));
+        }
+
+        let header: RecordingHeader = bincode::deserialize_from(&mut reader).map_err(|err| encode_error(*err))?;
+
+        let mut frames = VecDeque::new();
+        loop {
+            match bincode::deserialize_from(&mut reader) {
+                Ok(frame) => frames.push_back(frame),
+                Err(err) => match *err {
+                    bincode::ErrorKind::Io(err) if err.kind() == ErrorKind::UnexpectedEof => break,
+                    err => return Err(encode_error(err)),
+                },
+            }
+        }
+
+        log::info!("Loaded {} recorded frames from {}.", frames.len(), path.display());
+        Ok(Self { header, frames })
+    }
+
+    pub fn header(&self) -> &RecordingHeader {
+        &self.header
+    }
+
+    /// the frames not yet played.
+    pub fn remaining(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn next_frame(&mut self) -> Option<RecordedFrame> {
+        self.frames.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+
+    use glfw::{Action, Key, Modifiers};
+
+    use super::*;
+
+    const HEADER: RecordingHeader = RecordingHeader { tick_rate: 60.0, max_fixed_steps: 5, framebuffer_size: (800, 600) };
+
+    fn frame(time: f64) -> RecordedFrame {
+        RecordedFrame {
+            time,
+            events: vec![(time, WindowEvent::Key(Key::A, 30, Action::Press, Modifiers::Shift))],
+            gamepads: Vec::new(),
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ferra-{}-{}.rec", name, std::process::id()))
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temp_path("round-trip");
+        let mut recorder = Recorder::create(&path, &HEADER).unwrap();
+        recorder.record(&frame(0.0)).unwrap();
+        recorder.record(&frame(0.5)).unwrap();
+
+        // every frame is on disk before `finish`
+        let player = Player::open(&path);
+        recorder.finish().unwrap();
+        let mut player = player.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(player.header(), &HEADER);
+        assert_eq!(player.remaining(), 2);
+        for time in [0.0, 0.5] {
+            let frame = player.next_frame().unwrap();
+            assert_eq!(frame.time, time);
+            assert_eq!(frame.events, [(time, WindowEvent::Key(Key::A, 30, Action::Press, Modifiers::Shift))]);
+        }
+        assert!(player.next_frame().is_none());
+    }
+
+    #[test]
+    fn ignores_truncated_frames() {
+        let path = temp_path("truncated");
+        let mut recorder = Recorder::create(&path, &HEADER).unwrap();
+        recorder.record(&frame(0.0)).unwrap();
+        recorder.record(&frame(0.5)).unwrap();
+        recorder.finish().unwrap();
+
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(file.metadata().unwrap().len() - 3).unwrap();
+        drop(file);
+
+        let player = Player::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        let mut player = player.unwrap();
+        assert_eq!(player.remaining(), 1);
+        assert_eq!(player.next_frame().unwrap().time, 0.0);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let path = temp_path("other");
+        std::fs::write(&path, b"not a recording").unwrap();
+        let player = Player::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(player, Err(Error::Recording(_))));
+    }
+}
@@ -0,0 +1,382 @@
+use std::collections::HashSet;
+
+use glfw::{Action, Glfw, Key, MouseButton, Window, WindowEvent};
+use nalgebra::Vector2;
+
+use super::{actions::{Binding, InputMap}, gamepad::{Gamepads, RawGamepad}};
+
+/// how the cursor behaves over the window.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CursorMode {
+    #[default]
+    Normal,
+
+    /// invisible while over the window
+    Hidden,
+
+    /// invisible and held in place, only reporting movement. uses
+    /// raw mouse motion where supported, as for mouse look.
+    Locked,
+}
+
+/// the keys and buttons a frame started with.
+///
+/// fed with the window events polled at the end of every frame,
+/// so `just_pressed` and friends hold until the next poll. fixed
+/// updates running more than once a frame see them every time.
+#[derive(Debug, Default, Clone)]
+pub struct Input {
+    keys: HashSet<Key>,
+    keys_pressed: HashSet<Key>,
+    keys_released: HashSet<Key>,
+
+    buttons: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+
+    /// `None` until the first cursor event
+    cursor: Option<Vector2<f32>>,
+    cursor_delta: Vector2<f32>,
+    scroll: Vector2<f32>,
+    text: String,
+
+    /// the window size in screen coordinates, like the cursor
+    window_size: Vector2<f32>,
+
+    cursor_mode: CursorMode,
+    gamepads: Gamepads,
+    map: InputMap,
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// forget what happened during the last poll, before the next one.
+    pub fn begin_poll(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.cursor_delta = Vector2::zeros();
+        self.scroll = Vector2::zeros();
+        self.text.clear();
+    }
+
+    /// poll the gamepads, after the window events.
+    pub fn update_gamepads(&mut self, glfw: &Glfw) {
+        self.gamepads.update(glfw);
+    }
+
+    /// take `raw` as the gamepad states instead of polling them.
+    pub fn apply_gamepads(&mut self, glfw: &Glfw, raw: &[RawGamepad]) {
+        self.gamepads.apply(glfw, raw);
+    }
+
+    pub fn event(&mut self, event: &WindowEvent) {
+        match *event {
+            WindowEvent::Key(key, _, action, _) => {
+                update(&mut self.keys, &mut self.keys_pressed, &mut self.keys_released, key, action)
+            },
+            WindowEvent::MouseButton(button, action, _) => {
+                update(&mut self.buttons, &mut self.buttons_pressed, &mut self.buttons_released, button, action)
+            },
+            WindowEvent::CursorPos(x, y) => {
+                let position = Vector2::new(x as f32, y as f32);
+                if let Some(last) = self.cursor {
+                    self.cursor_delta += position - last;
+                }
+                self.cursor = Some(position);
+            },
+            WindowEvent::Scroll(x, y) => self.scroll += Vector2::new(x as f32, y as f32),
+            WindowEvent::Char(c) => self.text.push(c),
+            WindowEvent::Size(width, height) => self.window_size = Vector2::new(width as f32, height as f32),
+            WindowEvent::Focus(false) => {
+                // releases are not reported while unfocused
+                self.keys_released.extend(self.keys.drain());
+                self.buttons_released.extend(self.buttons.drain());
+            },
+            _ => {},
+        }
+    }
+
+    pub fn pressed(&self, key: Key) -> bool {
+        self.keys.contains(&key)
+    }
+
+    pub fn just_pressed(&self, key: Key) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    pub fn just_released(&self, key: Key) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    pub fn mouse_pressed(&self, button: MouseButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    pub fn mouse_just_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    pub fn mouse_just_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    /// the cursor position in screen coordinates from the
+    /// top left of the window.
+    pub fn cursor_position(&self) -> Vector2<f32> {
+        self.cursor.unwrap_or_else(Vector2::zeros)
+    }
+
+    /// how far the cursor moved during the last poll.
+    pub fn cursor_delta(&self) -> Vector2<f32> {
+        self.cursor_delta
+    }
+
+    /// how far was scrolled during the last poll.
+    pub fn scroll(&self) -> Vector2<f32> {
+        self.scroll
+    }
+
+    /// the window size in screen coordinates, as of the last poll.
+    pub fn window_size(&self) -> Vector2<f32> {
+        self.window_size
+    }
+
+    /// set the window size before any resize event, e.g. the
+    /// size the window was created with.
+    pub fn set_window_size(&mut self, width: i32, height: i32) {
+        self.window_size = Vector2::new(width as f32, height as f32);
+    }
+
+    /// the text typed during the last poll.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor_mode(&self) -> CursorMode {
+        self.cursor_mode
+    }
+
+    pub fn set_cursor_mode(&mut self, window: &mut Window, mode: CursorMode) {
+        log::debug!("Setting cursor mode... ({:?})", mode);
+
+        window.set_cursor_mode(match mode {
+            CursorMode::Normal => glfw::CursorMode::Normal,
+            CursorMode::Hidden => glfw::CursorMode::Hidden,
+            CursorMode::Locked => glfw::CursorMode::Disabled,
+        });
+
+        let raw = mode == CursorMode::Locked && window.glfw.supports_raw_motion();
+        window.set_raw_mouse_motion(raw);
+
+        // the cursor jumps when locked or released
+        self.cursor = None;
+        self.cursor_mode = mode;
+    }
+
+    pub fn gamepads(&self) -> &Gamepads {
+        &self.gamepads
+    }
+
+    pub fn gamepads_mut(&mut self) -> &mut Gamepads {
+        &mut self.gamepads
+    }
+
+    pub fn map(&self) -> &InputMap {
+        &self.map
+    }
+
+    pub fn set_map(&mut self, map: InputMap) {
+        self.map = map;
+    }
+
+    /// whether a binding is held.
+    pub fn binding_pressed(&self, binding: &Binding) -> bool {
+        match *binding {
+            Binding::Key(key) => self.pressed(key),
+            Binding::Mouse(button) => self.mouse_pressed(button),
+            Binding::Gamepad(button) => self.gamepads.any_pressed(button),
+        }
+    }
+
+    pub fn binding_just_pressed(&self, binding: &Binding) -> bool {
+        match *binding {
+            Binding::Key(key) => self.just_pressed(key),
+            Binding::Mouse(button) => self.mouse_just_pressed(button),
+            Binding::Gamepad(button) => self.gamepads.any_just_pressed(button),
+        }
+    }
+
+    pub fn binding_just_released(&self, binding: &Binding) -> bool {
+        match *binding {
+            Binding::Key(key) => self.just_released(key),
+            Binding::Mouse(button) => self.mouse_just_released(button),
+            Binding::Gamepad(button) => self.gamepads.any_just_released(button),
+        }
+    }
+
+    /// whether any binding of the action is held.
+    pub fn action(&self, action: &str) -> bool {
+        self.map.bindings(action).iter().any(|binding| self.binding_pressed(binding))
+    }
+
+    /// whether the action started during the last poll, i.e. one
+    /// of its bindings was pressed while none was held before.
+    pub fn action_just_pressed(&self, action: &str) -> bool {
+        let bindings = self.map.bindings(action);
+        bindings.iter().any(|binding| self.binding_just_pressed(binding))
+            && bindings
+                .iter()
+                .all(|binding| !self.binding_pressed(binding) || self.binding_just_pressed(binding))
+    }
+
+    /// whether the action ended during the last poll, i.e. one
+    /// of its bindings was released and none is held anymore.
+    pub fn action_just_released(&self, action: &str) -> bool {
+        let bindings = self.map.bindings(action);
+        bindings.iter().any(|binding| self.binding_just_released(binding))
+            && !bindings.iter().any(|binding| self.binding_pressed(binding))
+    }
+
+    /// the sum of the axis' bindings, 0 for an unknown axis.
+    pub fn axis(&self, axis: &str) -> f32 {
+        self.map.axis_bindings(axis).iter().map(|binding| binding.value(self)).sum()
+    }
+}
+
+/// track a key or button through an action.
+fn update<T: Copy + Eq + std::hash::Hash>(
+    held: &mut HashSet<T>,
+    pressed: &mut HashSet<T>,
+    released: &mut HashSet<T>,
+    input: T,
+    action: Action,
+) {
+    match action {
+        Action::Press => {
+            if held.insert(input) {
+                pressed.insert(input);
+            }
+        },
+        Action::Release => {
+            if held.remove(&input) {
+                released.insert(input);
+            }
+        },
+        Action::Repeat => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glfw::Modifiers;
+
+    use super::*;
+
+    fn key(input: &mut Input, key: Key, action: Action) {
+        input.event(&WindowEvent::Key(key, 0, action, Modifiers::empty()));
+    }
+
+    fn jump_input() -> Input {
+        let mut input = Input::new();
+        input.set_map(
+            InputMap::new()
+                .bind("jump", Binding::Key(Key::Space))
+                .bind("jump", Binding::Key(Key::W))
+                .bind("jump", Binding::Mouse(MouseButton::Button2)),
+        );
+        input
+    }
+
+    #[test]
+    fn tracks_presses_and_releases() {
+        let mut input = Input::new();
+        key(&mut input, Key::A, Action::Press);
+        assert!(input.pressed(Key::A) && input.just_pressed(Key::A) && !input.just_released(Key::A));
+
+        input.begin_poll();
+        key(&mut input, Key::A, Action::Repeat);
+        assert!(input.pressed(Key::A) && !input.just_pressed(Key::A));
+
+        input.begin_poll();
+        key(&mut input, Key::A, Action::Release);
+        assert!(!input.pressed(Key::A) && input.just_released(Key::A));
+
+        // a tap within one poll is both pressed and released
+        input.begin_poll();
+        key(&mut input, Key::B, Action::Press);
+        key(&mut input, Key::B, Action::Release);
+        assert!(!input.pressed(Key::B) && input.just_pressed(Key::B) && input.just_released(Key::B));
+
+        // a release without a press is ignored
+        input.begin_poll();
+        key(&mut input, Key::C, Action::Release);
+        assert!(!input.just_released(Key::C));
+    }
+
+    #[test]
+    fn losing_focus_releases_everything() {
+        let mut input = Input::new();
+        key(&mut input, Key::A, Action::Press);
+        input.event(&WindowEvent::MouseButton(MouseButton::Button1, Action::Press, Modifiers::empty()));
+        input.begin_poll();
+
+        input.event(&WindowEvent::Focus(false));
+        assert!(!input.pressed(Key::A) && input.just_released(Key::A));
+        assert!(!input.mouse_pressed(MouseButton::Button1) && input.mouse_just_released(MouseButton::Button1));
+
+        // the release reported after regaining focus changes nothing
+        input.begin_poll();
+        key(&mut input, Key::A, Action::Release);
+        assert!(!input.just_released(Key::A));
+    }
+
+    #[test]
+    fn tracks_the_cursor() {
+        let mut input = Input::new();
+        input.event(&WindowEvent::CursorPos(10.0, 20.0));
+        assert_eq!(input.cursor_delta(), Vector2::zeros());
+
+        input.begin_poll();
+        input.event(&WindowEvent::CursorPos(15.0, 18.0));
+        input.event(&WindowEvent::CursorPos(16.0, 18.0));
+        input.event(&WindowEvent::Scroll(0.0, 1.0));
+        assert_eq!(input.cursor_position(), Vector2::new(16.0, 18.0));
+        assert_eq!(input.cursor_delta(), Vector2::new(6.0, -2.0));
+        assert_eq!(input.scroll(), Vector2::new(0.0, 1.0));
+    }
+
+    #[test]
+    fn actions_start_with_their_first_binding() {
+        let mut input = jump_input();
+        key(&mut input, Key::Space, Action::Press);
+        assert!(input.action("jump") && input.action_just_pressed("jump"));
+
+        // a second binding pressed while the first is held is no new start
+        input.begin_poll();
+        key(&mut input, Key::W, Action::Press);
+        assert!(input.just_pressed(Key::W));
+        assert!(!input.action_just_pressed("jump"));
+
+        // nor is releasing one of them an end
+        input.begin_poll();
+        key(&mut input, Key::Space, Action::Release);
+        assert!(input.action("jump") && !input.action_just_released("jump"));
+
+        input.begin_poll();
+        key(&mut input, Key::W, Action::Release);
+        assert!(!input.action("jump") && input.action_just_released("jump"));
+
+        // two bindings pressed in the same poll start the action once
+        input.begin_poll();
+        key(&mut input, Key::Space, Action::Press);
+        input.event(&WindowEvent::MouseButton(MouseButton::Button2, Action::Press, Modifiers::empty()));
+        assert!(input.action_just_pressed("jump"));
+
+        assert!(!input.action("unbound") && !input.action_just_pressed("unbound"));
+    }
+}
@@ -0,0 +1,37 @@
+/// module for common code and utilities
+pub mod common;
+
+pub use common::err::*;
+
+/// module for rendering and windowing using 
+/// opengl and glfw.
+pub mod display;
+
+/// module for loading models and other
+/// assets from disk.
+pub mod assets;
+
+/// module for transforms and the scene graph.
+pub mod scene;
+
+/// module for the entity component system.
+pub mod ecs;
+
+/// module for keyboard, mouse and gamepad
+/// input and action mapping.
+pub mod input;
+
+/// module for the `App` trait and the
+/// main loop running it.
+pub mod app;
+
+/// module for frame timing and the
+/// fixed update clock.
+pub mod time;
+
+/// module for the engine configuration and its
+/// file, environment and command-line layers.
+pub mod config;
+
+pub use app::{run, App, AppContext};
+pub use config::EngineConfig;
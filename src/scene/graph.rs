@@ -0,0 +1,445 @@
+use std::{cell::Cell, rc::Rc};
+
+use nalgebra::Matrix4;
+
+use crate::{
+    display::{camera::Camera, context::GlContext, material::Material, mesh::Mesh},
+    Result,
+};
+
+use super::transform::Transform;
+
+/// a handle to a node in a `SceneGraph`.
+///
+/// the generation tells a node apart from later nodes
+/// that reuse its slot after it was removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    index: usize,
+    generation: u32,
+}
+
+/// what is drawn at a node's world transform.
+///
+/// besides the material's own parameters, its program
+/// receives the `model`, `view` and `projection` matrices.
+#[derive(Clone)]
+pub struct Renderable {
+    pub mesh: Rc<Mesh>,
+    pub material: Rc<Material>,
+}
+
+impl Renderable {
+    pub fn draw(
+        &self,
+        ctx: &GlContext,
+        model: &Matrix4<f32>,
+        view: &Matrix4<f32>,
+        projection: &Matrix4<f32>,
+    ) -> Result<()> {
+        self.material.bind(ctx)?;
+
+        let program = &self.material.program;
+        program.uniform_mat4(ctx, "model", model)?;
+        program.uniform_mat4(ctx, "view", view)?;
+        program.uniform_mat4(ctx, "projection", projection)?;
+
+        self.mesh.draw(ctx);
+
+        Ok(())
+    }
+
+    /// whether the `DeferredRenderer` draws this into its g-buffer;
+    /// blended materials are always drawn forward.
+    pub fn is_deferred(&self) -> bool {
+        self.material.deferred.is_some() && !self.material.state.blend.enabled
+    }
+
+    /// draw into the bound g-buffer with the deferred program of the material.
+    pub fn draw_deferred(
+        &self,
+        ctx: &GlContext,
+        model: &Matrix4<f32>,
+        view: &Matrix4<f32>,
+        projection: &Matrix4<f32>,
+    ) -> Result<()> {
+        self.material.bind_deferred(ctx)?;
+
+        let program = self.material.deferred.as_deref().unwrap_or(&self.material.program);
+        program.uniform_mat4(ctx, "model", model)?;
+        program.uniform_mat4(ctx, "view", view)?;
+        program.uniform_mat4(ctx, "projection", projection)?;
+
+        self.mesh.draw(ctx);
+
+        Ok(())
+    }
+}
+
+pub struct Node {
+    pub name: String,
+    pub renderable: Option<Renderable>,
+
+    transform: Transform,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+
+    /// cached world matrix, valid unless `dirty` is set
+    world: Cell<Matrix4<f32>>,
+    dirty: Cell<bool>,
+}
+
+impl Node {
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+/// a hierarchy of transforms.
+///
+/// world matrices are computed on demand and cached; changing a
+/// transform or a parent only marks the affected subtree dirty.
+#[derive(Default)]
+pub struct SceneGraph {
+    nodes: Vec<Option<Node>>,
+
+    /// the generation of every slot, bumped when its node is removed
+    generations: Vec<u32>,
+
+    /// slots of removed nodes, reused by `add`
+    free: Vec<usize>,
+
+    roots: Vec<NodeId>,
+}
+
+impl SceneGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    /// whether `id` refers to a node that was not removed.
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.generations.get(id.index) == Some(&id.generation) && self.nodes[id.index].is_some()
+    }
+
+    /// the node of `id`, or `None` if it was removed.
+    pub fn node(&self, id: NodeId) -> Option<&Node> {
+        if !self.contains(id) {
+            return None;
+        }
+        self.nodes[id.index].as_ref()
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        if !self.contains(id) {
+            return None;
+        }
+        self.nodes[id.index].as_mut()
+    }
+
+    /// a node linked from another one, which is always alive.
+    fn get(&self, id: NodeId) -> &Node {
+        self.node(id).expect("scene node links are kept valid")
+    }
+
+    fn get_mut(&mut self, id: NodeId) -> &mut Node {
+        self.node_mut(id).expect("scene node links are kept valid")
+    }
+
+    /// add a root node.
+    pub fn add(&mut self, name: &str, transform: Transform) -> NodeId {
+        let node = Node {
+            name: name.to_string(),
+            renderable: None,
+            transform,
+            parent: None,
+            children: Vec::new(),
+            world: Cell::new(Matrix4::identity()),
+            dirty: Cell::new(true),
+        };
+
+        let id = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = Some(node);
+                NodeId { index, generation: self.generations[index] }
+            },
+            None => {
+                self.nodes.push(Some(node));
+                self.generations.push(0);
+                NodeId { index: self.nodes.len() - 1, generation: 0 }
+            },
+        };
+
+        self.roots.push(id);
+        id
+    }
+
+    /// add a node under `parent`, or nothing if `parent` was removed.
+    pub fn add_child(&mut self, parent: NodeId, name: &str, transform: Transform) -> Option<NodeId> {
+        if !self.contains(parent) {
+            return None;
+        }
+        let id = self.add(name, transform);
+        self.set_parent(id, Some(parent));
+        Some(id)
+    }
+
+    /// remove a node together with all of its descendants.
+    /// ids of removed nodes are ignored.
+    pub fn remove(&mut self, id: NodeId) {
+        if !self.contains(id) {
+            return;
+        }
+
+        self.detach(id);
+        self.roots.retain(|&root| root != id);
+
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if !self.contains(id) {
+                continue;
+            }
+            if let Some(node) = self.nodes[id.index].take() {
+                stack.extend(node.children);
+                self.generations[id.index] = self.generations[id.index].wrapping_add(1);
+                self.free.push(id.index);
+            }
+        }
+    }
+
+    fn detach(&mut self, id: NodeId) {
+        if let Some(parent) = self.get(id).parent {
+            self.get_mut(parent).children.retain(|&child| child != id);
+        }
+    }
+
+    /// whether `ancestor` is `id` or one of its ancestors.
+    /// false if either was removed.
+    pub fn is_ancestor(&self, ancestor: NodeId, id: NodeId) -> bool {
+        let mut current = self.contains(ancestor).then_some(id);
+        while let Some(node) = current {
+            if node == ancestor {
+                return true;
+            }
+            current = self.node(node).and_then(|node| node.parent);
+        }
+        false
+    }
+
+    /// move a node under a new parent, or to the roots for `None`.
+    /// the local transform is kept, so the world transform changes.
+    /// requests with removed nodes or that would create a cycle are
+    /// ignored; returns whether the node was moved.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> bool {
+        if !self.contains(id) || parent.is_some_and(|parent| !self.contains(parent)) {
+            return false;
+        }
+        if parent.is_some_and(|parent| self.is_ancestor(id, parent)) {
+            log::warn!("Refusing to parent scene node {:?} to its descendant {:?}.", id, parent);
+            return false;
+        }
+
+        self.detach(id);
+        self.roots.retain(|&root| root != id);
+
+        match parent {
+            Some(parent) => self.get_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+
+        self.get_mut(id).parent = parent;
+        self.mark_dirty(id);
+        true
+    }
+
+    /// returns false if the node was removed.
+    pub fn set_transform(&mut self, id: NodeId, transform: Transform) -> bool {
+        self.update_transform(id, |current| *current = transform)
+    }
+
+    /// modify a node's transform in place. returns false if the node was removed.
+    pub fn update_transform(&mut self, id: NodeId, f: impl FnOnce(&mut Transform)) -> bool {
+        let Some(node) = self.node_mut(id) else {
+            return false;
+        };
+        f(&mut node.transform);
+        self.mark_dirty(id);
+        true
+    }
+
+    /// returns false if the node was removed.
+    pub fn set_renderable(&mut self, id: NodeId, renderable: Option<Renderable>) -> bool {
+        let Some(node) = self.node_mut(id) else {
+            return false;
+        };
+        node.renderable = renderable;
+        true
+    }
+
+    fn mark_dirty(&self, id: NodeId) {
+        let node = self.get(id);
+        node.dirty.set(true);
+
+        let mut stack = node.children.clone();
+        while let Some(id) = stack.pop() {
+            let node = self.get(id);
+
+            // computing a world matrix also computes those of all
+            // ancestors, so the subtree of a dirty node is dirty too
+            if !node.dirty.replace(true) {
+                stack.extend_from_slice(&node.children);
+            }
+        }
+    }
+
+    /// the transform from the node's local space to world space,
+    /// or `None` if the node was removed.
+    pub fn world_matrix(&self, id: NodeId) -> Option<Matrix4<f32>> {
+        self.contains(id).then(|| self.cached_world_matrix(id))
+    }
+
+    fn cached_world_matrix(&self, id: NodeId) -> Matrix4<f32> {
+        let node = self.get(id);
+        if !node.dirty.get() {
+            return node.world.get();
+        }
+
+        let local = node.transform.matrix();
+        let world = match node.parent {
+            Some(parent) => self.cached_world_matrix(parent) * local,
+            None => local,
+        };
+
+        node.world.set(world);
+        node.dirty.set(false);
+        world
+    }
+
+    /// visit every node depth-first, parents before children,
+    /// together with its world matrix.
+    pub fn traverse(&self, mut visit: impl FnMut(NodeId, &Node, &Matrix4<f32>)) {
+        let mut stack: Vec<NodeId> = self.roots.iter().rev().copied().collect();
+
+        while let Some(id) = stack.pop() {
+            let node = self.get(id);
+            visit(id, node, &self.cached_world_matrix(id));
+            stack.extend(node.children.iter().rev());
+        }
+    }
+
+    /// draw every renderable node as seen by `camera`.
+    pub fn draw(&self, ctx: &GlContext, camera: &Camera) -> Result<()> {
+        let view = camera.view_matrix();
+        let projection = camera.projection_matrix();
+
+        let mut result = Ok(());
+        self.traverse(|_, node, world| {
+            if result.is_err() {
+                return;
+            }
+            if let Some(renderable) = &node.renderable {
+                result = renderable.draw(ctx, world, &view, &projection);
+            }
+        });
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+
+    use super::*;
+
+    fn translation(matrix: Matrix4<f32>) -> Vector3<f32> {
+        matrix.fixed_view::<3, 1>(0, 3).into()
+    }
+
+    #[test]
+    fn stale_ids_miss_reused_slots() {
+        let mut graph = SceneGraph::new();
+        let old = graph.add("old", Transform::identity());
+        graph.remove(old);
+
+        let new = graph.add("new", Transform::identity());
+        assert!(graph.contains(new));
+        assert!(!graph.contains(old));
+        assert!(graph.node(old).is_none());
+        assert_eq!(graph.node(new).unwrap().name, "new");
+
+        assert!(!graph.set_transform(old, Transform::from_translation(Vector3::x())));
+        assert!(!graph.set_parent(old, None));
+        assert!(graph.add_child(old, "child", Transform::identity()).is_none());
+        assert!(graph.world_matrix(old).is_none());
+        assert_eq!(graph.world_matrix(new), Some(Matrix4::identity()));
+
+        graph.remove(old);
+        assert_eq!(graph.len(), 1);
+    }
+
+    #[test]
+    fn removes_descendants() {
+        let mut graph = SceneGraph::new();
+        let root = graph.add("root", Transform::identity());
+        let child = graph.add_child(root, "child", Transform::identity()).unwrap();
+        let grandchild = graph.add_child(child, "grandchild", Transform::identity()).unwrap();
+
+        graph.remove(child);
+        assert!(!graph.contains(grandchild));
+        assert!(graph.node(root).unwrap().children().is_empty());
+        assert_eq!(graph.len(), 1);
+    }
+
+    #[test]
+    fn world_matrices_follow_reparenting() {
+        let mut graph = SceneGraph::new();
+        let a = graph.add("a", Transform::from_translation(Vector3::new(1.0, 0.0, 0.0)));
+        let b = graph.add("b", Transform::from_translation(Vector3::new(0.0, 2.0, 0.0)));
+        let child = graph.add_child(a, "child", Transform::from_translation(Vector3::new(0.0, 0.0, 3.0))).unwrap();
+
+        assert_eq!(translation(graph.world_matrix(child).unwrap()), Vector3::new(1.0, 0.0, 3.0));
+
+        assert!(graph.set_parent(child, Some(b)));
+        assert_eq!(translation(graph.world_matrix(child).unwrap()), Vector3::new(0.0, 2.0, 3.0));
+
+        // moving the new parent dirties the cached child
+        graph.update_transform(b, |transform| transform.translation.y = 5.0);
+        assert_eq!(translation(graph.world_matrix(child).unwrap()), Vector3::new(0.0, 5.0, 3.0));
+
+        assert!(graph.set_parent(child, None));
+        assert_eq!(translation(graph.world_matrix(child).unwrap()), Vector3::new(0.0, 0.0, 3.0));
+        assert_eq!(graph.roots(), &[a, b, child]);
+    }
+
+    #[test]
+    fn refuses_cycles() {
+        let mut graph = SceneGraph::new();
+        let parent = graph.add("parent", Transform::identity());
+        let child = graph.add_child(parent, "child", Transform::identity()).unwrap();
+
+        assert!(!graph.set_parent(parent, Some(child)));
+        assert!(graph.is_ancestor(parent, child));
+        assert!(!graph.is_ancestor(child, parent));
+        assert_eq!(graph.node(parent).unwrap().parent(), None);
+    }
+}
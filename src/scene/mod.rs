@@ -0,0 +1,7 @@
+/// module for translation, rotation and
+/// scale transforms
+pub mod transform;
+
+/// module for the node hierarchy with
+/// lazily computed world matrices
+pub mod graph;
@@ -0,0 +1,49 @@
+use nalgebra::{Matrix4, Point3, UnitQuaternion, Vector3};
+
+/// a translation, rotation and scale, applied in the
+/// order scale, rotation, translation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self { translation: Vector3::zeros(), rotation: UnitQuaternion::identity(), scale: Vector3::repeat(1.0) }
+    }
+
+    pub fn from_translation(translation: Vector3<f32>) -> Self {
+        Self { translation, ..Self::identity() }
+    }
+
+    pub fn with_rotation(mut self, rotation: UnitQuaternion<f32>) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vector3<f32>) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// rotate so that -z points from the translation towards `target`.
+    pub fn look_at(mut self, target: &Point3<f32>, up: &Vector3<f32>) -> Self {
+        let direction = target.coords - self.translation;
+        self.rotation = UnitQuaternion::face_towards(&-direction, up);
+        self
+    }
+
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::new_translation(&self.translation)
+            * self.rotation.to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&self.scale)
+    }
+}
@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+
+/// how the main loop paces frames and fixed updates.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeSettings {
+    /// fixed updates per second
+    pub tick_rate: f64,
+
+    /// the most fixed updates run in one frame. time that would need
+    /// more is dropped, so a slow frame does not snowball.
+    pub max_fixed_steps: u32,
+
+    /// frames per second to sleep down to while vsync is off
+    pub max_fps: Option<f64>,
+}
+
+impl Default for TimeSettings {
+    fn default() -> Self {
+        Self { tick_rate: 60.0, max_fixed_steps: 5, max_fps: None }
+    }
+}
+
+impl TimeSettings {
+    /// the seconds between fixed updates.
+    pub fn fixed_delta(&self) -> f64 {
+        1.0 / self.tick_rate.max(f64::EPSILON)
+    }
+
+    /// the shortest a frame may take, if capped.
+    pub fn min_frame_time(&self) -> Option<f64> {
+        self.max_fps.filter(|fps| *fps > 0.0).map(|fps| 1.0 / fps)
+    }
+}
+
+/// how much weight the latest frame has in the smoothed fps.
+const FPS_SMOOTHING: f64 = 0.1;
+
+/// the timing of the current frame, as kept by a `Clock`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Time {
+    delta: f32,
+    elapsed: f64,
+    frame: u64,
+
+    /// exponentially smoothed seconds per frame
+    smoothed_delta: f64,
+
+    fixed_delta: f32,
+    fixed_ticks: u64,
+    alpha: f32,
+}
+
+impl Time {
+    /// the seconds since the previous frame.
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    /// the seconds since the first frame.
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    /// the number of the current frame, starting at 0.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// the frames per second, smoothed over recent frames.
+    pub fn fps(&self) -> f32 {
+        if self.smoothed_delta > 0.0 { (1.0 / self.smoothed_delta) as f32 } else { 0.0 }
+    }
+
+    /// the seconds simulated by every fixed update.
+    pub fn fixed_delta(&self) -> f32 {
+        self.fixed_delta
+    }
+
+    /// the number of fixed updates run so far.
+    pub fn fixed_ticks(&self) -> u64 {
+        self.fixed_ticks
+    }
+
+    /// how far the frame lies between the last fixed update and the
+    /// next one, in [0, 1). rendering interpolates between the last
+    /// two fixed states by it.
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+}
+
+/// turns timestamps into frame timings and fixed update steps.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    settings: TimeSettings,
+    time: Time,
+
+    /// the timestamp of the previous frame
+    last: Option<f64>,
+
+    /// the seconds not yet consumed by fixed updates
+    accumulator: f64,
+}
+
+impl Clock {
+    pub fn new(settings: TimeSettings) -> Self {
+        let time = Time {
+            delta: 0.0,
+            elapsed: 0.0,
+            frame: 0,
+            smoothed_delta: 0.0,
+            fixed_delta: settings.fixed_delta() as f32,
+            fixed_ticks: 0,
+            alpha: 0.0,
+        };
+        Self { settings, time, last: None, accumulator: 0.0 }
+    }
+
+    pub fn settings(&self) -> &TimeSettings {
+        &self.settings
+    }
+
+    pub fn time(&self) -> &Time {
+        &self.time
+    }
+
+    /// start a frame at `now` seconds and return how many
+    /// fixed updates it has to run.
+    pub fn tick(&mut self, now: f64) -> u32 {
+        let delta = self.last.map_or(0.0, |last| (now - last).max(0.0));
+
+        if self.last.is_some() {
+            self.time.frame += 1;
+            self.time.elapsed += delta;
+            self.time.smoothed_delta = match self.time.smoothed_delta {
+                0.0 => delta,
+                smoothed => smoothed + (delta - smoothed) * FPS_SMOOTHING,
+            };
+        }
+        self.last = Some(now);
+        self.time.delta = delta as f32;
+
+        let fixed_delta = self.settings.fixed_delta();
+        self.accumulator += delta;
+
+        let mut steps = (self.accumulator / fixed_delta) as u64;
+        if steps > self.settings.max_fixed_steps as u64 {
+            log::debug!(
+                "Dropping {:.3}s of fixed updates. (steps = {}, max = {})",
+                (steps - self.settings.max_fixed_steps as u64) as f64 * fixed_delta,
+                steps,
+                self.settings.max_fixed_steps
+            );
+            steps = self.settings.max_fixed_steps as u64;
+            self.accumulator = self.accumulator.rem_euclid(fixed_delta) + steps as f64 * fixed_delta;
+        }
+
+        self.accumulator = (self.accumulator - steps as f64 * fixed_delta).max(0.0);
+        self.time.fixed_ticks += steps;
+        self.time.alpha = ((self.accumulator / fixed_delta) as f32).min(1.0 - f32::EPSILON);
+
+        steps as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 4 ticks a second, so every step is exact in binary
+    fn clock(max_fixed_steps: u32) -> Clock {
+        Clock::new(TimeSettings { tick_rate: 4.0, max_fixed_steps, max_fps: None })
+    }
+
+    #[test]
+    fn accumulates_fixed_steps() {
+        let mut clock = clock(5);
+        assert_eq!(clock.tick(10.0), 0);
+        assert_eq!(clock.time().frame(), 0);
+        assert_eq!(clock.time().delta(), 0.0);
+
+        assert_eq!(clock.tick(10.125), 0);
+        assert_eq!(clock.time().alpha(), 0.5);
+
+        assert_eq!(clock.tick(10.25), 1);
+        assert_eq!(clock.time().alpha(), 0.0);
+
+        assert_eq!(clock.tick(10.875), 2);
+        assert_eq!(clock.time().alpha(), 0.5);
+        assert_eq!(clock.time().fixed_ticks(), 3);
+        assert_eq!(clock.time().frame(), 3);
+        assert_eq!(clock.time().elapsed(), 0.875);
+        assert_eq!(clock.time().fixed_delta(), 0.25);
+    }
+
+    #[test]
+    fn clamps_to_max_fixed_steps() {
+        let mut clock = clock(3);
+        clock.tick(0.0);
+
+        // 10 steps and a half are due, the 7 over the limit are dropped
+        assert_eq!(clock.tick(2.625), 3);
+        assert_eq!(clock.time().alpha(), 0.5);
+        assert_eq!(clock.tick(2.75), 1);
+        assert_eq!(clock.time().fixed_ticks(), 4);
+    }
+
+    #[test]
+    fn keeps_alpha_below_one() {
+        let mut clock = Clock::new(TimeSettings { tick_rate: 60.0, ..TimeSettings::default() });
+        let mut now = 0.0;
+        for i in 0..1000 {
+            now += 0.001 + (i % 7) as f64 * 0.003;
+            clock.tick(now);
+            assert!((0.0..1.0).contains(&clock.time().alpha()), "{}", clock.time().alpha());
+        }
+
+        // time running backwards counts as no time
+        let ticks = clock.time().fixed_ticks();
+        assert_eq!(clock.tick(now - 1.0), 0);
+        assert_eq!(clock.time().fixed_ticks(), ticks);
+    }
+
+    #[test]
+    fn smooths_fps() {
+        let mut clock = clock(5);
+        assert_eq!(clock.time().fps(), 0.0);
+        clock.tick(0.0);
+        clock.tick(0.5);
+        assert_eq!(clock.time().fps(), 2.0);
+
+        // a single fast frame moves the average by `FPS_SMOOTHING` only
+        clock.tick(0.6);
+        let expected = 1.0 / (0.5 + (0.1 - 0.5) * FPS_SMOOTHING);
+        assert!((clock.time().fps() as f64 - expected).abs() < 1e-4);
+
+        for i in 1..200 {
+            clock.tick(0.6 + i as f64 * 0.1);
+        }
+        assert!((clock.time().fps() - 10.0).abs() < 1e-3);
+    }
+}